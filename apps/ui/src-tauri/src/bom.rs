@@ -0,0 +1,1132 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use crate::data::{
+    load_assemblies, load_bom_items, load_data_dir_as_of, load_parts, read_csv_if_exists, write_bom_items, write_csv, BomItem, StockRow,
+};
+use crate::substitutes::check_line_group;
+use crate::uom::UomTable;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionStatus {
+    Draft,
+    Released,
+    Obsolete,
+}
+
+/// One row of `bom_revisions.csv`. BOM lines tagged with `revision` belong to
+/// that revision of their parent; untagged lines are the assembly's base BOM,
+/// used whenever no revision is effective.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BomRevision {
+    pub assembly_sku: String,
+    pub revision: String,
+    /// Inclusive YYYY-MM-DD; open-ended when empty
+    #[serde(default)]
+    pub effective_from: Option<String>,
+    /// Exclusive YYYY-MM-DD; open-ended when empty
+    #[serde(default)]
+    pub effective_to: Option<String>,
+    pub status: RevisionStatus,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+impl BomRevision {
+    /// Whether this revision governs builds on `date`. Drafts never do;
+    /// obsolete revisions only within a closed effectivity window.
+    pub fn is_effective_on(&self, date: NaiveDate) -> Result<bool> {
+        let from = parse_effectivity(&self.effective_from)?;
+        let to = parse_effectivity(&self.effective_to)?;
+        let in_window = from.map(|f| f <= date).unwrap_or(true) && to.map(|t| date < t).unwrap_or(true);
+        Ok(in_window && match self.status {
+            RevisionStatus::Draft => false,
+            RevisionStatus::Released => true,
+            RevisionStatus::Obsolete => to.is_some(),
+        })
+    }
+}
+
+fn parse_effectivity(date: &Option<String>) -> Result<Option<NaiveDate>> {
+    match date.as_deref().map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(d) => NaiveDate::parse_from_str(d, "%Y-%m-%d")
+            .map(Some)
+            .with_context(|| format!("Invalid effectivity date '{}', expected YYYY-MM-DD", d)),
+    }
+}
+
+pub fn load_bom_revisions(data_dir: &Path) -> Result<Vec<BomRevision>> {
+    read_csv_if_exists::<BomRevision>(&data_dir.join("bom_revisions.csv"))
+        .context("Reading bom_revisions.csv")
+}
+
+/// Revisions whose effectivity dates parse, and a warning for each one that
+/// does not. Views built from the rest stay usable around a single bad row.
+pub fn usable_revisions(revisions: Vec<BomRevision>) -> (Vec<BomRevision>, Vec<String>) {
+    let mut warnings = Vec::new();
+    let usable = revisions.into_iter()
+        .filter(|r| {
+            match parse_effectivity(&r.effective_from).and(parse_effectivity(&r.effective_to)) {
+                Ok(_) => true,
+                Err(e) => {
+                    warnings.push(format!("Skipped revision {} of {}: {:#}", r.revision, r.assembly_sku, e));
+                    false
+                },
+            }
+        })
+        .collect();
+    (usable, warnings)
+}
+
+fn write_bom_revisions(data_dir: &Path, revisions: &[BomRevision]) -> Result<()> {
+    write_csv(&data_dir.join("bom_revisions.csv"), revisions)
+        .context("Writing bom_revisions.csv")
+}
+
+/// Insert a revision, or update the one with the same assembly and code.
+/// Released revisions cannot return to draft and obsolete ones are frozen.
+pub fn save_bom_revision(data_dir: &Path, revision: BomRevision) -> Result<Vec<BomRevision>> {
+    if revision.revision.trim().is_empty() {
+        bail!("Revision code is required");
+    }
+    if !load_assemblies(data_dir)?.iter().any(|a| a.assembly_sku == revision.assembly_sku) {
+        bail!("Unknown assembly: {}", revision.assembly_sku);
+    }
+    let from = parse_effectivity(&revision.effective_from)?;
+    let to = parse_effectivity(&revision.effective_to)?;
+    if let (Some(from), Some(to)) = (from, to) {
+        if to <= from {
+            bail!("Revision {} must end after it starts", revision.revision);
+        }
+    }
+
+    let mut revisions = load_bom_revisions(data_dir)?;
+    match revisions.iter_mut()
+        .find(|r| r.assembly_sku == revision.assembly_sku && r.revision == revision.revision)
+    {
+        Some(existing) => {
+            match (existing.status, revision.status) {
+                (RevisionStatus::Obsolete, _) => bail!("Revision {} is obsolete", existing.revision),
+                (RevisionStatus::Released, RevisionStatus::Draft) => {
+                    bail!("Revision {} is released and cannot return to draft", existing.revision)
+                },
+                _ => {},
+            }
+            *existing = revision;
+        },
+        None => revisions.push(revision),
+    }
+    write_bom_revisions(data_dir, &revisions)?;
+    Ok(revisions)
+}
+
+/// Release a draft revision effective from `effective_from` (today when not
+/// given). Released revisions of the same assembly that are still open-ended
+/// end where the new one starts, so history keeps its meaning.
+pub fn release_bom_revision(
+    data_dir: &Path,
+    assembly_sku: &str,
+    revision: &str,
+    effective_from: Option<String>,
+) -> Result<Vec<BomRevision>> {
+    let effective_from = effective_from
+        .filter(|d| !d.trim().is_empty())
+        .unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+    let start = parse_effectivity(&Some(effective_from.clone()))?
+        .ok_or_else(|| anyhow!("Effective date is required"))?;
+
+    let mut revisions = load_bom_revisions(data_dir)?;
+    let target = revisions.iter()
+        .position(|r| r.assembly_sku == assembly_sku && r.revision == revision)
+        .ok_or_else(|| anyhow!("Revision {} of {} not found", revision, assembly_sku))?;
+    if revisions[target].status != RevisionStatus::Draft {
+        bail!("Only draft revisions can be released; {} is {:?}", revision, revisions[target].status);
+    }
+    if !load_bom_items(data_dir)?.iter()
+        .any(|b| b.parent_assembly_sku == assembly_sku && b.revision.as_deref() == Some(revision))
+    {
+        bail!("Revision {} of {} has no BOM lines", revision, assembly_sku);
+    }
+
+    for (idx, other) in revisions.iter_mut().enumerate() {
+        if idx == target || other.assembly_sku != assembly_sku || other.status != RevisionStatus::Released {
+            continue;
+        }
+        let other_from = parse_effectivity(&other.effective_from)?;
+        let other_to = parse_effectivity(&other.effective_to)?;
+        if other_from.map(|f| f < start).unwrap_or(true) && other_to.map(|t| t > start).unwrap_or(true) {
+            other.effective_to = Some(effective_from.clone());
+        }
+    }
+    let released = &mut revisions[target];
+    released.status = RevisionStatus::Released;
+    released.effective_from = Some(effective_from);
+    write_bom_revisions(data_dir, &revisions)?;
+    Ok(revisions)
+}
+
+/// The revision of `assembly_sku` effective on `date`. When windows overlap
+/// the one that became effective most recently wins.
+pub fn effective_revision<'a>(
+    revisions: &'a [BomRevision],
+    assembly_sku: &str,
+    date: NaiveDate,
+) -> Result<Option<&'a BomRevision>> {
+    let mut best: Option<&BomRevision> = None;
+    for rev in revisions.iter().filter(|r| r.assembly_sku == assembly_sku) {
+        if !rev.is_effective_on(date)? {
+            continue;
+        }
+        let newer = match best {
+            Some(b) => parse_effectivity(&rev.effective_from)? >= parse_effectivity(&b.effective_from)?,
+            None => true,
+        };
+        if newer {
+            best = Some(rev);
+        }
+    }
+    Ok(best)
+}
+
+/// Select the BOM lines in force on `date`: for each parent, the lines of its
+/// effective revision, or its untagged base lines when no revision is
+/// effective or the effective one has no lines of its own.
+pub fn effective_bom(bom_items: &[BomItem], revisions: &[BomRevision], date: NaiveDate) -> Result<Vec<BomItem>> {
+    let tagged: HashSet<(&str, &str)> = bom_items.iter()
+        .filter_map(|b| b.revision.as_deref().map(|r| (b.parent_assembly_sku.as_str(), r)))
+        .collect();
+    let mut chosen: HashMap<&str, Option<&str>> = HashMap::new();
+    for item in bom_items {
+        let parent = item.parent_assembly_sku.as_str();
+        if !chosen.contains_key(parent) {
+            let rev = effective_revision(revisions, parent, date)?
+                .map(|r| r.revision.as_str())
+                .filter(|r| tagged.contains(&(parent, *r)));
+            chosen.insert(parent, rev);
+        }
+    }
+    Ok(bom_items.iter()
+        .filter(|item| chosen[item.parent_assembly_sku.as_str()] == item.revision.as_deref())
+        .cloned()
+        .collect())
+}
+
+/// Build a lookup map from parent assembly SKU to its BOM rows.
+pub fn index_bom_by_parent(bom_items: &[BomItem]) -> HashMap<&str, Vec<&BomItem>> {
+    let mut map: HashMap<&str, Vec<&BomItem>> = HashMap::new();
+    for item in bom_items {
+        map.entry(item.parent_assembly_sku.as_str()).or_default().push(item);
+    }
+    map
+}
+
+/// Explode an assembly into leaf part requirements per 1 unit, including scrap
+/// and yield. Mirrors `explodeBom` in the UI so both sides agree on quantities.
+pub fn explode_bom(assembly_sku: &str, bom_items: &[BomItem]) -> Result<HashMap<String, f64>> {
+    let by_parent = index_bom_by_parent(bom_items);
+    let mut req = HashMap::new();
+    let mut visiting = HashSet::new();
+    explode_into(assembly_sku, 1.0, &by_parent, &mut visiting, &mut req)?;
+    Ok(req)
+}
+
+/// Units of an assembly and of each sub-assembly under it, phantoms included,
+/// made per unit of the assembly, with scrap and yield as in `explode_bom`.
+pub fn explode_assemblies(assembly_sku: &str, bom_items: &[BomItem]) -> Result<HashMap<String, f64>> {
+    fn walk<'a>(
+        sku: &'a str,
+        multiplier: f64,
+        by_parent: &HashMap<&'a str, Vec<&'a BomItem>>,
+        visiting: &mut HashSet<&'a str>,
+        out: &mut HashMap<String, f64>,
+    ) -> Result<()> {
+        *out.entry(sku.to_string()).or_insert(0.0) += multiplier;
+        if !visiting.insert(sku) {
+            bail!("Circular BOM detected at {}", sku);
+        }
+        for item in by_parent.get(sku).into_iter().flatten() {
+            let component = item.component_sku.as_str();
+            if by_parent.contains_key(component) {
+                walk(component, multiplier * effective_qty(item), by_parent, visiting, out)?;
+            }
+        }
+        visiting.remove(sku);
+        Ok(())
+    }
+
+    let by_parent = index_bom_by_parent(bom_items);
+    let mut out = HashMap::new();
+    walk(assembly_sku, 1.0, &by_parent, &mut HashSet::new(), &mut out)?;
+    Ok(out)
+}
+
+/// Stock available to build with per SKU: on hand less reserved, never negative.
+pub fn available_stock(stock: &[StockRow]) -> HashMap<String, f64> {
+    stock.iter()
+        .map(|s| (s.sku.clone(), (s.on_hand_qty - s.reserved_qty).max(0.0)))
+        .collect()
+}
+
+/// Whole units buildable from `available`. Mirrors `computeMaxBuildable` in the UI.
+pub fn max_buildable(req_per_unit: &HashMap<String, f64>, available: &HashMap<String, f64>) -> f64 {
+    req_per_unit.iter()
+        .filter(|(_, req)| **req > 0.0)
+        .map(|(sku, req)| (available.get(sku).copied().unwrap_or(0.0) / req).floor())
+        .reduce(f64::min)
+        .unwrap_or(0.0)
+}
+
+/// Every assembly that uses `sku`, directly or through sub-assemblies.
+pub fn ancestors(sku: &str, bom_items: &[BomItem]) -> BTreeSet<String> {
+    let mut found = BTreeSet::new();
+    let mut queue = vec![sku.to_string()];
+    while let Some(current) = queue.pop() {
+        for item in bom_items.iter().filter(|b| b.component_sku == current) {
+            if found.insert(item.parent_assembly_sku.clone()) {
+                queue.push(item.parent_assembly_sku.clone());
+            }
+        }
+    }
+    found
+}
+
+/// One route from a top-level assembly down to the SKU being traced.
+#[derive(Debug, serde::Serialize)]
+pub struct WhereUsedPath {
+    /// Top-level assembly first, traced SKU last
+    pub path: Vec<String>,
+    pub qty_per_unit: f64,
+}
+
+/// A top-level assembly that uses a SKU, with the effective quantity per unit
+/// summed over every path.
+#[derive(Debug, serde::Serialize)]
+pub struct WhereUsed {
+    pub assembly_sku: String,
+    pub qty_per_unit: f64,
+    pub paths: Vec<WhereUsedPath>,
+}
+
+/// Walk the BOM upward from `sku` through sub-assemblies and phantoms to every
+/// top-level assembly (one no other BOM line uses), with quantities including
+/// scrap and yield as `explode_bom` computes them.
+pub fn where_used(sku: &str, bom_items: &[BomItem]) -> Result<Vec<WhereUsed>> {
+    let mut by_component: HashMap<&str, Vec<&BomItem>> = HashMap::new();
+    for item in bom_items {
+        by_component.entry(item.component_sku.as_str()).or_default().push(item);
+    }
+
+    let mut paths = Vec::new();
+    let mut trail = vec![sku];
+    trace_up(sku, 1.0, &by_component, &mut trail, &mut paths)?;
+
+    let mut by_top: BTreeMap<String, WhereUsed> = BTreeMap::new();
+    for path in paths {
+        let top = by_top.entry(path.path[0].clone()).or_insert_with(|| WhereUsed {
+            assembly_sku: path.path[0].clone(),
+            qty_per_unit: 0.0,
+            paths: Vec::new(),
+        });
+        top.qty_per_unit += path.qty_per_unit;
+        top.paths.push(path);
+    }
+    Ok(by_top.into_values().collect())
+}
+
+fn trace_up<'a>(
+    sku: &'a str,
+    qty: f64,
+    by_component: &HashMap<&'a str, Vec<&'a BomItem>>,
+    trail: &mut Vec<&'a str>,
+    paths: &mut Vec<WhereUsedPath>,
+) -> Result<()> {
+    let Some(parents) = by_component.get(sku) else {
+        if trail.len() > 1 {
+            paths.push(WhereUsedPath {
+                path: trail.iter().rev().map(|s| s.to_string()).collect(),
+                qty_per_unit: qty,
+            });
+        }
+        return Ok(());
+    };
+    for item in parents {
+        let parent = item.parent_assembly_sku.as_str();
+        if trail.contains(&parent) {
+            bail!("Circular BOM detected at {}", parent);
+        }
+        trail.push(parent);
+        trace_up(parent, qty * effective_qty(item), by_component, trail, paths)?;
+        trail.pop();
+    }
+    Ok(())
+}
+
+/// One line of an indented BOM. Level 1 lines are the top assembly's own
+/// components; `extended_qty` is the cumulative quantity for the requested
+/// number of top-level units, including scrap and yield at every level.
+#[derive(Debug, serde::Serialize)]
+pub struct BomTreeRow {
+    pub level: usize,
+    pub parent_assembly_sku: String,
+    pub component_sku: String,
+    pub name: String,
+    pub qty_per: f64,
+    pub scrap_rate: f64,
+    pub yield_pct: f64,
+    pub is_phantom: bool,
+    pub extended_qty: f64,
+}
+
+/// Indented BOM for `quantity` units of an assembly, depth first in file order.
+pub fn bom_tree(
+    assembly_sku: &str,
+    quantity: f64,
+    bom_items: &[BomItem],
+    names: &HashMap<String, String>,
+) -> Result<Vec<BomTreeRow>> {
+    let by_parent = index_bom_by_parent(bom_items);
+    let mut rows = Vec::new();
+    let mut visiting = HashSet::new();
+    tree_into(assembly_sku, 1, quantity, &by_parent, names, &mut visiting, &mut rows)?;
+    Ok(rows)
+}
+
+fn tree_into<'a>(
+    sku: &'a str,
+    level: usize,
+    multiplier: f64,
+    by_parent: &HashMap<&'a str, Vec<&'a BomItem>>,
+    names: &HashMap<String, String>,
+    visiting: &mut HashSet<&'a str>,
+    rows: &mut Vec<BomTreeRow>,
+) -> Result<()> {
+    let Some(children) = by_parent.get(sku) else {
+        return Ok(());
+    };
+    if !visiting.insert(sku) {
+        bail!("Circular BOM detected at {}", sku);
+    }
+    for item in children {
+        let extended_qty = multiplier * effective_qty(item);
+        rows.push(BomTreeRow {
+            level,
+            parent_assembly_sku: item.parent_assembly_sku.clone(),
+            component_sku: item.component_sku.clone(),
+            name: names.get(&item.component_sku).cloned().unwrap_or_default(),
+            qty_per: item.qty_per,
+            scrap_rate: item.scrap_rate,
+            yield_pct: item.yield_pct,
+            is_phantom: item.is_phantom,
+            extended_qty,
+        });
+        tree_into(&item.component_sku, level + 1, extended_qty, by_parent, names, visiting, rows)?;
+    }
+    visiting.remove(sku);
+    Ok(())
+}
+
+/// Indented BOM with component names from `parts.csv` and `assemblies.csv`.
+pub fn load_bom_tree(data_dir: &Path, assembly_sku: &str, quantity: f64, bom_date: NaiveDate) -> Result<Vec<BomTreeRow>> {
+    let data = load_data_dir_as_of(data_dir, bom_date)?;
+    data.check_complete(assembly_sku)?;
+    let names = data.parts.iter()
+        .map(|p| (p.part_sku.clone(), p.name.clone()))
+        .chain(data.assemblies.iter().map(|a| (a.assembly_sku.clone(), a.name.clone())))
+        .collect();
+    bom_tree(assembly_sku, quantity, &data.bom_items, &names)
+}
+
+/// Effective quantity of one BOM line per parent unit after scrap and yield.
+pub fn effective_qty(item: &BomItem) -> f64 {
+    item.qty_per * (1.0 + item.scrap_rate) / item.yield_pct.max(0.01)
+}
+
+fn explode_into<'a>(
+    sku: &'a str,
+    multiplier: f64,
+    by_parent: &HashMap<&'a str, Vec<&'a BomItem>>,
+    visiting: &mut HashSet<&'a str>,
+    req: &mut HashMap<String, f64>,
+) -> Result<()> {
+    let children = match by_parent.get(sku) {
+        Some(children) => children,
+        None => {
+            *req.entry(sku.to_string()).or_insert(0.0) += multiplier;
+            return Ok(());
+        }
+    };
+
+    if !visiting.insert(sku) {
+        bail!("Circular BOM detected at {}", sku);
+    }
+
+    for item in children {
+        let qty = multiplier * effective_qty(item);
+        let component = item.component_sku.as_str();
+        if item.is_phantom || by_parent.contains_key(component) {
+            explode_into(component, qty, by_parent, visiting, req)?;
+        } else {
+            *req.entry(component.to_string()).or_insert(0.0) += qty;
+        }
+    }
+
+    visiting.remove(sku);
+    Ok(())
+}
+
+/// Assembly and part SKUs known to the data directory.
+pub struct KnownSkus {
+    pub assemblies: HashSet<String>,
+    pub parts: HashSet<String>,
+    pub inactive: HashSet<String>,
+}
+
+impl KnownSkus {
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        let assemblies = load_assemblies(data_dir)?;
+        let parts = load_parts(data_dir)?;
+        let inactive = assemblies.iter().filter(|a| !a.active).map(|a| a.assembly_sku.clone())
+            .chain(parts.iter().filter(|p| !p.active).map(|p| p.part_sku.clone()))
+            .collect();
+        Ok(KnownSkus {
+            assemblies: assemblies.into_iter().map(|a| a.assembly_sku).collect(),
+            parts: parts.into_iter().map(|p| p.part_sku).collect(),
+            inactive,
+        })
+    }
+
+    pub fn contains(&self, sku: &str) -> bool {
+        self.assemblies.contains(sku) || self.parts.contains(sku)
+    }
+}
+
+/// Check a single BOM line: known parent assembly and component, and sane
+/// quantity, scrap and yield.
+pub fn check_bom_line(item: &BomItem, known: &KnownSkus) -> Result<()> {
+    let parent = item.parent_assembly_sku.as_str();
+    let component = item.component_sku.as_str();
+    if !known.assemblies.contains(parent) {
+        bail!("Unknown assembly: {}", parent);
+    }
+    if !known.contains(component) {
+        bail!("Unknown component: {}", component);
+    }
+    if parent == component {
+        bail!("{} cannot contain itself", parent);
+    }
+    if let Some(sku) = [parent, component].into_iter().find(|sku| known.inactive.contains(*sku)) {
+        bail!("{} is inactive", sku);
+    }
+    if !(item.qty_per.is_finite() && item.qty_per > 0.0) {
+        bail!("Quantity per for {} on {} must be positive", component, parent);
+    }
+    if !(item.scrap_rate.is_finite() && item.scrap_rate >= 0.0 && item.scrap_rate < 1.0) {
+        bail!("Scrap rate for {} on {} must be at least 0 and below 1", component, parent);
+    }
+    if !(item.yield_pct.is_finite() && item.yield_pct > 0.0 && item.yield_pct <= 1.0) {
+        bail!("Yield for {} on {} must be greater than 0 and at most 1", component, parent);
+    }
+    Ok(())
+}
+
+/// Fail if `sku` reaches itself through any BOM line, whatever its revision.
+fn check_no_cycles(bom_items: &[BomItem], sku: &str) -> Result<()> {
+    explode_bom(sku, bom_items).map(|_| ())
+}
+
+/// Lines of released or obsolete revisions describe builds already made;
+/// they change through an ECO, not by editing.
+fn check_editable(data_dir: &Path, parent: &str, revision: Option<&str>) -> Result<()> {
+    let Some(revision) = revision else {
+        return Ok(());
+    };
+    match load_bom_revisions(data_dir)?.iter().find(|r| r.assembly_sku == parent && r.revision == revision) {
+        Some(r) if r.status != RevisionStatus::Draft => {
+            bail!("Revision {} of {} is {:?}; change it through an ECO", revision, parent, r.status)
+        },
+        Some(_) => Ok(()),
+        None => bail!("Revision {} of {} does not exist", revision, parent),
+    }
+}
+
+fn same_line(item: &BomItem, parent: &str, component: &str, revision: Option<&str>) -> bool {
+    item.parent_assembly_sku == parent && item.component_sku == component && item.revision.as_deref() == revision
+}
+
+/// Add a BOM line to the base BOM or a draft revision.
+pub fn add_bom_item(data_dir: &Path, item: BomItem) -> Result<Vec<BomItem>> {
+    let item = BomItem {
+        revision: item.revision.filter(|r| !r.trim().is_empty()),
+        substitute_group: item.substitute_group.map(|g| g.trim().to_string()).filter(|g| !g.is_empty()),
+        ..item
+    };
+    check_bom_line(&item, &KnownSkus::load(data_dir)?)?;
+    UomTable::load(data_dir)?.to_stock_units(vec![item.clone()])?;
+    check_line_group(data_dir, &item)?;
+    check_editable(data_dir, &item.parent_assembly_sku, item.revision.as_deref())?;
+
+    let mut bom_items = load_bom_items(data_dir)?;
+    if bom_items.iter().any(|b| same_line(b, &item.parent_assembly_sku, &item.component_sku, item.revision.as_deref())) {
+        bail!("{} is already on {}; update the existing line", item.component_sku, item.parent_assembly_sku);
+    }
+    let parent = item.parent_assembly_sku.clone();
+    bom_items.push(item);
+    check_no_cycles(&bom_items, &parent)?;
+    write_bom_items(data_dir, &bom_items)?;
+    Ok(bom_items)
+}
+
+/// Replace the quantity, scrap, yield, phantom flag, unit and substitute
+/// group of an existing line, matched by parent, component and revision.
+pub fn update_bom_item(data_dir: &Path, item: BomItem) -> Result<Vec<BomItem>> {
+    let item = BomItem {
+        revision: item.revision.filter(|r| !r.trim().is_empty()),
+        substitute_group: item.substitute_group.map(|g| g.trim().to_string()).filter(|g| !g.is_empty()),
+        ..item
+    };
+    check_bom_line(&item, &KnownSkus::load(data_dir)?)?;
+    UomTable::load(data_dir)?.to_stock_units(vec![item.clone()])?;
+    check_line_group(data_dir, &item)?;
+    check_editable(data_dir, &item.parent_assembly_sku, item.revision.as_deref())?;
+
+    let mut bom_items = load_bom_items(data_dir)?;
+    let parent = item.parent_assembly_sku.clone();
+    let existing = bom_items.iter_mut()
+        .find(|b| same_line(b, &item.parent_assembly_sku, &item.component_sku, item.revision.as_deref()))
+        .ok_or_else(|| anyhow!("{} is not on {}", item.component_sku, item.parent_assembly_sku))?;
+    *existing = item;
+    check_no_cycles(&bom_items, &parent)?;
+    write_bom_items(data_dir, &bom_items)?;
+    Ok(bom_items)
+}
+
+/// Remove a line from the base BOM or a draft revision.
+pub fn remove_bom_item(
+    data_dir: &Path,
+    parent_assembly_sku: &str,
+    component_sku: &str,
+    revision: Option<&str>,
+) -> Result<Vec<BomItem>> {
+    let revision = revision.filter(|r| !r.trim().is_empty());
+    check_editable(data_dir, parent_assembly_sku, revision)?;
+
+    let mut bom_items = load_bom_items(data_dir)?;
+    let before = bom_items.len();
+    bom_items.retain(|b| !same_line(b, parent_assembly_sku, component_sku, revision));
+    if bom_items.len() == before {
+        bail!("{} is not on {}", component_sku, parent_assembly_sku);
+    }
+    write_bom_items(data_dir, &bom_items)?;
+    Ok(bom_items)
+}
+
+/// One side of a BOM comparison: an assembly as of a date (today by default),
+/// optionally pinned to one of its revisions, draft or otherwise.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BomSide {
+    pub assembly_sku: String,
+    #[serde(default)]
+    pub revision: Option<String>,
+    #[serde(default)]
+    pub as_of: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// Difference in exploded quantity per unit of one leaf part.
+#[derive(Debug, serde::Serialize)]
+pub struct LeafDiff {
+    pub sku: String,
+    pub kind: DiffKind,
+    pub qty_a: f64,
+    pub qty_b: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct LineValues {
+    pub qty_per: f64,
+    pub scrap_rate: f64,
+    pub yield_pct: f64,
+    pub is_phantom: bool,
+}
+
+/// Difference in one BOM line, identified by its path of components below
+/// the top assembly so different assemblies can be lined up.
+#[derive(Debug, serde::Serialize)]
+pub struct StructureDiff {
+    pub path: Vec<String>,
+    pub kind: DiffKind,
+    pub a: Option<LineValues>,
+    pub b: Option<LineValues>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct BomComparison {
+    pub a: BomSide,
+    pub b: BomSide,
+    pub leaf: Vec<LeafDiff>,
+    pub structure: Vec<StructureDiff>,
+    pub unchanged_leaf_count: usize,
+}
+
+/// BOM lines for one comparison side, with `as_of` and `revision` filled in
+/// to show what was actually compared.
+fn resolve_side(data_dir: &Path, side: &BomSide) -> Result<(BomSide, Vec<BomItem>)> {
+    let date = match parse_effectivity(&side.as_of)? {
+        Some(date) => date,
+        None => chrono::Local::now().date_naive(),
+    };
+    let raw = UomTable::load(data_dir)?.to_stock_units(load_bom_items(data_dir)?)?;
+    let revisions = load_bom_revisions(data_dir)?;
+    let (usable, _) = usable_revisions(revisions.clone());
+    let mut lines = effective_bom(&raw, &usable, date)?;
+
+    let sku = side.assembly_sku.as_str();
+    let revision = match side.revision.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+        Some(code) => {
+            if !revisions.iter().any(|r| r.assembly_sku == sku && r.revision == code) {
+                bail!("Revision {} of {} does not exist", code, sku);
+            }
+            let pinned: Vec<BomItem> = raw.iter()
+                .filter(|b| b.parent_assembly_sku == sku && b.revision.as_deref() == Some(code))
+                .cloned()
+                .collect();
+            // A revision without lines of its own uses the base BOM
+            if !pinned.is_empty() {
+                lines.retain(|b| b.parent_assembly_sku != sku);
+                lines.extend(pinned);
+            }
+            Some(code.to_string())
+        },
+        None => effective_revision(&usable, sku, date)?.map(|r| r.revision.clone()),
+    };
+    if !lines.iter().any(|b| b.parent_assembly_sku == sku) {
+        bail!("{} has no BOM lines", sku);
+    }
+
+    let resolved = BomSide {
+        assembly_sku: sku.to_string(),
+        revision,
+        as_of: Some(date.format("%Y-%m-%d").to_string()),
+    };
+    Ok((resolved, lines))
+}
+
+/// Every BOM line below an assembly keyed by its component path. A parent
+/// listing the same component more than once gets one entry with the
+/// quantities summed, so no line drops out of the comparison.
+fn lines_by_path(assembly_sku: &str, bom_items: &[BomItem]) -> Result<BTreeMap<Vec<String>, LineValues>> {
+    fn walk<'a>(
+        sku: &'a str,
+        prefix: &mut Vec<String>,
+        by_parent: &HashMap<&'a str, Vec<&'a BomItem>>,
+        visiting: &mut HashSet<&'a str>,
+        out: &mut BTreeMap<Vec<String>, LineValues>,
+    ) -> Result<()> {
+        let Some(children) = by_parent.get(sku) else {
+            return Ok(());
+        };
+        if !visiting.insert(sku) {
+            bail!("Circular BOM detected at {}", sku);
+        }
+        for item in children {
+            prefix.push(item.component_sku.clone());
+            if let Some(values) = out.get_mut(prefix.as_slice()) {
+                values.qty_per += item.qty_per;
+                prefix.pop();
+                continue;
+            }
+            out.insert(prefix.clone(), LineValues {
+                qty_per: item.qty_per,
+                scrap_rate: item.scrap_rate,
+                yield_pct: item.yield_pct,
+                is_phantom: item.is_phantom,
+            });
+            walk(&item.component_sku, prefix, by_parent, visiting, out)?;
+            prefix.pop();
+        }
+        visiting.remove(sku);
+        Ok(())
+    }
+
+    let by_parent = index_bom_by_parent(bom_items);
+    let mut out = BTreeMap::new();
+    walk(assembly_sku, &mut Vec::new(), &by_parent, &mut HashSet::new(), &mut out)?;
+    Ok(out)
+}
+
+/// Compare two assemblies, or two revisions or dates of one assembly: leaf
+/// parts whose exploded quantity per unit differs, and BOM lines added,
+/// removed or changed anywhere in the structure.
+pub fn compare_bom(data_dir: &Path, a: &BomSide, b: &BomSide) -> Result<BomComparison> {
+    let (a, lines_a) = resolve_side(data_dir, a)?;
+    let (b, lines_b) = resolve_side(data_dir, b)?;
+
+    let leaf_a = explode_bom(&a.assembly_sku, &lines_a)?;
+    let leaf_b = explode_bom(&b.assembly_sku, &lines_b)?;
+    let skus: BTreeSet<&String> = leaf_a.keys().chain(leaf_b.keys()).collect();
+    let mut leaf = Vec::new();
+    let mut unchanged_leaf_count = 0;
+    for sku in skus {
+        let (qty_a, qty_b) = (leaf_a.get(sku).copied(), leaf_b.get(sku).copied());
+        let kind = match (qty_a, qty_b) {
+            (None, Some(_)) => DiffKind::Added,
+            (Some(_), None) => DiffKind::Removed,
+            (Some(x), Some(y)) if (x - y).abs() > 1e-9 => DiffKind::Changed,
+            _ => {
+                unchanged_leaf_count += 1;
+                continue;
+            },
+        };
+        leaf.push(LeafDiff {
+            sku: sku.clone(),
+            kind,
+            qty_a: qty_a.unwrap_or(0.0),
+            qty_b: qty_b.unwrap_or(0.0),
+        });
+    }
+
+    let mut paths_a = lines_by_path(&a.assembly_sku, &lines_a)?;
+    let paths_b = lines_by_path(&b.assembly_sku, &lines_b)?;
+    let mut structure = Vec::new();
+    for (path, values_b) in paths_b {
+        let kind = match paths_a.remove(&path) {
+            None => Some((DiffKind::Added, None)),
+            Some(values_a) if values_a != values_b => Some((DiffKind::Changed, Some(values_a))),
+            Some(_) => None,
+        };
+        if let Some((kind, values_a)) = kind {
+            structure.push(StructureDiff { path, kind, a: values_a, b: Some(values_b) });
+        }
+    }
+    structure.extend(paths_a.into_iter().map(|(path, values_a)| StructureDiff {
+        path,
+        kind: DiffKind::Removed,
+        a: Some(values_a),
+        b: None,
+    }));
+    structure.sort_by(|x, y| x.path.cmp(&y.path));
+
+    Ok(BomComparison { a, b, leaf, structure, unchanged_leaf_count })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    const BOM_HEADER: &str = "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom,revision,uom\n";
+
+    fn fixture() -> TestDir {
+        let dir = TestDir::new();
+        dir.write("assemblies.csv", "assembly_sku,name,uom,active\nA,Assembly,ea,true\nB,Other,ea,true\n");
+        dir.write("parts.csv", "part_sku,name,uom\nP1,Part one,ea\nP2,Part two,ea\n");
+        dir.write("bom_items.csv", &format!(
+            "{}A,P1,2,0,1,false,,\nA,P2,1,0,1,false,R1,\nB,P1,1,0,1,false,,\n",
+            BOM_HEADER,
+        ));
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,10,0\nP2,4,0\n");
+        dir
+    }
+
+    fn revision(assembly: &str, code: &str, from: &str, to: &str, status: RevisionStatus) -> BomRevision {
+        BomRevision {
+            assembly_sku: assembly.to_string(),
+            revision: code.to_string(),
+            effective_from: Some(from.to_string()).filter(|d| !d.is_empty()),
+            effective_to: Some(to.to_string()).filter(|d| !d.is_empty()),
+            status,
+            notes: None,
+        }
+    }
+
+    fn date(d: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn effectivity_depends_on_status_and_window() {
+        let released = revision("A", "R1", "2024-01-01", "2024-02-01", RevisionStatus::Released);
+        assert!(!released.is_effective_on(date("2023-12-31")).unwrap());
+        assert!(released.is_effective_on(date("2024-01-01")).unwrap());
+        assert!(!released.is_effective_on(date("2024-02-01")).unwrap());
+
+        let draft = revision("A", "R2", "", "", RevisionStatus::Draft);
+        assert!(!draft.is_effective_on(date("2024-01-15")).unwrap());
+        let open_obsolete = revision("A", "R0", "2023-01-01", "", RevisionStatus::Obsolete);
+        assert!(!open_obsolete.is_effective_on(date("2024-01-15")).unwrap());
+    }
+
+    #[test]
+    fn most_recently_effective_revision_wins() {
+        let revisions = vec![
+            revision("A", "R1", "2024-01-01", "", RevisionStatus::Released),
+            revision("A", "R2", "2024-03-01", "", RevisionStatus::Released),
+        ];
+        let on = |d| effective_revision(&revisions, "A", date(d)).unwrap().map(|r| r.revision.clone());
+        assert_eq!(on("2023-06-01"), None);
+        assert_eq!(on("2024-02-01").as_deref(), Some("R1"));
+        assert_eq!(on("2024-04-01").as_deref(), Some("R2"));
+    }
+
+    #[test]
+    fn effective_bom_falls_back_to_base_lines() {
+        let dir = fixture();
+        let items = load_bom_items(dir.path()).unwrap();
+        let revisions = vec![revision("A", "R1", "2024-01-01", "", RevisionStatus::Released)];
+
+        let before = effective_bom(&items, &revisions, date("2023-12-31")).unwrap();
+        let a_before: Vec<&str> = before.iter().filter(|b| b.parent_assembly_sku == "A").map(|b| b.component_sku.as_str()).collect();
+        assert_eq!(a_before, vec!["P1"]);
+
+        let after = effective_bom(&items, &revisions, date("2024-01-01")).unwrap();
+        let a_after: Vec<&str> = after.iter().filter(|b| b.parent_assembly_sku == "A").map(|b| b.component_sku.as_str()).collect();
+        assert_eq!(a_after, vec!["P2"]);
+        assert!(after.iter().any(|b| b.parent_assembly_sku == "B"));
+    }
+
+    #[test]
+    fn release_ends_open_revisions_and_requires_lines() {
+        let dir = fixture();
+        save_bom_revision(dir.path(), revision("A", "R0", "2023-01-01", "", RevisionStatus::Released)).unwrap();
+        save_bom_revision(dir.path(), revision("A", "R1", "", "", RevisionStatus::Draft)).unwrap();
+        save_bom_revision(dir.path(), revision("A", "R9", "", "", RevisionStatus::Draft)).unwrap();
+
+        let revisions = release_bom_revision(dir.path(), "A", "R1", Some("2024-05-01".to_string())).unwrap();
+        let r0 = revisions.iter().find(|r| r.revision == "R0").unwrap();
+        assert_eq!(r0.effective_to.as_deref(), Some("2024-05-01"));
+        let r1 = revisions.iter().find(|r| r.revision == "R1").unwrap();
+        assert_eq!(r1.status, RevisionStatus::Released);
+
+        assert!(release_bom_revision(dir.path(), "A", "R9", None).is_err());
+        let err = save_bom_revision(dir.path(), revision("A", "R1", "", "", RevisionStatus::Draft)).unwrap_err();
+        assert!(err.to_string().contains("cannot return to draft"));
+    }
+
+    #[test]
+    fn bad_effectivity_date_is_skipped_and_reported() {
+        let dir = fixture();
+        dir.write("bom_revisions.csv", "assembly_sku,revision,effective_from,effective_to,status,notes\n\
+            A,R1,2024-01-01,,released,\nB,R1,01/02/2024,,released,\n");
+
+        let data = load_data_dir_as_of(dir.path(), date("2024-06-01")).unwrap();
+        assert_eq!(data.bom_revisions.len(), 1);
+        assert_eq!(data.warnings.len(), 1);
+        assert!(data.warnings[0].contains("revision R1 of B"));
+        let a_lines: Vec<&str> = data.bom_items.iter()
+            .filter(|b| b.parent_assembly_sku == "A")
+            .map(|b| b.component_sku.as_str())
+            .collect();
+        assert_eq!(a_lines, vec!["P2"]);
+    }
+
+    #[test]
+    fn corrupt_revisions_file_is_not_overwritten() {
+        let dir = fixture();
+        let corrupt = "assembly_sku,revision,effective_from,effective_to,status,notes\nA,R1,,,released,,extra\n";
+        dir.write("bom_revisions.csv", corrupt);
+
+        assert!(load_bom_revisions(dir.path()).is_err());
+        assert!(save_bom_revision(dir.path(), revision("A", "R2", "", "", RevisionStatus::Draft)).is_err());
+        assert_eq!(dir.read("bom_revisions.csv"), corrupt);
+    }
+
+    fn line(parent: &str, component: &str, qty_per: f64) -> BomItem {
+        BomItem {
+            parent_assembly_sku: parent.to_string(),
+            component_sku: component.to_string(),
+            qty_per,
+            scrap_rate: 0.0,
+            yield_pct: 1.0,
+            is_phantom: false,
+            revision: None,
+            uom: None,
+            substitute_group: None,
+        }
+    }
+
+    #[test]
+    fn bom_lines_are_added_updated_and_removed() {
+        let dir = fixture();
+        let items = add_bom_item(dir.path(), line("B", "P2", 3.0)).unwrap();
+        assert!(items.iter().any(|b| b.parent_assembly_sku == "B" && b.component_sku == "P2"));
+        assert!(add_bom_item(dir.path(), line("B", "P2", 1.0)).is_err());
+
+        let items = update_bom_item(dir.path(), line("B", "P2", 5.0)).unwrap();
+        assert_eq!(items.iter().find(|b| b.parent_assembly_sku == "B" && b.component_sku == "P2").unwrap().qty_per, 5.0);
+
+        remove_bom_item(dir.path(), "B", "P2", None).unwrap();
+        assert!(!load_bom_items(dir.path()).unwrap().iter().any(|b| b.parent_assembly_sku == "B" && b.component_sku == "P2"));
+        assert!(remove_bom_item(dir.path(), "B", "P2", None).is_err());
+    }
+
+    #[test]
+    fn invalid_lines_and_cycles_are_rejected() {
+        let dir = fixture();
+        let before = dir.read("bom_items.csv");
+        assert!(add_bom_item(dir.path(), line("A", "NOPE", 1.0)).is_err());
+        assert!(add_bom_item(dir.path(), line("P1", "P2", 1.0)).is_err());
+        assert!(add_bom_item(dir.path(), line("A", "A", 1.0)).is_err());
+        assert!(add_bom_item(dir.path(), line("B", "P2", 0.0)).is_err());
+        assert!(add_bom_item(dir.path(), BomItem { scrap_rate: 1.0, ..line("B", "P2", 1.0) }).is_err());
+        assert!(add_bom_item(dir.path(), BomItem { yield_pct: 0.0, ..line("B", "P2", 1.0) }).is_err());
+
+        add_bom_item(dir.path(), line("A", "B", 1.0)).unwrap();
+        let err = add_bom_item(dir.path(), line("B", "A", 1.0)).unwrap_err();
+        assert!(err.to_string().contains("Circular"));
+        assert_ne!(dir.read("bom_items.csv"), before);
+        assert!(!dir.read("bom_items.csv").contains("B,A,"));
+    }
+
+    #[test]
+    fn released_revision_lines_are_not_editable() {
+        let dir = fixture();
+        dir.write("bom_revisions.csv", "assembly_sku,revision,effective_from,effective_to,status,notes\n\
+            A,R1,2024-01-01,,released,\nA,R2,,,draft,\n");
+        let err = update_bom_item(dir.path(), BomItem { revision: Some("R1".to_string()), ..line("A", "P2", 2.0) }).unwrap_err();
+        assert!(err.to_string().contains("through an ECO"));
+
+        add_bom_item(dir.path(), BomItem { revision: Some("R2".to_string()), ..line("A", "P1", 1.0) }).unwrap();
+        assert!(add_bom_item(dir.path(), BomItem { revision: Some("R3".to_string()), ..line("A", "P1", 1.0) }).is_err());
+    }
+
+    #[test]
+    fn corrupt_bom_items_file_is_not_overwritten() {
+        let dir = fixture();
+        let corrupt = format!("{}A,P1,two,0,1,false,,\n", BOM_HEADER);
+        dir.write("bom_items.csv", &corrupt);
+        assert!(add_bom_item(dir.path(), line("B", "P2", 1.0)).is_err());
+        assert!(remove_bom_item(dir.path(), "A", "P1", None).is_err());
+        assert_eq!(dir.read("bom_items.csv"), corrupt);
+    }
+
+    fn nested_bom() -> Vec<BomItem> {
+        vec![
+            line("TOP", "SUB", 2.0),
+            line("TOP", "P1", 1.0),
+            BomItem { is_phantom: true, ..line("SUB", "KIT", 1.0) },
+            line("SUB", "P1", 3.0),
+            BomItem { scrap_rate: 0.5, ..line("KIT", "P1", 2.0) },
+            line("OTHER", "SUB", 1.0),
+        ]
+    }
+
+    #[test]
+    fn where_used_sums_every_path_to_each_top_level_assembly() {
+        let used = where_used("P1", &nested_bom()).unwrap();
+        let tops: Vec<&str> = used.iter().map(|u| u.assembly_sku.as_str()).collect();
+        assert_eq!(tops, vec!["OTHER", "TOP"]);
+
+        let top = &used[1];
+        // Direct 1, through SUB 2 x 3, through SUB and KIT 2 x 1 x 2 x 1.5
+        assert!((top.qty_per_unit - 13.0).abs() < 1e-9);
+        assert_eq!(top.paths.len(), 3);
+        assert!(top.paths.iter().any(|p| p.path == vec!["TOP", "SUB", "KIT", "P1"]));
+        assert!((used[0].qty_per_unit - 6.0).abs() < 1e-9);
+        assert!(where_used("TOP", &nested_bom()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn where_used_reports_cycles() {
+        let items = vec![line("A", "B", 1.0), line("B", "A", 1.0), line("B", "P1", 1.0)];
+        assert!(where_used("P1", &items).is_err());
+    }
+
+    #[test]
+    fn bom_tree_is_depth_first_with_cumulative_quantities() {
+        let names = HashMap::from([("SUB".to_string(), "Sub-assembly".to_string())]);
+        let rows = bom_tree("TOP", 2.0, &nested_bom(), &names).unwrap();
+        let outline: Vec<(usize, &str, f64)> = rows.iter()
+            .map(|r| (r.level, r.component_sku.as_str(), r.extended_qty))
+            .collect();
+        assert_eq!(outline, vec![
+            (1, "SUB", 4.0),
+            (2, "KIT", 4.0),
+            (3, "P1", 12.0),
+            (2, "P1", 12.0),
+            (1, "P1", 2.0),
+        ]);
+        assert_eq!(rows[0].name, "Sub-assembly");
+        assert!(rows[1].is_phantom);
+    }
+
+    #[test]
+    fn bom_tree_loads_names_and_reports_cycles() {
+        let dir = fixture();
+        let rows = load_bom_tree(dir.path(), "A", 3.0, date("2024-01-01")).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!((rows[0].name.as_str(), rows[0].extended_qty), ("Part one", 6.0));
+
+        let items = vec![line("A", "B", 1.0), line("B", "A", 1.0)];
+        assert!(bom_tree("A", 1.0, &items, &HashMap::new()).is_err());
+    }
+
+    fn side(assembly: &str, revision: Option<&str>, as_of: &str) -> BomSide {
+        BomSide {
+            assembly_sku: assembly.to_string(),
+            revision: revision.map(str::to_string),
+            as_of: Some(as_of.to_string()),
+        }
+    }
+
+    #[test]
+    fn compare_revisions_of_one_assembly() {
+        let dir = fixture();
+        dir.write("bom_revisions.csv", "assembly_sku,revision,effective_from,effective_to,status,notes\nA,R1,,,draft,\n");
+
+        let cmp = compare_bom(dir.path(), &side("A", None, "2024-01-01"), &side("A", Some("R1"), "2024-01-01")).unwrap();
+        assert_eq!(cmp.a.revision, None);
+        assert_eq!(cmp.b.revision.as_deref(), Some("R1"));
+        let leaf: Vec<(&str, DiffKind)> = cmp.leaf.iter().map(|d| (d.sku.as_str(), d.kind)).collect();
+        assert_eq!(leaf, vec![("P1", DiffKind::Removed), ("P2", DiffKind::Added)]);
+        assert_eq!(cmp.structure.len(), 2);
+        assert_eq!(cmp.unchanged_leaf_count, 0);
+
+        assert!(compare_bom(dir.path(), &side("A", None, "2024-01-01"), &side("A", Some("R7"), "2024-01-01")).is_err());
+    }
+
+    #[test]
+    fn compare_lines_up_different_assemblies_by_path() {
+        let dir = fixture();
+        dir.write("bom_items.csv", &format!(
+            "{}A,P1,2,0,1,false,,\nA,P2,1,0,1,false,,\nB,P1,2,0,1,false,,\nB,P2,3,0,1,false,,\n",
+            BOM_HEADER,
+        ));
+        let cmp = compare_bom(dir.path(), &side("A", None, "2024-01-01"), &side("B", None, "2024-01-01")).unwrap();
+        assert_eq!(cmp.unchanged_leaf_count, 1);
+        assert_eq!(cmp.leaf.len(), 1);
+        assert_eq!((cmp.leaf[0].qty_a, cmp.leaf[0].qty_b), (1.0, 3.0));
+        assert_eq!(cmp.structure.len(), 1);
+        assert_eq!(cmp.structure[0].path, vec!["P2"]);
+        assert_eq!(cmp.structure[0].kind, DiffKind::Changed);
+    }
+
+    #[test]
+    fn compare_sums_a_component_listed_twice() {
+        let dir = fixture();
+        dir.write("bom_items.csv", &format!(
+            "{}A,P1,2,0,1,false,,\nA,P1,1,0,1,false,,\nB,P1,3,0,1,false,,\n",
+            BOM_HEADER,
+        ));
+        let cmp = compare_bom(dir.path(), &side("A", None, "2024-01-01"), &side("B", None, "2024-01-01")).unwrap();
+        assert!(cmp.leaf.is_empty());
+        assert!(cmp.structure.is_empty());
+
+        dir.write("bom_items.csv", &format!("{}A,P1,2,0,1,false,,\nA,P1,1,0,1,false,,\nB,P1,2,0,1,false,,\n", BOM_HEADER));
+        let cmp = compare_bom(dir.path(), &side("A", None, "2024-01-01"), &side("B", None, "2024-01-01")).unwrap();
+        assert_eq!(cmp.structure.len(), 1);
+        assert_eq!(cmp.structure[0].kind, DiffKind::Changed);
+        assert_eq!(cmp.structure[0].a.as_ref().map(|v| v.qty_per), Some(3.0));
+        assert_eq!(cmp.structure[0].b.as_ref().map(|v| v.qty_per), Some(2.0));
+    }
+}
@@ -0,0 +1,356 @@
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::bom::{explode_bom, KnownSkus};
+use crate::data::{load_data_dir_as_of, load_parts, read_csv_if_exists, write_csv, BuildHistoryRecord};
+use crate::history::{filter_history, ExplosionCache, HistoryQuery, SkippedRow};
+use crate::purchasing::load_purchase_orders;
+
+/// Which unit cost drives a rollup. Either falls back to the other for parts
+/// that have only one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostMethod {
+    /// From `part_costs.csv`
+    #[default]
+    Standard,
+    /// Weighted by quantity received across purchase order lines
+    Average,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostSource {
+    Standard,
+    Average,
+    Missing,
+}
+
+/// Standard cost of one SKU in `part_costs.csv`, per stock unit.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct StandardCost {
+    pub sku: String,
+    pub standard_cost: f64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PartCost {
+    pub sku: String,
+    pub standard_cost: Option<f64>,
+    pub average_cost: Option<f64>,
+    /// Quantity behind `average_cost`
+    pub received_qty: f64,
+}
+
+impl PartCost {
+    pub fn unit_cost(&self, method: CostMethod) -> (Option<f64>, CostSource) {
+        let standard = self.standard_cost.map(|c| (Some(c), CostSource::Standard));
+        let average = self.average_cost.map(|c| (Some(c), CostSource::Average));
+        let preferred = match method {
+            CostMethod::Standard => standard.or(average),
+            CostMethod::Average => average.or(standard),
+        };
+        preferred.unwrap_or((None, CostSource::Missing))
+    }
+}
+
+/// Material cost of one leaf part per unit of the assembly.
+#[derive(Debug, serde::Serialize)]
+pub struct CostLine {
+    pub sku: String,
+    /// Including scrap and yield along the BOM path
+    pub qty_per_unit: f64,
+    pub unit_cost: Option<f64>,
+    pub extended_cost: f64,
+    pub source: CostSource,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct CostRollup {
+    pub assembly_sku: String,
+    pub method: CostMethod,
+    pub bom_date: String,
+    /// Per unit of the assembly, excluding parts without a cost
+    pub material_cost: f64,
+    pub lines: Vec<CostLine>,
+    pub missing_costs: Vec<String>,
+}
+
+/// A build history row with the material it consumed at the chosen cost.
+#[derive(Debug, serde::Serialize)]
+pub struct BuildCostRow {
+    pub id: String,
+    pub timestamp: String,
+    pub work_order: String,
+    pub sales_order: String,
+    pub customer: String,
+    pub assembly_sku: String,
+    pub quantity_built: f64,
+    pub operator: Option<String>,
+    pub notes: Option<String>,
+    pub bom_revision: Option<String>,
+    pub substitutions: Option<String>,
+    pub labor_minutes: Option<f64>,
+    pub unit_material_cost: Option<f64>,
+    pub material_cost: Option<f64>,
+    /// Parts left out of the cost for lack of one, separated by `;`
+    pub missing_costs: Option<String>,
+}
+
+pub fn load_standard_costs(data_dir: &Path) -> Result<Vec<StandardCost>> {
+    read_csv_if_exists::<StandardCost>(&data_dir.join("part_costs.csv"))
+        .context("Reading part_costs.csv")
+}
+
+/// Merge standard costs from a CSV with `sku,standard_cost` columns into
+/// `part_costs.csv`. Existing SKUs take the imported cost.
+pub fn import_standard_costs(data_dir: &Path, source: &Path) -> Result<Vec<StandardCost>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .trim(csv::Trim::All)
+        .from_path(source)
+        .with_context(|| format!("Failed to open {}", source.display()))?;
+    let imported = rdr.deserialize::<StandardCost>()
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to parse {}", source.display()))?;
+
+    let known = KnownSkus::load(data_dir)?;
+    for cost in &imported {
+        if !known.contains(&cost.sku) {
+            bail!("Unknown SKU: {}", cost.sku);
+        }
+        if cost.standard_cost < 0.0 || !cost.standard_cost.is_finite() {
+            bail!("Standard cost for {} must not be negative", cost.sku);
+        }
+    }
+
+    let mut costs = load_standard_costs(data_dir)?;
+    for cost in imported {
+        match costs.iter_mut().find(|c| c.sku == cost.sku) {
+            Some(existing) => *existing = cost,
+            None => costs.push(cost),
+        }
+    }
+    costs.sort_by(|a, b| a.sku.cmp(&b.sku));
+    write_csv(&data_dir.join("part_costs.csv"), &costs)
+        .context("Writing part_costs.csv")?;
+    Ok(costs)
+}
+
+/// `part_costs` keyed by SKU.
+pub fn part_cost_index(data_dir: &Path) -> Result<HashMap<String, PartCost>> {
+    Ok(part_costs(data_dir)?.into_iter().map(|c| (c.sku.clone(), c)).collect())
+}
+
+/// Standard and received-price average cost of every part and of any other
+/// SKU that has either.
+pub fn part_costs(data_dir: &Path) -> Result<Vec<PartCost>> {
+    let standard: HashMap<String, f64> = load_standard_costs(data_dir)?.into_iter()
+        .map(|c| (c.sku, c.standard_cost))
+        .collect();
+    let mut received: HashMap<String, (f64, f64)> = HashMap::new();
+    for line in load_purchase_orders(data_dir)?.iter().filter(|l| l.qty_received > 0.0) {
+        let (qty, value) = received.entry(line.part_sku.clone()).or_default();
+        *qty += line.qty_received;
+        *value += line.qty_received * line.unit_cost;
+    }
+
+    let skus: BTreeSet<String> = load_parts(data_dir)?.into_iter().map(|p| p.part_sku)
+        .chain(standard.keys().cloned())
+        .chain(received.keys().cloned())
+        .collect();
+    Ok(skus.into_iter()
+        .map(|sku| {
+            let (received_qty, value) = received.get(&sku).copied().unwrap_or_default();
+            PartCost {
+                standard_cost: standard.get(&sku).copied(),
+                average_cost: (received_qty > 0.0).then(|| value / received_qty),
+                received_qty,
+                sku,
+            }
+        })
+        .collect())
+}
+
+/// Cost lines for leaf requirements per unit, sorted by SKU.
+fn cost_lines(req_per_unit: &HashMap<String, f64>, costs: &HashMap<String, PartCost>, method: CostMethod) -> Vec<CostLine> {
+    let mut lines: Vec<CostLine> = req_per_unit.iter()
+        .map(|(sku, qty)| {
+            let (unit_cost, source) = costs.get(sku)
+                .map(|c| c.unit_cost(method))
+                .unwrap_or((None, CostSource::Missing));
+            CostLine {
+                sku: sku.clone(),
+                qty_per_unit: *qty,
+                unit_cost,
+                extended_cost: unit_cost.map(|c| c * qty).unwrap_or(0.0),
+                source,
+            }
+        })
+        .collect();
+    lines.sort_by(|a, b| a.sku.cmp(&b.sku));
+    lines
+}
+
+/// Material cost of one unit of an assembly from the BOM in effect on
+/// `bom_date`, exploded to leaf parts with scrap and yield.
+pub fn cost_rollup(
+    data_dir: &Path,
+    assembly_sku: &str,
+    method: CostMethod,
+    bom_date: chrono::NaiveDate,
+) -> Result<CostRollup> {
+    let data = load_data_dir_as_of(data_dir, bom_date)?;
+    data.check_complete(assembly_sku)?;
+    if !data.bom_items.iter().any(|b| b.parent_assembly_sku == assembly_sku) {
+        bail!("{} has no BOM lines", assembly_sku);
+    }
+    let lines = cost_lines(&explode_bom(assembly_sku, &data.bom_items)?, &part_cost_index(data_dir)?, method);
+    Ok(CostRollup {
+        assembly_sku: assembly_sku.to_string(),
+        method,
+        bom_date: data.bom_date,
+        material_cost: lines.iter().map(|l| l.extended_cost).sum(),
+        missing_costs: lines.iter().filter(|l| l.source == CostSource::Missing).map(|l| l.sku.clone()).collect(),
+        lines,
+    })
+}
+
+/// Filtered build history with each build's material cost, using the BOM
+/// in effect on its build date and today's part costs, the history rows that
+/// could not be read, and the BOM lines left out for lack of a unit conversion.
+pub fn build_cost_rows(
+    data_dir: &Path,
+    query: &HistoryQuery,
+    method: CostMethod,
+) -> Result<(Vec<BuildCostRow>, Vec<SkippedRow>, Vec<String>)> {
+    let costs = part_cost_index(data_dir)?;
+    let mut explosions = ExplosionCache::load(data_dir)?;
+    let history = filter_history(data_dir, query)?;
+    let mut rows = Vec::new();
+    for (date, record) in history.records {
+        let lines = explosions.requirements(&record, date)?
+            .map(|req| cost_lines(req, &costs, method));
+        let unit_cost = lines.as_ref().map(|l| l.iter().map(|c| c.extended_cost).sum::<f64>());
+        let missing = lines.as_ref()
+            .map(|l| l.iter().filter(|c| c.source == CostSource::Missing).map(|c| c.sku.as_str()).collect::<Vec<_>>().join(";"))
+            .filter(|m| !m.is_empty());
+        rows.push(cost_row(record, unit_cost, missing));
+    }
+    Ok((rows, history.skipped, explosions.skipped_lines))
+}
+
+fn cost_row(record: BuildHistoryRecord, unit_cost: Option<f64>, missing_costs: Option<String>) -> BuildCostRow {
+    BuildCostRow {
+        material_cost: unit_cost.map(|c| c * record.quantity_built),
+        unit_material_cost: unit_cost,
+        missing_costs,
+        id: record.id,
+        timestamp: record.timestamp,
+        work_order: record.work_order,
+        sales_order: record.sales_order,
+        customer: record.customer,
+        assembly_sku: record.assembly_sku,
+        quantity_built: record.quantity_built,
+        operator: record.operator,
+        notes: record.notes,
+        bom_revision: record.bom_revision,
+        substitutions: record.substitutions,
+        labor_minutes: record.labor_minutes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    fn fixture() -> TestDir {
+        let dir = TestDir::new();
+        dir.write("assemblies.csv", "assembly_sku,name,uom,active\nA,Assembly,ea,true\nSUB,Sub-assembly,ea,true\n");
+        dir.write("parts.csv", "part_sku,name,uom\nP1,Part one,ea\nP2,Part two,ea\nP3,Part three,ea\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\n\
+            A,SUB,2,0,1,false\nA,P1,1,0.5,1,false\nSUB,P2,1,0,0.5,false\nSUB,P3,1,0,1,false\n");
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,10,0\n");
+        dir.write("part_costs.csv", "sku,standard_cost\nP1,2\nP2,1\n");
+        dir.write("purchase_orders.csv", "po_number,line_no,supplier_id,part_sku,qty_ordered,qty_received,unit_cost,order_date,expected_date,status\n\
+            PO-1,1,S1,P1,10,10,3,2024-01-01,2024-01-10,received\n\
+            PO-2,1,S1,P1,30,30,1,2024-02-01,2024-02-10,received\n\
+            PO-2,2,S1,P3,5,0,9,2024-02-01,2024-02-10,open\n");
+        dir
+    }
+
+    fn date() -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()
+    }
+
+    #[test]
+    fn average_cost_weights_received_quantities() {
+        let dir = fixture();
+        let costs = part_cost_index(dir.path()).unwrap();
+        let p1 = &costs["P1"];
+        assert_eq!(p1.standard_cost, Some(2.0));
+        assert_eq!(p1.average_cost, Some(1.5));
+        assert_eq!(p1.received_qty, 40.0);
+        // Nothing received yet, so no average
+        assert_eq!(costs["P3"].average_cost, None);
+
+        assert_eq!(p1.unit_cost(CostMethod::Average), (Some(1.5), CostSource::Average));
+        assert_eq!(costs["P2"].unit_cost(CostMethod::Average), (Some(1.0), CostSource::Standard));
+        assert_eq!(costs["P3"].unit_cost(CostMethod::Standard), (None, CostSource::Missing));
+    }
+
+    #[test]
+    fn rollup_explodes_with_scrap_and_yield() {
+        let dir = fixture();
+        let rollup = cost_rollup(dir.path(), "A", CostMethod::Standard, date()).unwrap();
+        let qty: Vec<(&str, f64)> = rollup.lines.iter().map(|l| (l.sku.as_str(), l.qty_per_unit)).collect();
+        assert_eq!(qty, vec![("P1", 1.5), ("P2", 4.0), ("P3", 2.0)]);
+        // 1.5 x 2 + 4 x 1, with P3 missing
+        assert_eq!(rollup.material_cost, 7.0);
+        assert_eq!(rollup.missing_costs, vec!["P3"]);
+
+        assert!(cost_rollup(dir.path(), "P1", CostMethod::Standard, date()).is_err());
+    }
+
+    #[test]
+    fn build_rows_cost_each_build() {
+        let dir = fixture();
+        dir.write("panel_history.csv", "id,timestamp,work_order,sales_order,customer,assembly_sku,quantity_built\n\
+            b1,2024-03-01T10:00:00Z,WO-1,SO-1,Beacon,A,2\nb2,2024-03-02T10:00:00Z,WO-2,SO-2,Beacon,P1,1\n");
+        let query = HistoryQuery { ascending: true, ..HistoryQuery::default() };
+        let (rows, skipped, skipped_lines) = build_cost_rows(dir.path(), &query, CostMethod::Standard).unwrap();
+        assert!(skipped.is_empty());
+        assert!(skipped_lines.is_empty());
+        assert_eq!(rows[0].unit_material_cost, Some(7.0));
+        assert_eq!(rows[0].material_cost, Some(14.0));
+        assert_eq!(rows[0].missing_costs.as_deref(), Some("P3"));
+        assert_eq!(rows[1].material_cost, None);
+    }
+
+    #[test]
+    fn import_merges_and_validates_costs() {
+        let dir = fixture();
+        dir.write("import.csv", "sku,standard_cost\nP2,1.25\nP3,4\n");
+        let costs = import_standard_costs(dir.path(), &dir.path().join("import.csv")).unwrap();
+        let values: Vec<(&str, f64)> = costs.iter().map(|c| (c.sku.as_str(), c.standard_cost)).collect();
+        assert_eq!(values, vec![("P1", 2.0), ("P2", 1.25), ("P3", 4.0)]);
+
+        dir.write("bad.csv", "sku,standard_cost\nNOPE,1\n");
+        assert!(import_standard_costs(dir.path(), &dir.path().join("bad.csv")).is_err());
+        dir.write("bad.csv", "sku,standard_cost\nP1,-1\n");
+        assert!(import_standard_costs(dir.path(), &dir.path().join("bad.csv")).is_err());
+    }
+
+    #[test]
+    fn corrupt_costs_file_is_not_overwritten() {
+        let dir = fixture();
+        let corrupt = "sku,standard_cost\nP1,two\n";
+        dir.write("part_costs.csv", corrupt);
+        dir.write("import.csv", "sku,standard_cost\nP2,1\n");
+        assert!(part_costs(dir.path()).is_err());
+        assert!(import_standard_costs(dir.path(), &dir.path().join("import.csv")).is_err());
+        assert_eq!(dir.read("part_costs.csv"), corrupt);
+    }
+}
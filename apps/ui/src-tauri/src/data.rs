@@ -1,24 +1,42 @@
 use anyhow::{Context, Result};
+use chrono::NaiveDate;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
 use std::path::Path;
 use std::io::Write;
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+use crate::bom::{effective_bom, load_bom_revisions, usable_revisions, BomRevision};
+use crate::uom::{load_uom_conversions, UomTable};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Assembly {
     pub assembly_sku: String,
     pub name: String,
     pub uom: String,
+    /// Inactive assemblies stay in history but cannot be built or get new BOM lines
+    #[serde(default = "default_true")]
+    pub active: bool,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Part {
     pub part_sku: String,
     pub name: String,
     pub uom: String,
+    /// Inactive parts cannot be added to BOMs
+    #[serde(default = "default_true")]
+    pub active: bool,
+    /// Grouping for inventory valuation totals
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+pub fn default_true() -> bool {
+    true
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct BomItem {
     pub parent_assembly_sku: String,
     pub component_sku: String,
@@ -26,16 +44,25 @@ pub struct BomItem {
     pub scrap_rate: f64,
     pub yield_pct: f64,
     pub is_phantom: bool,
+    /// Revision of the parent this line belongs to; empty for the base BOM
+    #[serde(default)]
+    pub revision: Option<String>,
+    /// Unit `qty_per` is given in; empty means the component's stock unit
+    #[serde(default)]
+    pub uom: Option<String>,
+    /// Substitute group the component may be replaced from when it runs out
+    #[serde(default)]
+    pub substitute_group: Option<String>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct StockRow {
     pub sku: String,
     pub on_hand_qty: f64,
     pub reserved_qty: f64,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct BuildHistoryRecord {
     pub id: String,
     pub timestamp: String,
@@ -48,9 +75,18 @@ pub struct BuildHistoryRecord {
     pub operator: Option<String>,
     #[serde(default)]
     pub notes: Option<String>,
+    /// Revision of the assembly in effect when it was built
+    #[serde(default)]
+    pub bom_revision: Option<String>,
+    /// Substitutes consumed in place of exhausted primaries, e.g. `B2 for B x4`
+    #[serde(default)]
+    pub substitutions: Option<String>,
+    /// Actual labor for the whole build, when captured
+    #[serde(default)]
+    pub labor_minutes: Option<f64>,
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct InventoryItem {
     pub sku: String,
     pub name: String,
@@ -62,18 +98,101 @@ pub struct InventoryItem {
     pub reorder_point: Option<f64>,
     #[serde(default)]
     pub supplier: Option<String>,
+    /// Open purchase order quantity; derived, never stored in main_inventory.csv
+    #[serde(default)]
+    pub on_order_qty: f64,
+}
+
+/// Row layout of `main_inventory.csv`, which omits derived fields like `on_order_qty`.
+#[derive(serde::Serialize)]
+struct MainInventoryRow<'a> {
+    sku: &'a str,
+    name: &'a str,
+    uom: &'a str,
+    on_hand_qty: f64,
+    reserved_qty: f64,
+    available_qty: f64,
+    reorder_point: Option<f64>,
+    supplier: Option<&'a str>,
 }
 
 #[derive(Debug, serde::Serialize)]
 pub struct DataSnapshot {
     pub assemblies: Vec<Assembly>,
     pub parts: Vec<Part>,
+    /// BOM lines effective on `bom_date`, quantities in each component's stock unit
     pub bom_items: Vec<BomItem>,
+    #[serde(default)]
+    pub bom_revisions: Vec<BomRevision>,
+    #[serde(default)]
+    pub bom_date: String,
     pub stock: Vec<StockRow>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub build_history: Option<Vec<BuildHistoryRecord>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub inventory: Option<Vec<InventoryItem>>,
+    /// Rows left out of this snapshot because they could not be used
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// BOM lines left out because their unit could not be converted, with the reason
+    #[serde(skip)]
+    pub unconverted_lines: Vec<(BomItem, String)>,
+}
+
+impl DataSnapshot {
+    /// Fail if a BOM line of `assembly_sku`, or of anything below it, was left
+    /// out of this snapshot, since quantities for it would be understated.
+    pub fn check_complete(&self, assembly_sku: &str) -> Result<()> {
+        let mut below = std::collections::HashSet::from([assembly_sku]);
+        let mut queue = vec![assembly_sku];
+        while let Some(sku) = queue.pop() {
+            for item in self.bom_items.iter().filter(|b| b.parent_assembly_sku == sku) {
+                if below.insert(item.component_sku.as_str()) {
+                    queue.push(item.component_sku.as_str());
+                }
+            }
+        }
+        match self.unconverted_lines.iter().find(|(b, _)| below.contains(b.parent_assembly_sku.as_str())) {
+            Some((_, reason)) => anyhow::bail!("{}", reason),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Which files hold the authoritative on-hand and reserved quantities.
+///
+/// Precedence: when `main_inventory.csv` exists it is authoritative, and builds
+/// are deducted from it. Otherwise quantities come from `stock.csv`, with names
+/// and units taken from `parts.csv`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InventorySource {
+    MainInventory,
+    PartsStock,
+}
+
+/// A SKU whose quantities disagree between `main_inventory.csv` and `stock.csv`.
+/// A `None` side means the SKU is missing from that file.
+#[derive(Debug, serde::Serialize)]
+pub struct InventoryDiscrepancy {
+    pub sku: String,
+    pub main_on_hand_qty: Option<f64>,
+    pub main_reserved_qty: Option<f64>,
+    pub stock_on_hand_qty: Option<f64>,
+    pub stock_reserved_qty: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ReconciliationReport {
+    pub authoritative_source: InventorySource,
+    pub compared_skus: usize,
+    pub discrepancies: Vec<InventoryDiscrepancy>,
+}
+
+/// Parse a `YYYY-MM-DD` date as entered in the CSV files and forms.
+pub fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+        .with_context(|| format!("Invalid date '{}', expected YYYY-MM-DD", date))
 }
 
 fn read_csv<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Vec<T>> {
@@ -92,6 +211,16 @@ fn read_csv<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Vec<T>> {
     Ok(out)
 }
 
+/// Read a CSV that may not have been created yet. A missing file reads as
+/// empty, but one that exists and does not parse is an error, so a caller
+/// that rewrites the file never replaces its rows with an empty list.
+pub fn read_csv_if_exists<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Vec<T>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    read_csv(path)
+}
+
 pub fn read_csv_optional<T: for<'de> Deserialize<'de>>(path: &Path) -> Option<Vec<T>> {
     match read_csv::<T>(path) {
         Ok(data) => Some(data),
@@ -99,6 +228,156 @@ pub fn read_csv_optional<T: for<'de> Deserialize<'de>>(path: &Path) -> Option<Ve
     }
 }
 
+/// Write rows to a CSV file, replacing its contents. Fields are quoted as needed.
+/// Rows go to a temporary file that then replaces the original, so a failed
+/// write never leaves a half-written file behind.
+pub fn write_csv<T: serde::Serialize>(path: &Path, rows: &[T]) -> Result<()> {
+    let tmp_path = path.with_extension("csv.tmp");
+    let result = write_csv_rows(&tmp_path, rows)
+        .and_then(|_| std::fs::rename(&tmp_path, path)
+            .with_context(|| format!("Failed to replace {}", path.display())));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+fn write_csv_rows<T: serde::Serialize>(path: &Path, rows: &[T]) -> Result<()> {
+    let mut wtr = csv::Writer::from_path(path)
+        .with_context(|| format!("Failed to open {} for writing", path.display()))?;
+    for row in rows {
+        wtr.serialize(row)
+            .with_context(|| format!("Failed to write row to {}", path.display()))?;
+    }
+    wtr.flush()
+        .with_context(|| format!("Failed to flush {}", path.display()))?;
+    Ok(())
+}
+
+/// Append rows to a CSV file, writing the header first if the file is new.
+/// Files written before a column was added are rewritten with the current
+/// header first, matching existing columns by name and leaving new ones empty.
+pub fn append_csv<T: serde::Serialize>(path: &Path, rows: &[T]) -> Result<()> {
+    let Some(first) = rows.first() else {
+        return Ok(());
+    };
+    let header = serialized_header(first)?;
+    let file_exists = path.exists();
+    if file_exists {
+        migrate_csv_header(path, &header)?;
+    }
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {} for writing", path.display()))?;
+    let mut wtr = csv::WriterBuilder::new()
+        .has_headers(!file_exists)
+        .from_writer(file);
+    for row in rows {
+        wtr.serialize(row)
+            .with_context(|| format!("Failed to write row to {}", path.display()))?;
+    }
+    wtr.flush()
+        .with_context(|| format!("Failed to flush {}", path.display()))?;
+    Ok(())
+}
+
+/// Run `f`, which writes some of `files` in `data_dir`, and put every one of
+/// them back as it was if it fails, so an update spanning several files lands
+/// completely or not at all. Files that did not exist beforehand are removed.
+pub fn with_rollback<T, F>(data_dir: &Path, files: &[&str], f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+{
+    let mut backups: Vec<(std::path::PathBuf, Option<std::path::PathBuf>)> = Vec::new();
+    for name in files {
+        let path = data_dir.join(name);
+        if !path.exists() {
+            backups.push((path, None));
+            continue;
+        }
+        let backup = path.with_extension("csv.bak");
+        if let Err(e) = std::fs::copy(&path, &backup) {
+            for made in backups.into_iter().filter_map(|(_, backup)| backup) {
+                let _ = std::fs::remove_file(made);
+            }
+            return Err(e).with_context(|| format!("Failed to back up {}", path.display()));
+        }
+        backups.push((path, Some(backup)));
+    }
+
+    let result = f();
+    let mut unrestored = Vec::new();
+    for (path, backup) in backups {
+        let restored = match (&result, backup) {
+            (Ok(_), Some(backup)) => {
+                let _ = std::fs::remove_file(backup);
+                Ok(())
+            },
+            (Ok(_), None) => Ok(()),
+            (Err(_), Some(backup)) => std::fs::rename(&backup, &path),
+            (Err(_), None) if path.exists() => std::fs::remove_file(&path),
+            (Err(_), None) => Ok(()),
+        };
+        if restored.is_err() {
+            unrestored.push(path.display().to_string());
+        }
+    }
+    match result {
+        Err(e) if !unrestored.is_empty() => {
+            Err(e.context(format!("Could not restore {}", unrestored.join(", "))))
+        },
+        result => result,
+    }
+}
+
+fn serialized_header<T: serde::Serialize>(row: &T) -> Result<csv::StringRecord> {
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    wtr.serialize(row).context("Failed to serialize CSV header")?;
+    let bytes = wtr.into_inner().context("Failed to serialize CSV header")?;
+    let mut rdr = csv::ReaderBuilder::new().has_headers(true).from_reader(bytes.as_slice());
+    Ok(rdr.headers().context("Failed to serialize CSV header")?.clone())
+}
+
+fn migrate_csv_header(path: &Path, header: &csv::StringRecord) -> Result<()> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .with_context(|| format!("Failed to open CSV: {}", path.display()))?;
+    let old_header = rdr.headers()
+        .with_context(|| format!("Failed to read header of {}", path.display()))?
+        .clone();
+    if old_header.iter().eq(header.iter()) {
+        return Ok(());
+    }
+
+    let positions: Vec<Option<usize>> = header.iter()
+        .map(|name| old_header.iter().position(|old| old == name))
+        .collect();
+    let mut migrated = Vec::new();
+    for rec in rdr.records() {
+        let rec = rec.with_context(|| format!("Failed to parse row in {}", path.display()))?;
+        migrated.push(positions.iter()
+            .map(|pos| pos.and_then(|p| rec.get(p)).unwrap_or("").to_string())
+            .collect::<Vec<String>>());
+    }
+
+    let mut wtr = csv::Writer::from_path(path)
+        .with_context(|| format!("Failed to open {} for writing", path.display()))?;
+    wtr.write_record(header)
+        .with_context(|| format!("Failed to write header to {}", path.display()))?;
+    for row in migrated {
+        wtr.write_record(&row)
+            .with_context(|| format!("Failed to write row to {}", path.display()))?;
+    }
+    wtr.flush()
+        .with_context(|| format!("Failed to flush {}", path.display()))?;
+    Ok(())
+}
+
 fn create_unified_inventory(parts: &[Part], stock: &[StockRow]) -> Vec<InventoryItem> {
     let mut inventory = Vec::new();
     
@@ -122,101 +401,308 @@ fn create_unified_inventory(parts: &[Part], stock: &[StockRow]) -> Vec<Inventory
             available_qty: on_hand - reserved,
             reorder_point: None, // Could be loaded from separate CSV in future
             supplier: None, // Could be loaded from separate CSV in future
+            on_order_qty: 0.0,
         });
     }
     
     inventory
 }
 
-pub fn load_data_dir(data_dir: &Path) -> Result<DataSnapshot> {
-    let assemblies = read_csv::<Assembly>(&data_dir.join("assemblies.csv"))
-        .context("Reading assemblies.csv")?;
+pub fn inventory_source(data_dir: &Path) -> InventorySource {
+    if data_dir.join("main_inventory.csv").exists() {
+        InventorySource::MainInventory
+    } else {
+        InventorySource::PartsStock
+    }
+}
+
+/// Load the inventory from whichever source is authoritative.
+///
+/// With `main_inventory.csv`, parts listed in `parts.csv` but missing from the
+/// main inventory are included with zero quantities so every known part appears.
+pub fn load_inventory(data_dir: &Path) -> Result<Vec<InventoryItem>> {
     let parts = read_csv::<Part>(&data_dir.join("parts.csv"))
         .context("Reading parts.csv")?;
-    let bom_items = read_csv::<BomItem>(&data_dir.join("bom_items.csv"))
-        .context("Reading bom_items.csv")?;
-    let stock = read_csv::<StockRow>(&data_dir.join("stock.csv"))
-        .context("Reading stock.csv")?;
+    match inventory_source(data_dir) {
+        InventorySource::MainInventory => {
+            let mut items = load_main_inventory(data_dir)?;
+            let known: std::collections::HashSet<String> =
+                items.iter().map(|i| i.sku.clone()).collect();
+            items.extend(create_unified_inventory(&parts, &[]).into_iter()
+                .filter(|i| !known.contains(&i.sku)));
+            Ok(items)
+        },
+        InventorySource::PartsStock => {
+            let stock = read_csv::<StockRow>(&data_dir.join("stock.csv"))
+                .context("Reading stock.csv")?;
+            Ok(create_unified_inventory(&parts, &stock))
+        }
+    }
+}
+
+pub fn load_data_dir(data_dir: &Path) -> Result<DataSnapshot> {
+    load_data_dir_as_of(data_dir, chrono::Local::now().date_naive())
+}
+
+/// Every BOM line in `bom_items.csv`, across all revisions.
+pub fn load_bom_items(data_dir: &Path) -> Result<Vec<BomItem>> {
+    read_csv::<BomItem>(&data_dir.join("bom_items.csv"))
+        .context("Reading bom_items.csv")
+}
+
+pub fn write_bom_items(data_dir: &Path, bom_items: &[BomItem]) -> Result<()> {
+    write_csv(&data_dir.join("bom_items.csv"), bom_items)
+        .context("Writing bom_items.csv")
+}
+
+pub fn load_assemblies(data_dir: &Path) -> Result<Vec<Assembly>> {
+    read_csv::<Assembly>(&data_dir.join("assemblies.csv"))
+        .context("Reading assemblies.csv")
+}
+
+pub fn load_parts(data_dir: &Path) -> Result<Vec<Part>> {
+    read_csv::<Part>(&data_dir.join("parts.csv"))
+        .context("Reading parts.csv")
+}
+
+/// Load the data directory with the BOM revisions effective on `bom_date`.
+pub fn load_data_dir_as_of(data_dir: &Path, bom_date: NaiveDate) -> Result<DataSnapshot> {
+    let assemblies = load_assemblies(data_dir)?;
+    let parts = load_parts(data_dir)?;
+    let (bom_revisions, mut warnings) = usable_revisions(load_bom_revisions(data_dir)?);
+    let (bom_items, unconverted_lines) = UomTable::new(load_uom_conversions(data_dir)?, &assemblies, &parts)
+        .usable_lines(effective_bom(&load_bom_items(data_dir)?, &bom_revisions, bom_date)?);
+    warnings.extend(unconverted_lines.iter().map(|(_, reason)| format!("Skipped {}", reason)));
     
     // Optional files - don't fail if they don't exist yet
     let build_history = read_csv_optional::<BuildHistoryRecord>(&data_dir.join("build_history.csv"));
     
-    // Create unified inventory from parts and stock
-    let inventory = create_unified_inventory(&parts, &stock);
+    // Stock always reflects the authoritative source so buildability matches the inventory view
+    let (stock, inventory) = match inventory_source(data_dir) {
+        InventorySource::MainInventory => {
+            let inventory = load_inventory(data_dir)?;
+            (inventory_to_stock(&inventory), inventory)
+        },
+        InventorySource::PartsStock => {
+            let stock = read_csv::<StockRow>(&data_dir.join("stock.csv"))
+                .context("Reading stock.csv")?;
+            let inventory = create_unified_inventory(&parts, &stock);
+            (stock, inventory)
+        }
+    };
 
     Ok(DataSnapshot {
         assemblies,
         parts,
         bom_items,
+        bom_revisions,
+        bom_date: bom_date.format("%Y-%m-%d").to_string(),
         stock,
         build_history,
         inventory: Some(inventory),
+        warnings,
+        unconverted_lines,
     })
 }
 
+fn inventory_to_stock(items: &[InventoryItem]) -> Vec<StockRow> {
+    items.iter().map(|i| StockRow {
+        sku: i.sku.clone(),
+        on_hand_qty: i.on_hand_qty,
+        reserved_qty: i.reserved_qty,
+    }).collect()
+}
+
+/// Compare `main_inventory.csv` against `stock.csv` and list every SKU that
+/// is missing from one side or whose quantities differ.
+pub fn reconcile_inventory(data_dir: &Path) -> Result<ReconciliationReport> {
+    let authoritative_source = inventory_source(data_dir);
+    if authoritative_source == InventorySource::PartsStock {
+        // Only one source exists, so there is nothing to disagree with
+        return Ok(ReconciliationReport {
+            authoritative_source,
+            compared_skus: 0,
+            discrepancies: Vec::new(),
+        });
+    }
+    let main_items = load_main_inventory(data_dir)?;
+    let stock = read_csv_if_exists::<StockRow>(&data_dir.join("stock.csv"))
+        .context("Reading stock.csv")?;
+
+    let main_map: HashMap<&str, &InventoryItem> =
+        main_items.iter().map(|i| (i.sku.as_str(), i)).collect();
+    let stock_map: HashMap<&str, &StockRow> =
+        stock.iter().map(|s| (s.sku.as_str(), s)).collect();
+
+    let mut skus: Vec<&str> = main_map.keys().chain(stock_map.keys()).copied().collect();
+    skus.sort_unstable();
+    skus.dedup();
+
+    let mut discrepancies = Vec::new();
+    for sku in &skus {
+        let main = main_map.get(sku);
+        let stock = stock_map.get(sku);
+        let matches = match (main, stock) {
+            (Some(m), Some(s)) => {
+                (m.on_hand_qty - s.on_hand_qty).abs() < 1e-9
+                    && (m.reserved_qty - s.reserved_qty).abs() < 1e-9
+            },
+            _ => false,
+        };
+        if !matches {
+            discrepancies.push(InventoryDiscrepancy {
+                sku: sku.to_string(),
+                main_on_hand_qty: main.map(|m| m.on_hand_qty),
+                main_reserved_qty: main.map(|m| m.reserved_qty),
+                stock_on_hand_qty: stock.map(|s| s.on_hand_qty),
+                stock_reserved_qty: stock.map(|s| s.reserved_qty),
+            });
+        }
+    }
+
+    Ok(ReconciliationReport {
+        authoritative_source,
+        compared_skus: skus.len(),
+        discrepancies,
+    })
+}
+
+/// Load the authoritative stock quantities, let `f` change them, and write them
+/// back to the same source. Rows added by `f` for SKUs not yet in
+/// `main_inventory.csv` take their name and unit from `parts.csv`.
+pub fn modify_stock<F>(data_dir: &Path, f: F) -> Result<()>
+where
+    F: FnOnce(&mut Vec<StockRow>) -> Result<()>,
+{
+    match inventory_source(data_dir) {
+        InventorySource::PartsStock => {
+            let mut stock = read_csv::<StockRow>(&data_dir.join("stock.csv"))
+                .context("Reading stock.csv")?;
+            f(&mut stock)?;
+            write_stock_csv(data_dir, &stock)
+        },
+        InventorySource::MainInventory => {
+            let mut items = load_main_inventory(data_dir)?;
+            let mut stock = inventory_to_stock(&items);
+            f(&mut stock)?;
+
+            let parts = read_csv_if_exists::<Part>(&data_dir.join("parts.csv"))
+                .context("Reading parts.csv")?;
+            for row in stock {
+                match items.iter_mut().find(|i| i.sku == row.sku) {
+                    Some(item) => {
+                        item.on_hand_qty = row.on_hand_qty;
+                        item.reserved_qty = row.reserved_qty;
+                        item.available_qty = row.on_hand_qty - row.reserved_qty;
+                    },
+                    None => {
+                        let part = parts.iter().find(|p| p.part_sku == row.sku);
+                        items.push(InventoryItem {
+                            name: part.map(|p| p.name.clone()).unwrap_or_else(|| row.sku.clone()),
+                            uom: part.map(|p| p.uom.clone()).unwrap_or_else(|| "ea".to_string()),
+                            available_qty: row.on_hand_qty - row.reserved_qty,
+                            on_hand_qty: row.on_hand_qty,
+                            reserved_qty: row.reserved_qty,
+                            sku: row.sku,
+                            reorder_point: None,
+                            supplier: None,
+                            on_order_qty: 0.0,
+                        });
+                    }
+                }
+            }
+            write_main_inventory_csv(data_dir, &items)
+        }
+    }
+}
+
 /// Add a build record to the build_history.csv file
 pub fn add_build_record(data_dir: &Path, record: &BuildHistoryRecord) -> Result<()> {
-    let file_path = data_dir.join("build_history.csv");
-    
-    // Check if file exists, if not create with headers
-    let file_exists = file_path.exists();
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&file_path)
-        .with_context(|| format!("Failed to open build_history.csv for writing"))?;
-    
-    // Write headers if file is new
-    if !file_exists {
-        writeln!(file, "id,timestamp,work_order,sales_order,customer,assembly_sku,quantity_built,operator,notes")
-            .context("Failed to write headers to build_history.csv")?;
+    // Appending through the CSV writer quotes free-text fields and adds
+    // columns introduced since the file was created
+    append_csv(&data_dir.join("build_history.csv"), std::slice::from_ref(record))
+        .context("Failed to write build record")
+}
+
+/// Components consumed by building `quantity` units of an assembly: its
+/// direct BOM lines at `qty_per`, summed per component. Phantom lines are
+/// never stocked, so they are blown through to their own direct lines.
+pub fn direct_requirements(assembly_sku: &str, quantity: f64, bom_items: &[BomItem]) -> Result<HashMap<String, f64>> {
+    fn walk<'a>(
+        sku: &'a str,
+        multiplier: f64,
+        bom_items: &'a [BomItem],
+        visiting: &mut Vec<&'a str>,
+        out: &mut HashMap<String, f64>,
+    ) -> Result<()> {
+        if visiting.contains(&sku) {
+            anyhow::bail!("Circular BOM detected at {}", sku);
+        }
+        visiting.push(sku);
+        for item in bom_items.iter().filter(|b| b.parent_assembly_sku == sku) {
+            let qty = item.qty_per * multiplier;
+            let component = item.component_sku.as_str();
+            if item.is_phantom && bom_items.iter().any(|b| b.parent_assembly_sku == component) {
+                walk(component, qty, bom_items, visiting, out)?;
+            } else {
+                *out.entry(component.to_string()).or_insert(0.0) += qty;
+            }
+        }
+        visiting.pop();
+        Ok(())
     }
-    
-    // Write the record
-    writeln!(file, "{},{},{},{},{},{},{},{},{}",
-        record.id,
-        record.timestamp,
-        record.work_order,
-        record.sales_order,
-        record.customer,
-        record.assembly_sku,
-        record.quantity_built,
-        record.operator.as_deref().unwrap_or(""),
-        record.notes.as_deref().unwrap_or("")
-    ).context("Failed to write build record")?;
-    
-    Ok(())
+
+    let mut parts_consumed = HashMap::new();
+    walk(assembly_sku, quantity, bom_items, &mut Vec::new(), &mut parts_consumed)?;
+    Ok(parts_consumed)
 }
 
-/// Update stock quantities by deducting parts consumed in a build
+/// Update stock quantities by deducting parts consumed in a build from the
+/// authoritative inventory source. Returns the quantity actually deducted per SKU.
 pub fn update_stock_after_build(
     data_dir: &Path,
     assembly_sku: &str,
     quantity_built: f64,
     bom_items: &[BomItem],
-    current_stock: &mut Vec<StockRow>
-) -> Result<()> {
+) -> Result<HashMap<String, f64>> {
     // Find all parts needed for this assembly
-    let mut parts_consumed = std::collections::HashMap::new();
-    
-    for bom_item in bom_items {
-        if bom_item.parent_assembly_sku == assembly_sku {
-            let total_needed = bom_item.qty_per * quantity_built;
-            *parts_consumed.entry(bom_item.component_sku.clone()).or_insert(0.0) += total_needed;
-        }
-    }
+    let parts_consumed = direct_requirements(assembly_sku, quantity_built, bom_items)?;
     
-    // Update stock quantities
-    for stock_item in current_stock.iter_mut() {
-        if let Some(consumed) = parts_consumed.get(&stock_item.sku) {
-            stock_item.on_hand_qty = (stock_item.on_hand_qty - consumed).max(0.0);
+    deduct_stock(data_dir, &parts_consumed)
+}
+
+/// Deduct consumed quantities from the authoritative inventory source, never
+/// below zero. Returns the quantity actually deducted per SKU.
+pub fn deduct_stock(data_dir: &Path, consumed: &HashMap<String, f64>) -> Result<HashMap<String, f64>> {
+    let mut deducted = HashMap::new();
+    modify_stock(data_dir, |stock| {
+        for stock_item in stock.iter_mut() {
+            if let Some(consumed) = consumed.get(&stock_item.sku) {
+                let new_qty = (stock_item.on_hand_qty - consumed).max(0.0);
+                deducted.insert(stock_item.sku.clone(), stock_item.on_hand_qty - new_qty);
+                stock_item.on_hand_qty = new_qty;
+            }
         }
-    }
-    
-    // Write updated stock back to CSV
-    write_stock_csv(data_dir, current_stock)?;
+        Ok(())
+    })?;
     
-    Ok(())
+    Ok(deducted)
+}
+
+/// Write inventory items back to main_inventory.csv
+fn write_main_inventory_csv(data_dir: &Path, items: &[InventoryItem]) -> Result<()> {
+    let rows: Vec<MainInventoryRow> = items.iter().map(|i| MainInventoryRow {
+        sku: &i.sku,
+        name: &i.name,
+        uom: &i.uom,
+        on_hand_qty: i.on_hand_qty,
+        reserved_qty: i.reserved_qty,
+        available_qty: i.available_qty,
+        reorder_point: i.reorder_point,
+        supplier: i.supplier.as_deref(),
+    }).collect();
+    write_csv(&data_dir.join("main_inventory.csv"), &rows)
+        .context("Writing main_inventory.csv")
 }
 
 /// Write stock data back to stock.csv
@@ -243,36 +729,8 @@ fn write_stock_csv(data_dir: &Path, stock: &[StockRow]) -> Result<()> {
 
 /// Add a build record to the panel_history.csv file
 pub fn add_panel_history_record(data_dir: &Path, record: &BuildHistoryRecord) -> Result<()> {
-    let file_path = data_dir.join("panel_history.csv");
-    
-    // Check if file exists, if not create with headers
-    let file_exists = file_path.exists();
-    let mut file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&file_path)
-        .with_context(|| format!("Failed to open panel_history.csv for writing"))?;
-    
-    // Write headers if file is new
-    if !file_exists {
-        writeln!(file, "id,timestamp,work_order,sales_order,customer,assembly_sku,quantity_built,operator,notes")
-            .context("Failed to write headers to panel_history.csv")?;
-    }
-    
-    // Write the record
-    writeln!(file, "{},{},{},{},{},{},{},{},{}",
-        record.id,
-        record.timestamp,
-        record.work_order,
-        record.sales_order,
-        record.customer,
-        record.assembly_sku,
-        record.quantity_built,
-        record.operator.as_deref().unwrap_or(""),
-        record.notes.as_deref().unwrap_or("")
-    ).context("Failed to write panel history record")?;
-    
-    Ok(())
+    append_csv(&data_dir.join("panel_history.csv"), std::slice::from_ref(record))
+        .context("Failed to write panel history record")
 }
 
 pub fn load_main_inventory(data_dir: &Path) -> Result<Vec<InventoryItem>> {
@@ -292,3 +750,180 @@ pub fn load_main_inventory(data_dir: &Path) -> Result<Vec<InventoryItem>> {
     
     Ok(inventory_items)
 }
+
+/// Scratch data directory for unit tests, removed when dropped.
+#[cfg(test)]
+pub(crate) struct TestDir(std::path::PathBuf);
+
+#[cfg(test)]
+impl TestDir {
+    pub(crate) fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("bom-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).expect("create test dir");
+        TestDir(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Write `contents` to `name` inside the directory.
+    pub(crate) fn write(&self, name: &str, contents: &str) {
+        std::fs::write(self.0.join(name), contents).expect("write test file");
+    }
+
+    pub(crate) fn read(&self, name: &str) -> String {
+        std::fs::read_to_string(self.0.join(name)).expect("read test file")
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Row {
+        id: String,
+        note: Option<String>,
+    }
+
+    #[derive(serde::Serialize)]
+    struct WideRow {
+        id: String,
+        added: String,
+        note: Option<String>,
+    }
+
+    #[test]
+    fn write_csv_replaces_contents_and_quotes_fields() {
+        let dir = TestDir::new();
+        let path = dir.path().join("rows.csv");
+        write_csv(&path, &[Row { id: "a".into(), note: Some("x, y".into()) }]).unwrap();
+        write_csv(&path, &[Row { id: "b".into(), note: None }]).unwrap();
+        assert_eq!(dir.read("rows.csv"), "id,note\nb,\n");
+        assert!(!dir.path().join("rows.csv.tmp").exists());
+
+        write_csv(&path, &[Row { id: "c".into(), note: Some("x, y".into()) }]).unwrap();
+        let rows: Vec<Row> = read_csv(&path).unwrap();
+        assert_eq!(rows[0].note.as_deref(), Some("x, y"));
+    }
+
+    #[test]
+    fn append_csv_writes_header_once() {
+        let dir = TestDir::new();
+        let path = dir.path().join("rows.csv");
+        append_csv(&path, &[Row { id: "a".into(), note: None }]).unwrap();
+        append_csv(&path, &[Row { id: "b".into(), note: Some("n".into()) }]).unwrap();
+        assert_eq!(dir.read("rows.csv"), "id,note\na,\nb,n\n");
+    }
+
+    #[test]
+    fn append_csv_migrates_old_header_by_column_name() {
+        let dir = TestDir::new();
+        dir.write("rows.csv", "note,id\nfirst,a\n");
+        let path = dir.path().join("rows.csv");
+        append_csv(&path, &[WideRow { id: "b".into(), added: "new".into(), note: None }]).unwrap();
+        assert_eq!(dir.read("rows.csv"), "id,added,note\na,,first\nb,new,\n");
+    }
+
+    #[test]
+    fn read_csv_if_exists_treats_only_missing_files_as_empty() {
+        let dir = TestDir::new();
+        let path = dir.path().join("rows.csv");
+        assert!(read_csv_if_exists::<Row>(&path).unwrap().is_empty());
+
+        dir.write("rows.csv", "id,note\na,b,c\n");
+        assert!(read_csv_if_exists::<Row>(&path).is_err());
+        assert!(read_csv_optional::<Row>(&path).is_none());
+    }
+
+    #[test]
+    fn deduct_stock_never_goes_below_zero() {
+        let dir = TestDir::new();
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nA,10,0\nB,2,0\n");
+        let consumed = HashMap::from([("A".to_string(), 4.0), ("B".to_string(), 5.0)]);
+        let deducted = deduct_stock(dir.path(), &consumed).unwrap();
+        assert_eq!(deducted["A"], 4.0);
+        assert_eq!(deducted["B"], 2.0);
+        assert_eq!(dir.read("stock.csv"), "sku,on_hand_qty,reserved_qty\nA,6,0\nB,0,0\n");
+    }
+
+    #[test]
+    fn build_deducts_direct_bom_lines_only() {
+        let dir = TestDir::new();
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nSUB,4,0\nP1,10,0\nP2,10,0\n");
+        let line = |parent: &str, component: &str, qty_per| BomItem {
+            parent_assembly_sku: parent.to_string(),
+            component_sku: component.to_string(),
+            qty_per,
+            scrap_rate: 0.1,
+            yield_pct: 0.5,
+            is_phantom: false,
+            revision: None,
+            uom: None,
+            substitute_group: None,
+        };
+        let bom = [line("A", "SUB", 1.0), line("A", "P1", 2.0), line("A", "P1", 1.0), line("SUB", "P2", 3.0)];
+        let deducted = update_stock_after_build(dir.path(), "A", 2.0, &bom).unwrap();
+        assert_eq!(deducted.len(), 2);
+        assert_eq!(deducted["SUB"], 2.0);
+        assert_eq!(deducted["P1"], 6.0);
+        assert_eq!(dir.read("stock.csv"), "sku,on_hand_qty,reserved_qty\nSUB,2,0\nP1,4,0\nP2,10,0\n");
+    }
+
+    #[test]
+    fn with_rollback_restores_every_file_on_failure() {
+        let dir = TestDir::new();
+        dir.write("a.csv", "id\n1\n");
+        let result: Result<()> = with_rollback(dir.path(), &["a.csv", "b.csv"], || {
+            dir.write("a.csv", "id\n2\n");
+            dir.write("b.csv", "id\n3\n");
+            anyhow::bail!("late failure")
+        });
+        assert!(result.is_err());
+        assert_eq!(dir.read("a.csv"), "id\n1\n");
+        assert!(!dir.path().join("b.csv").exists());
+        assert!(!dir.path().join("a.csv.bak").exists());
+
+        with_rollback(dir.path(), &["a.csv", "b.csv"], || {
+            dir.write("a.csv", "id\n2\n");
+            Ok(())
+        }).unwrap();
+        assert_eq!(dir.read("a.csv"), "id\n2\n");
+        assert!(!dir.path().join("a.csv.bak").exists());
+    }
+
+    const MAIN_INVENTORY: &str = "sku,name,uom,on_hand_qty,reserved_qty,available_qty,reorder_point,supplier\nP1,Part one,ea,10,0,10,,\n";
+
+    #[test]
+    fn main_inventory_is_not_rewritten_with_an_unreadable_parts_file() {
+        let dir = TestDir::new();
+        dir.write("main_inventory.csv", MAIN_INVENTORY);
+        dir.write("parts.csv", "part_sku,name,uom\nP1,Part one\n");
+        let result = modify_stock(dir.path(), |stock| {
+            stock.push(StockRow { sku: "P2".to_string(), on_hand_qty: 1.0, reserved_qty: 0.0 });
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(dir.read("main_inventory.csv"), MAIN_INVENTORY);
+    }
+
+    #[test]
+    fn reconciliation_fails_on_an_unreadable_stock_file() {
+        let dir = TestDir::new();
+        dir.write("parts.csv", "part_sku,name,uom\nP1,Part one,ea\n");
+        dir.write("main_inventory.csv", MAIN_INVENTORY);
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,10,0\n");
+        assert_eq!(reconcile_inventory(dir.path()).unwrap().discrepancies.len(), 0);
+
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,ten,0\n");
+        assert!(reconcile_inventory(dir.path()).is_err());
+    }
+}
@@ -0,0 +1,678 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::Path;
+
+use crate::bom::{
+    ancestors, available_stock, check_bom_line, explode_bom, load_bom_revisions, max_buildable,
+    release_bom_revision, save_bom_revision, BomRevision, KnownSkus, RevisionStatus,
+};
+use crate::data::{
+    append_csv, load_bom_items, load_data_dir_as_of, parse_date, read_csv_if_exists, with_rollback, write_bom_items,
+    write_csv, BomItem, DataSnapshot,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EcoStatus {
+    Proposed,
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EcoAction {
+    Add,
+    Remove,
+    ChangeQty,
+}
+
+/// Header row of an engineering change order in `ecos.csv`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Eco {
+    pub eco_number: String,
+    pub title: String,
+    pub status: EcoStatus,
+    pub proposed_by: String,
+    pub proposed_at: String,
+    /// Date the resulting revisions take effect; today at approval when empty
+    #[serde(default)]
+    pub effective_from: Option<String>,
+    #[serde(default)]
+    pub decided_by: Option<String>,
+    #[serde(default)]
+    pub decided_at: Option<String>,
+    /// Revisions created on approval as `ASSEMBLY:REV`, ';'-separated
+    #[serde(default)]
+    pub new_revisions: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// One proposed BOM line change in `eco_changes.csv`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EcoChange {
+    pub eco_number: String,
+    pub line_no: u32,
+    pub parent_assembly_sku: String,
+    pub action: EcoAction,
+    pub component_sku: String,
+    #[serde(default)]
+    pub qty_per: Option<f64>,
+    #[serde(default)]
+    pub scrap_rate: Option<f64>,
+    #[serde(default)]
+    pub yield_pct: Option<f64>,
+    #[serde(default)]
+    pub is_phantom: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct EcoChangeRequest {
+    pub parent_assembly_sku: String,
+    pub action: EcoAction,
+    pub component_sku: String,
+    #[serde(default)]
+    pub qty_per: Option<f64>,
+    #[serde(default)]
+    pub scrap_rate: Option<f64>,
+    #[serde(default)]
+    pub yield_pct: Option<f64>,
+    #[serde(default)]
+    pub is_phantom: Option<bool>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct EcoRequest {
+    pub title: String,
+    pub proposed_by: String,
+    #[serde(default)]
+    pub effective_from: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    pub changes: Vec<EcoChangeRequest>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct EcoDetail {
+    #[serde(flatten)]
+    pub eco: Eco,
+    pub changes: Vec<EcoChange>,
+}
+
+/// Who proposed, approved or rejected an ECO and when, in `eco_audit.csv`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EcoAuditEntry {
+    pub eco_number: String,
+    pub timestamp: String,
+    pub action: EcoStatus,
+    pub user: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct BuildabilityDelta {
+    pub assembly_sku: String,
+    pub before: f64,
+    pub after: f64,
+}
+
+/// Stock of a component that no BOM uses once the ECO is applied.
+#[derive(Debug, serde::Serialize)]
+pub struct ObsoleteStock {
+    pub sku: String,
+    pub on_hand_qty: f64,
+    pub dropped_from: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct EcoImpact {
+    pub eco_number: String,
+    pub as_of: String,
+    pub changed_assemblies: Vec<String>,
+    /// Changed assemblies plus every assembly that uses them
+    pub affected_assemblies: Vec<String>,
+    pub buildability: Vec<BuildabilityDelta>,
+    pub obsolete_stock: Vec<ObsoleteStock>,
+}
+
+/// Files an ECO decision writes, restored together if any write fails.
+const ECO_FILES: &[&str] = &["bom_items.csv", "bom_revisions.csv", "ecos.csv", "eco_changes.csv", "eco_audit.csv"];
+
+pub fn load_ecos(data_dir: &Path) -> Result<Vec<Eco>> {
+    read_csv_if_exists::<Eco>(&data_dir.join("ecos.csv"))
+        .context("Reading ecos.csv")
+}
+
+pub fn load_eco_changes(data_dir: &Path) -> Result<Vec<EcoChange>> {
+    read_csv_if_exists::<EcoChange>(&data_dir.join("eco_changes.csv"))
+        .context("Reading eco_changes.csv")
+}
+
+pub fn load_eco_audit(data_dir: &Path) -> Result<Vec<EcoAuditEntry>> {
+    read_csv_if_exists::<EcoAuditEntry>(&data_dir.join("eco_audit.csv"))
+        .context("Reading eco_audit.csv")
+}
+
+/// Every ECO with its change lines, newest first.
+pub fn load_eco_details(data_dir: &Path) -> Result<Vec<EcoDetail>> {
+    let changes = load_eco_changes(data_dir)?;
+    let mut details: Vec<EcoDetail> = load_ecos(data_dir)?.into_iter()
+        .map(|eco| {
+            let changes = changes.iter().filter(|c| c.eco_number == eco.eco_number).cloned().collect();
+            EcoDetail { eco, changes }
+        })
+        .collect();
+    details.reverse();
+    Ok(details)
+}
+
+fn write_ecos(data_dir: &Path, ecos: &[Eco]) -> Result<()> {
+    write_csv(&data_dir.join("ecos.csv"), ecos)
+        .context("Writing ecos.csv")
+}
+
+fn audit(data_dir: &Path, eco_number: &str, action: EcoStatus, user: &str, notes: Option<String>) -> Result<()> {
+    let entry = EcoAuditEntry {
+        eco_number: eco_number.to_string(),
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        action,
+        user: user.to_string(),
+        notes,
+    };
+    append_csv(&data_dir.join("eco_audit.csv"), &[entry])
+        .context("Writing eco_audit.csv")
+}
+
+fn next_eco_number(ecos: &[Eco]) -> String {
+    let max = ecos.iter()
+        .filter_map(|e| e.eco_number.strip_prefix("ECO-").and_then(|n| n.parse::<u32>().ok()))
+        .max()
+        .unwrap_or(0);
+    format!("ECO-{:05}", max + 1)
+}
+
+/// Date an ECO is evaluated and released at: the override, the ECO's own
+/// effective date, or today.
+fn eco_date(eco: &Eco, effective_from: Option<&str>) -> Result<NaiveDate> {
+    match effective_from.or(eco.effective_from.as_deref()).filter(|d| !d.trim().is_empty()) {
+        Some(date) => parse_date(date),
+        None => Ok(chrono::Local::now().date_naive()),
+    }
+}
+
+/// Apply ECO changes to the BOM lines in force, checking each change against
+/// the lines it modifies and that the result has no cycles.
+fn apply_changes(data_dir: &Path, bom_items: &[BomItem], changes: &[EcoChange]) -> Result<Vec<BomItem>> {
+    let known = KnownSkus::load(data_dir)?;
+    let mut result = bom_items.to_vec();
+    let mut seen = HashSet::new();
+    for change in changes {
+        let parent = change.parent_assembly_sku.as_str();
+        let component = change.component_sku.as_str();
+        if !seen.insert((parent, component)) {
+            bail!("Line {}: {} on {} is changed more than once", change.line_no, component, parent);
+        }
+
+        let matches: Vec<usize> = result.iter()
+            .enumerate()
+            .filter(|(_, b)| b.parent_assembly_sku == parent && b.component_sku == component)
+            .map(|(idx, _)| idx)
+            .collect();
+        if matches.len() > 1 {
+            bail!("Line {}: {} appears {} times on {}; the change is ambiguous", change.line_no, component, matches.len(), parent);
+        }
+        let existing = matches.first().copied();
+        let line = match (change.action, existing) {
+            (EcoAction::Add, Some(_)) => {
+                bail!("Line {}: {} is already on {}; use a quantity change", change.line_no, component, parent)
+            },
+            (EcoAction::Add, None) => {
+                let qty_per = change.qty_per
+                    .ok_or_else(|| anyhow!("Line {}: quantity per is required", change.line_no))?;
+                result.push(BomItem {
+                    parent_assembly_sku: parent.to_string(),
+                    component_sku: component.to_string(),
+                    qty_per,
+                    scrap_rate: change.scrap_rate.unwrap_or(0.0),
+                    yield_pct: change.yield_pct.unwrap_or(1.0),
+                    is_phantom: change.is_phantom.unwrap_or(false),
+                    revision: None,
+                    uom: None,
+                    substitute_group: None,
+                });
+                result.last()
+            },
+            (EcoAction::Remove | EcoAction::ChangeQty, None) => {
+                bail!("Line {}: {} is not on {}", change.line_no, component, parent)
+            },
+            (EcoAction::Remove, Some(idx)) => {
+                result.remove(idx);
+                None
+            },
+            (EcoAction::ChangeQty, Some(idx)) => {
+                let line = &mut result[idx];
+                line.qty_per = change.qty_per
+                    .ok_or_else(|| anyhow!("Line {}: quantity per is required", change.line_no))?;
+                line.scrap_rate = change.scrap_rate.unwrap_or(line.scrap_rate);
+                line.yield_pct = change.yield_pct.unwrap_or(line.yield_pct);
+                line.is_phantom = change.is_phantom.unwrap_or(line.is_phantom);
+                Some(&*line)
+            },
+        };
+        if let Some(line) = line {
+            check_bom_line(line, &known).with_context(|| format!("Line {}", change.line_no))?;
+        }
+    }
+
+    for parent in changed_assemblies(changes) {
+        explode_bom(&parent, &result)
+            .with_context(|| format!("Changes to {} would break its BOM", parent))?;
+    }
+    Ok(result)
+}
+
+fn changed_assemblies(changes: &[EcoChange]) -> BTreeSet<String> {
+    changes.iter().map(|c| c.parent_assembly_sku.clone()).collect()
+}
+
+/// The data an ECO is checked against on `date`, refusing when a line of an
+/// assembly it changes had to be left out.
+fn eco_data(data_dir: &Path, date: NaiveDate, changes: &[EcoChange]) -> Result<DataSnapshot> {
+    let data = load_data_dir_as_of(data_dir, date)?;
+    for parent in changed_assemblies(changes) {
+        data.check_complete(&parent)?;
+    }
+    Ok(data)
+}
+
+/// Record a proposed ECO after checking its changes against today's BOM, or
+/// the BOM on its effective date.
+pub fn propose_eco(data_dir: &Path, request: EcoRequest) -> Result<EcoDetail> {
+    if request.title.trim().is_empty() {
+        bail!("ECO title is required");
+    }
+    if request.proposed_by.trim().is_empty() {
+        bail!("Proposer is required");
+    }
+    if request.changes.is_empty() {
+        bail!("An ECO needs at least one change");
+    }
+
+    let mut ecos = load_ecos(data_dir)?;
+    let eco = Eco {
+        eco_number: next_eco_number(&ecos),
+        title: request.title.trim().to_string(),
+        status: EcoStatus::Proposed,
+        proposed_by: request.proposed_by.trim().to_string(),
+        proposed_at: chrono::Utc::now().to_rfc3339(),
+        effective_from: request.effective_from.filter(|d| !d.trim().is_empty()),
+        decided_by: None,
+        decided_at: None,
+        new_revisions: None,
+        notes: request.notes,
+    };
+    let changes: Vec<EcoChange> = request.changes.into_iter()
+        .enumerate()
+        .map(|(idx, c)| EcoChange {
+            eco_number: eco.eco_number.clone(),
+            line_no: idx as u32 + 1,
+            parent_assembly_sku: c.parent_assembly_sku.trim().to_string(),
+            action: c.action,
+            component_sku: c.component_sku.trim().to_string(),
+            qty_per: c.qty_per,
+            scrap_rate: c.scrap_rate,
+            yield_pct: c.yield_pct,
+            is_phantom: c.is_phantom,
+        })
+        .collect();
+
+    let data = eco_data(data_dir, eco_date(&eco, None)?, &changes)?;
+    apply_changes(data_dir, &data.bom_items, &changes)?;
+
+    ecos.push(eco.clone());
+    with_rollback(data_dir, ECO_FILES, || {
+        write_ecos(data_dir, &ecos)?;
+        append_csv(&data_dir.join("eco_changes.csv"), &changes)
+            .context("Writing eco_changes.csv")?;
+        audit(data_dir, &eco.eco_number, EcoStatus::Proposed, &eco.proposed_by, None)
+    })?;
+    Ok(EcoDetail { eco, changes })
+}
+
+fn find_eco(data_dir: &Path, eco_number: &str) -> Result<(Eco, Vec<EcoChange>)> {
+    let eco = load_ecos(data_dir)?.into_iter()
+        .find(|e| e.eco_number == eco_number)
+        .ok_or_else(|| anyhow!("ECO {} not found", eco_number))?;
+    let changes = load_eco_changes(data_dir)?.into_iter()
+        .filter(|c| c.eco_number == eco_number)
+        .collect();
+    Ok((eco, changes))
+}
+
+/// What approving an ECO would do: the assemblies it touches, how many of
+/// each could be built from current stock before and after, and stocked
+/// components that no BOM would use any more.
+pub fn preview_eco(data_dir: &Path, eco_number: &str) -> Result<EcoImpact> {
+    let (eco, changes) = find_eco(data_dir, eco_number)?;
+    let date = eco_date(&eco, None)?;
+    let data = eco_data(data_dir, date, &changes)?;
+    let after = apply_changes(data_dir, &data.bom_items, &changes)?;
+
+    let changed = changed_assemblies(&changes);
+    let mut affected = changed.clone();
+    for parent in &changed {
+        affected.extend(ancestors(parent, &data.bom_items));
+        affected.extend(ancestors(parent, &after));
+    }
+
+    let available = available_stock(&data.stock);
+    let mut buildability = Vec::new();
+    for sku in &affected {
+        buildability.push(BuildabilityDelta {
+            assembly_sku: sku.clone(),
+            before: max_buildable(&explode_bom(sku, &data.bom_items)?, &available),
+            after: max_buildable(&explode_bom(sku, &after)?, &available),
+        });
+    }
+
+    let still_used: HashSet<&str> = after.iter().map(|b| b.component_sku.as_str()).collect();
+    let mut dropped: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for item in &data.bom_items {
+        if !still_used.contains(item.component_sku.as_str()) {
+            dropped.entry(item.component_sku.as_str()).or_default().push(item.parent_assembly_sku.clone());
+        }
+    }
+    let obsolete_stock = dropped.into_iter()
+        .filter_map(|(sku, dropped_from)| {
+            let on_hand_qty = data.stock.iter().find(|s| s.sku == sku).map(|s| s.on_hand_qty).unwrap_or(0.0);
+            (on_hand_qty > 0.0).then(|| ObsoleteStock { sku: sku.to_string(), on_hand_qty, dropped_from })
+        })
+        .collect();
+
+    Ok(EcoImpact {
+        eco_number: eco.eco_number,
+        as_of: date.format("%Y-%m-%d").to_string(),
+        changed_assemblies: changed.into_iter().collect(),
+        affected_assemblies: affected.into_iter().collect(),
+        buildability,
+        obsolete_stock,
+    })
+}
+
+/// Next revision code after `last`: numeric suffixes count up, letters run
+/// A..Z then AA, and anything else gets a "1" appended.
+fn increment_revision(last: &str) -> String {
+    let digits = last.chars().rev().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        let (prefix, number) = last.split_at(last.len() - digits);
+        let next = number.parse::<u64>().unwrap_or(0) + 1;
+        return format!("{}{:0width$}", prefix, next, width = digits);
+    }
+    if !last.is_empty() && last.chars().all(|c| c.is_ascii_uppercase()) {
+        let mut chars: Vec<u8> = last.bytes().collect();
+        for idx in (0..chars.len()).rev() {
+            if chars[idx] < b'Z' {
+                chars[idx] += 1;
+                return String::from_utf8(chars).unwrap_or_default();
+            }
+            chars[idx] = b'A';
+        }
+        chars.insert(0, b'A');
+        return String::from_utf8(chars).unwrap_or_default();
+    }
+    format!("{}1", last)
+}
+
+fn next_revision_code(revisions: &[BomRevision], assembly_sku: &str) -> String {
+    let existing: Vec<&str> = revisions.iter()
+        .filter(|r| r.assembly_sku == assembly_sku)
+        .map(|r| r.revision.as_str())
+        .collect();
+    let mut code = existing.last().map(|last| increment_revision(last)).unwrap_or_else(|| "A".to_string());
+    while existing.contains(&code.as_str()) {
+        code = increment_revision(&code);
+    }
+    code
+}
+
+/// Approve a proposed ECO: re-check it against the BOM on its effective date,
+/// release a new revision of each changed assembly carrying the changes, and
+/// record the approver.
+pub fn approve_eco(
+    data_dir: &Path,
+    eco_number: &str,
+    approved_by: &str,
+    effective_from: Option<String>,
+) -> Result<EcoDetail> {
+    if approved_by.trim().is_empty() {
+        bail!("Approver is required");
+    }
+    let (eco, changes) = find_eco(data_dir, eco_number)?;
+    if eco.status != EcoStatus::Proposed {
+        bail!("ECO {} is already {:?}", eco_number, eco.status);
+    }
+    let date = eco_date(&eco, effective_from.as_deref())?;
+    let effective = date.format("%Y-%m-%d").to_string();
+    let data = eco_data(data_dir, date, &changes)?;
+    let after = apply_changes(data_dir, &data.bom_items, &changes)?;
+
+    // Everything is checked and staged before the first write
+    let mut bom_items = load_bom_items(data_dir)?;
+    let revisions = load_bom_revisions(data_dir)?;
+    let mut ecos = load_ecos(data_dir)?;
+    let mut created = Vec::new();
+    for parent in changed_assemblies(&changes) {
+        let code = next_revision_code(&revisions, &parent);
+        let lines: Vec<BomItem> = after.iter()
+            .filter(|b| b.parent_assembly_sku == parent)
+            .map(|b| BomItem { revision: Some(code.clone()), ..b.clone() })
+            .collect();
+        if lines.is_empty() {
+            bail!("ECO {} would leave {} with no BOM lines", eco_number, parent);
+        }
+        bom_items.extend(lines);
+        created.push((parent, code));
+    }
+
+    let approved_by = approved_by.trim().to_string();
+    let stored = ecos.iter_mut()
+        .find(|e| e.eco_number == eco_number)
+        .ok_or_else(|| anyhow!("ECO {} not found", eco_number))?;
+    stored.status = EcoStatus::Approved;
+    stored.effective_from = Some(effective.clone());
+    stored.decided_by = Some(approved_by.clone());
+    stored.decided_at = Some(chrono::Utc::now().to_rfc3339());
+    stored.new_revisions = Some(created.iter()
+        .map(|(parent, code)| format!("{}:{}", parent, code))
+        .collect::<Vec<_>>()
+        .join(";"));
+    let eco = stored.clone();
+
+    with_rollback(data_dir, ECO_FILES, || {
+        write_bom_items(data_dir, &bom_items)?;
+        for (parent, code) in &created {
+            save_bom_revision(data_dir, BomRevision {
+                assembly_sku: parent.clone(),
+                revision: code.clone(),
+                effective_from: None,
+                effective_to: None,
+                status: RevisionStatus::Draft,
+                notes: Some(format!("{}: {}", eco.eco_number, eco.title)),
+            })?;
+            release_bom_revision(data_dir, parent, code, Some(effective.clone()))?;
+        }
+        write_ecos(data_dir, &ecos)?;
+        audit(data_dir, eco_number, EcoStatus::Approved, &approved_by, eco.new_revisions.clone())
+    })?;
+    Ok(EcoDetail { eco, changes })
+}
+
+/// Reject a proposed ECO, leaving the BOM untouched.
+pub fn reject_eco(data_dir: &Path, eco_number: &str, rejected_by: &str, reason: Option<String>) -> Result<EcoDetail> {
+    if rejected_by.trim().is_empty() {
+        bail!("Reviewer is required");
+    }
+    let (_, changes) = find_eco(data_dir, eco_number)?;
+    let mut ecos = load_ecos(data_dir)?;
+    let stored = ecos.iter_mut()
+        .find(|e| e.eco_number == eco_number)
+        .ok_or_else(|| anyhow!("ECO {} not found", eco_number))?;
+    if stored.status != EcoStatus::Proposed {
+        bail!("ECO {} is already {:?}", eco_number, stored.status);
+    }
+    stored.status = EcoStatus::Rejected;
+    stored.decided_by = Some(rejected_by.trim().to_string());
+    stored.decided_at = Some(chrono::Utc::now().to_rfc3339());
+    let eco = stored.clone();
+    with_rollback(data_dir, ECO_FILES, || {
+        write_ecos(data_dir, &ecos)?;
+        audit(data_dir, eco_number, EcoStatus::Rejected, rejected_by.trim(), reason)
+    })?;
+    Ok(EcoDetail { eco, changes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    fn fixture() -> TestDir {
+        let dir = TestDir::new();
+        dir.write("assemblies.csv", "assembly_sku,name,uom,active\nA,Assembly,ea,true\n");
+        dir.write("parts.csv", "part_sku,name,uom\nP1,Part one,ea\nP2,Part two,ea\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\nA,P1,2,0,1,false\n");
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,10,0\nP2,3,0\n");
+        dir
+    }
+
+    fn change(action: EcoAction, component: &str, qty_per: Option<f64>) -> EcoChangeRequest {
+        EcoChangeRequest {
+            parent_assembly_sku: "A".to_string(),
+            action,
+            component_sku: component.to_string(),
+            qty_per,
+            scrap_rate: None,
+            yield_pct: None,
+            is_phantom: None,
+        }
+    }
+
+    fn request(changes: Vec<EcoChangeRequest>) -> EcoRequest {
+        EcoRequest {
+            title: "Swap P1 for P2".to_string(),
+            proposed_by: "dana".to_string(),
+            effective_from: Some("2024-03-01".to_string()),
+            notes: None,
+            changes,
+        }
+    }
+
+    #[test]
+    fn increment_revision_counts_digits_and_letters() {
+        assert_eq!(increment_revision("A"), "B");
+        assert_eq!(increment_revision("Z"), "AA");
+        assert_eq!(increment_revision("AZ"), "BA");
+        assert_eq!(increment_revision("ZZ"), "AAA");
+        assert_eq!(increment_revision("R1"), "R2");
+        assert_eq!(increment_revision("R09"), "R10");
+        assert_eq!(increment_revision("rev"), "rev1");
+    }
+
+    #[test]
+    fn next_revision_code_skips_existing_codes() {
+        let rev = |code: &str| BomRevision {
+            assembly_sku: "A".to_string(),
+            revision: code.to_string(),
+            effective_from: None,
+            effective_to: None,
+            status: RevisionStatus::Draft,
+            notes: None,
+        };
+        assert_eq!(next_revision_code(&[], "A"), "A");
+        assert_eq!(next_revision_code(&[rev("C"), rev("A")], "A"), "B");
+        assert_eq!(next_revision_code(&[rev("B"), rev("A")], "A"), "C");
+    }
+
+    #[test]
+    fn proposal_is_checked_against_the_bom() {
+        let dir = fixture();
+        let err = propose_eco(dir.path(), request(vec![change(EcoAction::Remove, "P2", None)])).unwrap_err();
+        assert!(err.to_string().contains("is not on A"));
+
+        let err = propose_eco(dir.path(), request(vec![
+            change(EcoAction::ChangeQty, "P1", Some(3.0)),
+            change(EcoAction::Remove, "P1", None),
+        ])).unwrap_err();
+        assert!(err.to_string().contains("changed more than once"));
+        assert!(!dir.path().join("ecos.csv").exists());
+    }
+
+    #[test]
+    fn duplicate_bom_lines_make_a_change_ambiguous() {
+        let dir = fixture();
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\n\
+            A,P1,2,0,1,false\nA,P1,1,0,1,false\n");
+        let err = propose_eco(dir.path(), request(vec![change(EcoAction::ChangeQty, "P1", Some(3.0))])).unwrap_err();
+        assert!(err.to_string().contains("ambiguous"));
+    }
+
+    #[test]
+    fn approval_releases_a_revision_with_the_changes() {
+        let dir = fixture();
+        let proposed = propose_eco(dir.path(), request(vec![
+            change(EcoAction::Remove, "P1", None),
+            change(EcoAction::Add, "P2", Some(1.0)),
+        ])).unwrap();
+        assert_eq!(proposed.eco.eco_number, "ECO-00001");
+
+        let impact = preview_eco(dir.path(), "ECO-00001").unwrap();
+        let delta = &impact.buildability[0];
+        assert_eq!((delta.before, delta.after), (5.0, 3.0));
+        assert_eq!(impact.obsolete_stock[0].sku, "P1");
+
+        let approved = approve_eco(dir.path(), "ECO-00001", "lee", None).unwrap();
+        assert_eq!(approved.eco.status, EcoStatus::Approved);
+        assert_eq!(approved.eco.new_revisions.as_deref(), Some("A:A"));
+
+        let data = load_data_dir_as_of(dir.path(), parse_date("2024-03-01").unwrap()).unwrap();
+        let lines: Vec<&str> = data.bom_items.iter().map(|b| b.component_sku.as_str()).collect();
+        assert_eq!(lines, vec!["P2"]);
+        let before = load_data_dir_as_of(dir.path(), parse_date("2024-02-29").unwrap()).unwrap();
+        assert_eq!(before.bom_items[0].component_sku, "P1");
+
+        assert!(approve_eco(dir.path(), "ECO-00001", "lee", None).is_err());
+        let actions: Vec<EcoStatus> = load_eco_audit(dir.path()).unwrap().iter().map(|a| a.action).collect();
+        assert_eq!(actions, vec![EcoStatus::Proposed, EcoStatus::Approved]);
+    }
+
+    #[test]
+    fn failed_approval_leaves_every_file_as_it_was() {
+        let dir = fixture();
+        propose_eco(dir.path(), request(vec![change(EcoAction::ChangeQty, "P1", Some(3.0))])).unwrap();
+        // An audit file that cannot be migrated fails the last write
+        let audit = "eco_number,timestamp,action,user\nECO-00001,2024-01-01T00:00:00Z,proposed,dana,extra\n";
+        dir.write("eco_audit.csv", audit);
+        let files = ["bom_items.csv", "ecos.csv", "eco_changes.csv"].map(|f| (f, dir.read(f)));
+
+        assert!(approve_eco(dir.path(), "ECO-00001", "lee", None).is_err());
+        for (name, contents) in files {
+            assert_eq!(dir.read(name), contents, "{} changed", name);
+        }
+        assert!(!dir.path().join("bom_revisions.csv").exists());
+        assert_eq!(dir.read("eco_audit.csv"), audit);
+    }
+
+    #[test]
+    fn corrupt_ecos_file_is_not_overwritten() {
+        let dir = fixture();
+        let corrupt = "eco_number,title,status,proposed_by,proposed_at\nECO-00001,Broken,unknown,dana,now\n";
+        dir.write("ecos.csv", corrupt);
+
+        assert!(load_eco_details(dir.path()).is_err());
+        assert!(propose_eco(dir.path(), request(vec![change(EcoAction::ChangeQty, "P1", Some(3.0))])).is_err());
+        assert_eq!(dir.read("ecos.csv"), corrupt);
+    }
+}
@@ -0,0 +1,268 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::bom::load_bom_tree;
+use crate::costs::{build_cost_rows, CostMethod};
+use crate::history::HistoryQuery;
+use crate::valuation::{inventory_valuation, ValuationMethod, ValuationTotal};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Markdown,
+}
+
+/// Rows as CSV text with a header line.
+pub fn render_csv<T: serde::Serialize>(rows: &[T]) -> Result<String> {
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        wtr.serialize(row).context("Failed to serialize CSV row")?;
+    }
+    let bytes = wtr.into_inner().context("Failed to serialize CSV")?;
+    String::from_utf8(bytes).context("CSV output is not UTF-8")
+}
+
+/// A Markdown table. Pipes in cell text are escaped so columns stay aligned.
+pub fn render_markdown(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let escape = |cell: &str| cell.replace('|', "\\|");
+    let mut out = format!("| {} |\n", headers.join(" | "));
+    out += &format!("|{}\n", headers.iter().map(|_| " --- |").collect::<String>());
+    for row in rows {
+        out += &format!("| {} |\n", row.iter().map(|c| escape(c)).collect::<Vec<_>>().join(" | "));
+    }
+    out
+}
+
+/// Write exported text to `output_path` when one is given, and return it.
+pub fn finish_export(content: String, output_path: Option<&str>) -> Result<String> {
+    if let Some(path) = output_path.filter(|p| !p.trim().is_empty()) {
+        std::fs::write(path, &content)
+            .with_context(|| format!("Failed to write export to {}", path))?;
+    }
+    Ok(content)
+}
+
+/// Format a quantity without trailing zeros for printed reports.
+fn qty(value: f64) -> String {
+    let rounded = format!("{:.4}", value);
+    rounded.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Indented BOM for `quantity` units of an assembly, with the BOM in effect
+/// on `bom_date`, as CSV or as a Markdown build-packet table. Markdown levels
+/// use the dotted convention (".1", "..2") so the nesting survives any viewer.
+pub fn export_bom_tree(
+    data_dir: &Path,
+    assembly_sku: &str,
+    quantity: f64,
+    bom_date: chrono::NaiveDate,
+    format: ExportFormat,
+) -> Result<String> {
+    let rows = load_bom_tree(data_dir, assembly_sku, quantity, bom_date)?;
+    match format {
+        ExportFormat::Csv => render_csv(&rows),
+        ExportFormat::Markdown => {
+            let table: Vec<Vec<String>> = rows.iter()
+                .map(|r| vec![
+                    format!("{}{}", ".".repeat(r.level), r.level),
+                    r.component_sku.clone(),
+                    r.name.clone(),
+                    r.parent_assembly_sku.clone(),
+                    qty(r.qty_per),
+                    qty(r.scrap_rate),
+                    qty(r.yield_pct),
+                    if r.is_phantom { "yes".to_string() } else { String::new() },
+                    qty(r.extended_qty),
+                ])
+                .collect();
+            Ok(format!(
+                "# {} × {}\n\nBOM as of {}\n\n{}",
+                assembly_sku,
+                qty(quantity),
+                bom_date.format("%Y-%m-%d"),
+                render_markdown(
+                    &["Level", "Component", "Name", "Parent", "Qty per", "Scrap", "Yield", "Phantom", "Extended qty"],
+                    &table,
+                ),
+            ))
+        },
+    }
+}
+
+fn money(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+/// Build history matching `query` with each build's material cost, as CSV or
+/// as a Markdown table ending in a totals row.
+pub fn export_build_history(
+    data_dir: &Path,
+    query: &HistoryQuery,
+    method: CostMethod,
+    format: ExportFormat,
+) -> Result<String> {
+    let (rows, skipped, skipped_lines) = build_cost_rows(data_dir, query, method)?;
+    match format {
+        ExportFormat::Csv => render_csv(&rows),
+        ExportFormat::Markdown => {
+            let mut table: Vec<Vec<String>> = rows.iter()
+                .map(|r| vec![
+                    r.timestamp.get(..10).unwrap_or(&r.timestamp).to_string(),
+                    r.work_order.clone(),
+                    r.sales_order.clone(),
+                    r.customer.clone(),
+                    r.assembly_sku.clone(),
+                    qty(r.quantity_built),
+                    r.unit_material_cost.map(money).unwrap_or_default(),
+                    r.material_cost.map(money).unwrap_or_default(),
+                ])
+                .collect();
+            table.push(vec![
+                "**Total**".to_string(),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                qty(rows.iter().map(|r| r.quantity_built).sum()),
+                String::new(),
+                money(rows.iter().filter_map(|r| r.material_cost).sum()),
+            ]);
+            let missing = rows.iter().filter(|r| r.missing_costs.is_some()).count();
+            let mut note = String::new();
+            if missing > 0 {
+                note.push_str(&format!("\n\n{} builds use parts without a cost; their material cost is understated.", missing));
+            }
+            if !skipped.is_empty() {
+                let lines: Vec<String> = skipped.iter().map(|s| s.line.to_string()).collect();
+                note.push_str(&format!("\n\nUnreadable history rows left out (lines {}).", lines.join(", ")));
+            }
+            for reason in &skipped_lines {
+                note.push_str(&format!("\n\nLeft out of material costs: {}.", reason));
+            }
+            Ok(format!(
+                "# Build history\n\nMaterial at {} cost\n\n{}{}",
+                match method {
+                    CostMethod::Standard => "standard",
+                    CostMethod::Average => "average",
+                },
+                render_markdown(
+                    &["Date", "Work order", "Sales order", "Customer", "Assembly", "Qty", "Unit material cost", "Material cost"],
+                    &table,
+                ),
+                note,
+            ))
+        },
+    }
+}
+
+/// Inventory valuation as CSV (one row per SKU) or as a Markdown report with
+/// the per-SKU table followed by category and supplier totals.
+pub fn export_inventory_valuation(
+    data_dir: &Path,
+    method: ValuationMethod,
+    as_of: chrono::NaiveDate,
+    format: ExportFormat,
+) -> Result<String> {
+    let valuation = inventory_valuation(data_dir, method, as_of)?;
+    match format {
+        ExportFormat::Csv => render_csv(&valuation.lines),
+        ExportFormat::Markdown => {
+            let lines: Vec<Vec<String>> = valuation.lines.iter()
+                .map(|l| vec![
+                    l.sku.clone(),
+                    l.name.clone(),
+                    l.category.clone().unwrap_or_default(),
+                    l.supplier.clone().unwrap_or_default(),
+                    qty(l.on_hand_qty),
+                    l.unit_cost.map(money).unwrap_or_default(),
+                    l.value.map(money).unwrap_or_default(),
+                ])
+                .collect();
+            let totals = |rows: &[ValuationTotal]| -> Vec<Vec<String>> {
+                rows.iter()
+                    .map(|t| vec![t.key.clone(), t.sku_count.to_string(), qty(t.on_hand_qty), money(t.value)])
+                    .collect()
+            };
+            let mut out = format!(
+                "# Inventory valuation\n\nAs of {} ({}), total {}\n\n{}",
+                valuation.as_of,
+                match method {
+                    ValuationMethod::Fifo => "FIFO",
+                    ValuationMethod::WeightedAverage => "weighted average",
+                    ValuationMethod::Standard => "standard cost",
+                },
+                money(valuation.total_value),
+                render_markdown(&["SKU", "Name", "Category", "Supplier", "On hand", "Unit cost", "Value"], &lines),
+            );
+            out += &format!("\n## By category\n\n{}",
+                render_markdown(&["Category", "SKUs", "On hand", "Value"], &totals(&valuation.by_category)));
+            out += &format!("\n## By supplier\n\n{}",
+                render_markdown(&["Supplier", "SKUs", "On hand", "Value"], &totals(&valuation.by_supplier)));
+            if !valuation.unvalued_skus.is_empty() {
+                out += &format!("\nNo cost known for {}; left out of the totals.\n", valuation.unvalued_skus.join(", "));
+            }
+            Ok(out)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    fn fixture() -> TestDir {
+        let dir = TestDir::new();
+        dir.write("assemblies.csv", "assembly_sku,name,uom\nA,Assembly,ea\nSUB,Sub-assembly,ea\n");
+        dir.write("parts.csv", "part_sku,name,uom\nP1,Part one,ea\nP2,Part | two,ea\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\n\
+            A,SUB,2,0,1,true\nA,P1,1.5,0,1,false\nSUB,P2,3,0,1,false\n");
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,10,0\n");
+        dir
+    }
+
+    fn date() -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()
+    }
+
+    #[test]
+    fn quantities_drop_trailing_zeros() {
+        assert_eq!(qty(2.0), "2");
+        assert_eq!(qty(1.25), "1.25");
+        assert_eq!(qty(0.33333), "0.3333");
+    }
+
+    #[test]
+    fn markdown_escapes_pipes() {
+        let table = render_markdown(&["SKU", "Name"], &[vec!["P2".to_string(), "Part | two".to_string()]]);
+        assert_eq!(table, "| SKU | Name |\n| --- | --- |\n| P2 | Part \\| two |\n");
+    }
+
+    #[test]
+    fn bom_tree_exports_as_csv_and_markdown() {
+        let dir = fixture();
+        let csv = export_bom_tree(dir.path(), "A", 2.0, date(), ExportFormat::Csv).unwrap();
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("level,"));
+        assert_eq!(lines.count(), 3);
+
+        let markdown = export_bom_tree(dir.path(), "A", 2.0, date(), ExportFormat::Markdown).unwrap();
+        assert!(markdown.starts_with("# A × 2\n\nBOM as of 2024-06-01\n"));
+        // Dotted levels, phantom flag and extended quantities for 2 units
+        assert!(markdown.contains("| .1 | SUB | Sub-assembly | A | 2 | 0 | 1 | yes | 4 |"));
+        assert!(markdown.contains("| ..2 | P2 | Part \\| two | SUB | 3 | 0 | 1 |  | 12 |"));
+        assert!(markdown.contains("| .1 | P1 | Part one | A | 1.5 | 0 | 1 |  | 3 |"));
+    }
+
+    #[test]
+    fn finish_export_writes_only_with_a_path() {
+        let dir = TestDir::new();
+        assert_eq!(finish_export("text".to_string(), None).unwrap(), "text");
+        assert_eq!(finish_export("text".to_string(), Some(" ")).unwrap(), "text");
+
+        let path = dir.path().join("out.md");
+        finish_export("report".to_string(), path.to_str()).unwrap();
+        assert_eq!(dir.read("out.md"), "report");
+    }
+}
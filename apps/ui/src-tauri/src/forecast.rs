@@ -0,0 +1,406 @@
+use anyhow::Result;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::bom::explode_bom;
+use crate::data::{load_data_dir, load_inventory, InventoryItem};
+use crate::history::{consumption_history, production_analytics, Period, SkippedRow};
+use crate::purchasing::{apply_on_order, load_suppliers};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForecastMethod {
+    #[default]
+    MovingAverage,
+    ExponentialSmoothing,
+    SeasonalNaive,
+}
+
+/// Forecast settings; every field has a sensible default.
+#[derive(Debug, serde::Deserialize)]
+#[serde(default)]
+pub struct ForecastRequest {
+    pub period: Period,
+    pub method: ForecastMethod,
+    /// Number of future periods to forecast
+    pub horizon: usize,
+    /// Periods averaged by the moving average
+    pub window: usize,
+    /// Smoothing factor for exponential smoothing, 0..=1
+    pub alpha: f64,
+    /// Periods per season for seasonal naive; defaults to a year
+    pub season_length: Option<usize>,
+    /// Lead time for parts whose supplier has none on file
+    pub default_lead_time_days: f64,
+    /// Safety stock in standard deviations of per-period demand (1.65 ≈ 95% service)
+    pub safety_z: f64,
+}
+
+impl Default for ForecastRequest {
+    fn default() -> Self {
+        ForecastRequest {
+            period: Period::Week,
+            method: ForecastMethod::MovingAverage,
+            horizon: 4,
+            window: 4,
+            alpha: 0.3,
+            season_length: None,
+            default_lead_time_days: 14.0,
+            safety_z: 1.65,
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct AssemblyForecast {
+    pub assembly_sku: String,
+    pub history: Vec<f64>,
+    pub forecast: Vec<f64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ComponentForecast {
+    pub sku: String,
+    pub forecast: Vec<f64>,
+    pub total: f64,
+}
+
+/// Reorder point covering forecast demand over the supplier lead time plus
+/// safety stock for the variability seen in consumption history.
+#[derive(Debug, serde::Serialize)]
+pub struct ReorderSuggestion {
+    pub sku: String,
+    pub daily_demand: f64,
+    pub lead_time_days: f64,
+    pub safety_stock: f64,
+    pub suggested_reorder_point: f64,
+    pub current_reorder_point: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct DemandForecast {
+    pub period: Period,
+    pub method: ForecastMethod,
+    pub history_periods: Vec<String>,
+    pub forecast_periods: Vec<String>,
+    pub assemblies: Vec<AssemblyForecast>,
+    pub components: Vec<ComponentForecast>,
+    pub reorder_points: Vec<ReorderSuggestion>,
+    /// History rows left out of the forecast because they could not be read
+    pub skipped_rows: Vec<SkippedRow>,
+    /// BOM lines left out of the consumption history because their unit cannot be converted
+    pub skipped_lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverageSortField {
+    #[default]
+    DaysOfCover,
+    StockoutDate,
+    DailyConsumption,
+    AvailableQty,
+    Sku,
+}
+
+/// Inventory item with its recent consumption rate and projected stockout.
+/// Parts with no consumption in the lookback window have no cover or stockout.
+#[derive(Debug, serde::Serialize)]
+pub struct InventoryCoverage {
+    #[serde(flatten)]
+    pub item: InventoryItem,
+    pub daily_consumption: f64,
+    pub days_of_cover: Option<f64>,
+    pub stockout_date: Option<String>,
+}
+
+/// Days of cover per item, with the history rows and BOM lines that were
+/// left out of the consumption it is based on.
+#[derive(Debug, serde::Serialize)]
+pub struct CoverageReport {
+    pub items: Vec<InventoryCoverage>,
+    pub skipped_rows: Vec<SkippedRow>,
+    pub skipped_lines: Vec<String>,
+}
+
+/// Flat forecast at the mean of the last `window` values.
+pub fn moving_average(history: &[f64], window: usize, horizon: usize) -> Vec<f64> {
+    let window = window.max(1).min(history.len().max(1));
+    let tail = &history[history.len().saturating_sub(window)..];
+    let level = if tail.is_empty() { 0.0 } else { tail.iter().sum::<f64>() / tail.len() as f64 };
+    vec![level; horizon]
+}
+
+/// Flat forecast at the exponentially smoothed level.
+pub fn exponential_smoothing(history: &[f64], alpha: f64, horizon: usize) -> Vec<f64> {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let level = history.iter().skip(1)
+        .fold(history.first().copied().unwrap_or(0.0), |level, x| alpha * x + (1.0 - alpha) * level);
+    vec![level; horizon]
+}
+
+/// Repeat the last full season. Falls back to a moving average over one
+/// season when there is not yet a full season of history.
+pub fn seasonal_naive(history: &[f64], season_length: usize, horizon: usize) -> Vec<f64> {
+    let season_length = season_length.max(1);
+    if history.len() < season_length {
+        return moving_average(history, season_length, horizon);
+    }
+    let season = &history[history.len() - season_length..];
+    (0..horizon).map(|k| season[k % season_length]).collect()
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    var.sqrt()
+}
+
+/// Forecast units per assembly from `panel_history.csv`, explode the forecast
+/// into component demand, and suggest reorder points for inventory items.
+pub fn forecast_demand(data_dir: &Path, request: &ForecastRequest) -> Result<DemandForecast> {
+    let period = request.period;
+    // History runs up to today so recent quiet periods count as zero demand
+    let today = chrono::Local::now().date_naive();
+    let today_str = Some(today.format("%Y-%m-%d").to_string());
+    let production = production_analytics(data_dir, period, None, today_str.clone(), Some(1))?;
+    let consumption = consumption_history(data_dir, period, None, today_str, None)?;
+    let data = load_data_dir(data_dir)?;
+
+    let season_length = request.season_length.unwrap_or_else(|| period.season_length());
+    let mut forecast_periods = Vec::new();
+    let mut next = period.next(period.start_of(today));
+    for _ in 0..request.horizon {
+        forecast_periods.push(next.format("%Y-%m-%d").to_string());
+        next = period.next(next);
+    }
+
+    let mut assemblies = Vec::new();
+    let mut component_demand: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    for series in production.series {
+        let forecast = match request.method {
+            ForecastMethod::MovingAverage => moving_average(&series.units, request.window, request.horizon),
+            ForecastMethod::ExponentialSmoothing => exponential_smoothing(&series.units, request.alpha, request.horizon),
+            ForecastMethod::SeasonalNaive => seasonal_naive(&series.units, season_length, request.horizon),
+        };
+        if data.bom_items.iter().any(|b| b.parent_assembly_sku == series.assembly_sku) {
+            for (sku, per_unit) in explode_bom(&series.assembly_sku, &data.bom_items)? {
+                let demand = component_demand.entry(sku).or_insert_with(|| vec![0.0; request.horizon]);
+                for (slot, units) in demand.iter_mut().zip(&forecast) {
+                    *slot += units * per_unit;
+                }
+            }
+        }
+        assemblies.push(AssemblyForecast {
+            assembly_sku: series.assembly_sku,
+            history: series.units,
+            forecast,
+        });
+    }
+
+    let history_by_sku: HashMap<&str, &[f64]> = consumption.components.iter()
+        .map(|c| (c.sku.as_str(), c.quantities.as_slice()))
+        .collect();
+    let lead_times: HashMap<String, f64> = load_suppliers(data_dir)?.into_iter()
+        .filter_map(|s| s.lead_time_days.map(|d| (s.supplier_id, d as f64)))
+        .collect();
+
+    let mut reorder_points = Vec::new();
+    for item in load_inventory(data_dir)? {
+        let Some(demand) = component_demand.get(&item.sku) else {
+            continue;
+        };
+        let per_period = if demand.is_empty() { 0.0 } else { demand.iter().sum::<f64>() / demand.len() as f64 };
+        let daily_demand = per_period / period.days();
+        let lead_time_days = item.supplier.as_ref()
+            .and_then(|s| lead_times.get(s).copied())
+            .unwrap_or(request.default_lead_time_days);
+        let sigma = history_by_sku.get(item.sku.as_str()).map(|h| std_dev(h)).unwrap_or(0.0);
+        let safety_stock = request.safety_z * sigma * (lead_time_days / period.days()).sqrt();
+        reorder_points.push(ReorderSuggestion {
+            suggested_reorder_point: (daily_demand * lead_time_days + safety_stock).ceil(),
+            sku: item.sku,
+            daily_demand,
+            lead_time_days,
+            safety_stock,
+            current_reorder_point: item.reorder_point,
+        });
+    }
+
+    Ok(DemandForecast {
+        period,
+        method: request.method,
+        history_periods: production.periods,
+        forecast_periods,
+        assemblies,
+        components: component_demand.into_iter()
+            .map(|(sku, forecast)| ComponentForecast { total: forecast.iter().sum(), sku, forecast })
+            .collect(),
+        reorder_points,
+        skipped_rows: production.skipped_rows,
+        skipped_lines: consumption.skipped_lines,
+    })
+}
+
+/// Days of cover per inventory item from average daily consumption over the
+/// last `lookback_days` of build history. Sorted shortest cover first by
+/// default; items that never run out sort last either way.
+pub fn inventory_coverage(
+    data_dir: &Path,
+    lookback_days: u32,
+    sort_by: CoverageSortField,
+    descending: bool,
+) -> Result<CoverageReport> {
+    let lookback_days = lookback_days.max(1);
+    let today = chrono::Local::now().date_naive();
+    let from = today - chrono::Duration::days(lookback_days as i64 - 1);
+    let consumption = consumption_history(
+        data_dir,
+        Period::Day,
+        Some(from.format("%Y-%m-%d").to_string()),
+        Some(today.format("%Y-%m-%d").to_string()),
+        None,
+    )?;
+    let daily: HashMap<String, f64> = consumption.components.iter()
+        .map(|c| (c.sku.clone(), c.total / lookback_days as f64))
+        .collect();
+
+    let mut items = load_inventory(data_dir)?;
+    apply_on_order(data_dir, &mut items);
+    let mut coverage: Vec<InventoryCoverage> = items.into_iter()
+        .map(|item| {
+            let daily_consumption = daily.get(&item.sku).copied().unwrap_or(0.0);
+            let days_of_cover = (daily_consumption > 0.0)
+                .then(|| item.available_qty.max(0.0) / daily_consumption);
+            let stockout_date = days_of_cover
+                .map(|days| (today + chrono::Duration::days(days.floor() as i64)).format("%Y-%m-%d").to_string());
+            InventoryCoverage { item, daily_consumption, days_of_cover, stockout_date }
+        })
+        .collect();
+
+    coverage.sort_by(|a, b| {
+        let ord = match sort_by {
+            CoverageSortField::DaysOfCover => return cmp_cover(a.days_of_cover, b.days_of_cover, descending),
+            CoverageSortField::StockoutDate => return cmp_cover(
+                a.days_of_cover.map(f64::floor),
+                b.days_of_cover.map(f64::floor),
+                descending,
+            ),
+            CoverageSortField::DailyConsumption => a.daily_consumption.total_cmp(&b.daily_consumption),
+            CoverageSortField::AvailableQty => a.item.available_qty.total_cmp(&b.item.available_qty),
+            CoverageSortField::Sku => a.item.sku.cmp(&b.item.sku),
+        };
+        if descending { ord.reverse() } else { ord }
+    });
+    Ok(CoverageReport {
+        items: coverage,
+        skipped_rows: consumption.skipped_rows,
+        skipped_lines: consumption.skipped_lines,
+    })
+}
+
+/// Order by cover, keeping items without a stockout at the end.
+fn cmp_cover(a: Option<f64>, b: Option<f64>, descending: bool) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    match (a, b) {
+        (Some(a), Some(b)) if descending => b.total_cmp(&a),
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    /// Timestamp `days_ago` days before today, at noon UTC.
+    fn days_ago(days_ago: i64) -> String {
+        let date = chrono::Local::now().date_naive() - chrono::Duration::days(days_ago);
+        format!("{}T12:00:00Z", date.format("%Y-%m-%d"))
+    }
+
+    fn fixture(builds: &[(i64, f64)]) -> TestDir {
+        let dir = TestDir::new();
+        dir.write("assemblies.csv", "assembly_sku,name,uom\nA,Assembly,ea\n");
+        dir.write("parts.csv", "part_sku,name,uom\nP1,Part one,ea\nP2,Part two,ea\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\nA,P1,2,0,1,false\n");
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,40,10\nP2,5,0\n");
+        let mut history = "id,timestamp,work_order,sales_order,customer,assembly_sku,quantity_built\n".to_string();
+        for (idx, (ago, qty)) in builds.iter().enumerate() {
+            history.push_str(&format!("B{idx},{},WO-1,SO-1,Beacon,A,{qty}\n", days_ago(*ago)));
+        }
+        dir.write("panel_history.csv", &history);
+        dir
+    }
+
+    #[test]
+    fn moving_average_uses_the_last_window() {
+        assert_eq!(moving_average(&[1.0, 2.0, 6.0, 4.0], 2, 3), vec![5.0; 3]);
+        assert_eq!(moving_average(&[3.0], 4, 2), vec![3.0; 2]);
+        assert_eq!(moving_average(&[], 4, 1), vec![0.0]);
+    }
+
+    #[test]
+    fn exponential_smoothing_weights_recent_values() {
+        assert_eq!(exponential_smoothing(&[10.0, 20.0], 0.5, 1), vec![15.0]);
+        assert_eq!(exponential_smoothing(&[10.0, 20.0], 1.0, 2), vec![20.0; 2]);
+        assert_eq!(exponential_smoothing(&[10.0, 20.0], 0.0, 1), vec![10.0]);
+    }
+
+    #[test]
+    fn seasonal_naive_repeats_the_last_season() {
+        assert_eq!(seasonal_naive(&[1.0, 2.0, 3.0, 4.0, 5.0], 2, 3), vec![4.0, 5.0, 4.0]);
+        // Less than a season of history falls back to its average
+        assert_eq!(seasonal_naive(&[2.0, 4.0], 3, 2), vec![3.0; 2]);
+    }
+
+    #[test]
+    fn std_dev_is_the_sample_deviation() {
+        assert_eq!(std_dev(&[5.0]), 0.0);
+        assert!((std_dev(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]) - 2.138).abs() < 1e-3);
+    }
+
+    #[test]
+    fn forecast_explodes_assembly_demand_into_components() {
+        let dir = fixture(&[(1, 3.0), (2, 1.0)]);
+        let request = ForecastRequest {
+            period: Period::Day,
+            horizon: 2,
+            window: 3,
+            ..ForecastRequest::default()
+        };
+        let forecast = forecast_demand(dir.path(), &request).unwrap();
+        assert_eq!(forecast.forecast_periods.len(), 2);
+        // Today counts as a quiet day: (1 + 3 + 0) / 3
+        let expected = 4.0 / 3.0;
+        assert!(forecast.assemblies[0].forecast.iter().all(|f| (f - expected).abs() < 1e-9));
+        assert_eq!(forecast.components.len(), 1);
+        assert!((forecast.components[0].total - 4.0 * expected).abs() < 1e-9);
+        assert_eq!(forecast.reorder_points[0].sku, "P1");
+        assert_eq!(forecast.reorder_points[0].lead_time_days, 14.0);
+    }
+
+    #[test]
+    fn coverage_projects_stockout_from_recent_consumption() {
+        // 10 units of A over a 10 day lookback consume 2 P1 per day
+        let dir = fixture(&[(0, 4.0), (3, 6.0), (20, 50.0)]);
+        let coverage = inventory_coverage(dir.path(), 10, CoverageSortField::DaysOfCover, false).unwrap().items;
+        assert_eq!(coverage[0].item.sku, "P1");
+        assert_eq!(coverage[0].daily_consumption, 2.0);
+        assert_eq!(coverage[0].days_of_cover, Some(15.0));
+        let expected = chrono::Local::now().date_naive() + chrono::Duration::days(15);
+        assert_eq!(coverage[0].stockout_date, Some(expected.format("%Y-%m-%d").to_string()));
+        // Parts that are never consumed sort last
+        assert_eq!(coverage[1].item.sku, "P2");
+        assert_eq!(coverage[1].days_of_cover, None);
+
+        let by_sku = inventory_coverage(dir.path(), 10, CoverageSortField::Sku, true).unwrap().items;
+        assert_eq!(by_sku[0].item.sku, "P2");
+    }
+}
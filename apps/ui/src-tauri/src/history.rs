@@ -0,0 +1,734 @@
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+use crate::bom::{effective_bom, explode_bom, load_bom_revisions, usable_revisions, BomRevision};
+use crate::data::{load_bom_items, parse_date, BomItem, BuildHistoryRecord};
+use crate::uom::UomTable;
+use crate::operators::load_operators;
+use crate::orders::load_customers;
+
+/// Bucket size for history reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+}
+
+impl Period {
+    /// Average length of one period in days.
+    pub fn days(self) -> f64 {
+        match self {
+            Period::Day => 1.0,
+            Period::Week => 7.0,
+            Period::Month => 365.25 / 12.0,
+        }
+    }
+
+    /// Default season length: a year of weeks or months, or a week of days.
+    pub fn season_length(self) -> usize {
+        match self {
+            Period::Day => 7,
+            Period::Week => 52,
+            Period::Month => 12,
+        }
+    }
+
+    /// First day of the bucket after the one starting at `start`.
+    pub fn next(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => start + Duration::days(1),
+            Period::Week => start + Duration::days(7),
+            Period::Month => start.checked_add_months(chrono::Months::new(1)).unwrap_or(start),
+        }
+    }
+
+    /// Every bucket start from the bucket containing `from` to the one containing `to`.
+    pub fn range(self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let mut starts = Vec::new();
+        let mut current = self.start_of(from);
+        let last = self.start_of(to);
+        while current <= last {
+            starts.push(current);
+            current = self.next(current);
+        }
+        starts
+    }
+
+    /// First day of the bucket containing `date`. Weeks start on Monday.
+    pub fn start_of(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => date,
+            Period::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            Period::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct OperatorProduction {
+    pub operator: String,
+    pub period_start: String,
+    pub units_built: f64,
+    pub build_count: usize,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct OperatorProductionReport {
+    pub rows: Vec<OperatorProduction>,
+    pub skipped_rows: Vec<SkippedRow>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistorySortField {
+    #[default]
+    Timestamp,
+    AssemblySku,
+    Customer,
+    WorkOrder,
+    SalesOrder,
+    Operator,
+    QuantityBuilt,
+}
+
+/// Filters, sorting and paging for `query_history`. Dates are inclusive
+/// YYYY-MM-DD; text filters ignore case, and the customer filter also
+/// matches names and aliases from `customers.csv`.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    pub from_date: Option<String>,
+    pub to_date: Option<String>,
+    pub assembly_sku: Option<String>,
+    pub customer: Option<String>,
+    pub work_order: Option<String>,
+    pub sales_order: Option<String>,
+    pub operator: Option<String>,
+    pub sort_by: HistorySortField,
+    /// Oldest first when true; newest first by default
+    pub ascending: bool,
+    /// 1-based page number
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct HistoryPage {
+    pub records: Vec<BuildHistoryRecord>,
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+    pub total_pages: usize,
+    pub skipped_rows: Vec<SkippedRow>,
+}
+
+/// A row of `panel_history.csv` left out of reports because it could not be
+/// read or has no valid timestamp.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SkippedRow {
+    /// Line in the file, counting the header as line 1
+    pub line: u64,
+    pub error: String,
+}
+
+/// The readable builds of `panel_history.csv` in file order, each with its
+/// build date, and the rows that were skipped.
+#[derive(Debug, Default)]
+pub struct PanelHistory {
+    pub records: Vec<(NaiveDate, BuildHistoryRecord)>,
+    pub skipped: Vec<SkippedRow>,
+}
+
+/// Units built per period for one assembly, aligned with `ProductionAnalytics.periods`.
+#[derive(Debug, serde::Serialize)]
+pub struct AssemblySeries {
+    pub assembly_sku: String,
+    pub units: Vec<f64>,
+    /// Trailing average over `rolling_window` periods (fewer at the start)
+    pub rolling_average: Vec<f64>,
+    pub total_units: f64,
+    pub build_count: usize,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct CustomerTotal {
+    pub customer: String,
+    pub units_built: f64,
+    pub build_count: usize,
+}
+
+/// Chart-ready production summary. `periods` has no gaps, so each series
+/// can be plotted directly against it.
+#[derive(Debug, serde::Serialize)]
+pub struct ProductionAnalytics {
+    pub period: Period,
+    pub rolling_window: usize,
+    pub periods: Vec<String>,
+    pub series: Vec<AssemblySeries>,
+    pub customer_totals: Vec<CustomerTotal>,
+    pub total_units: f64,
+    pub skipped_rows: Vec<SkippedRow>,
+}
+
+/// Quantity of one component consumed per period, aligned with `ConsumptionHistory.periods`.
+#[derive(Debug, serde::Serialize)]
+pub struct ComponentSeries {
+    pub sku: String,
+    pub quantities: Vec<f64>,
+    pub total: f64,
+}
+
+/// Component demand history derived by exploding each recorded build.
+#[derive(Debug, serde::Serialize)]
+pub struct ConsumptionHistory {
+    pub period: Period,
+    pub periods: Vec<String>,
+    pub components: Vec<ComponentSeries>,
+    /// Assemblies in history that no longer have a BOM and so were skipped
+    pub unexploded_assemblies: Vec<String>,
+    pub skipped_rows: Vec<SkippedRow>,
+    /// BOM lines left out of every explosion because their unit cannot be converted
+    pub skipped_lines: Vec<String>,
+}
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const MAX_PAGE_SIZE: usize = 1000;
+
+type HistoryCache = HashMap<PathBuf, (SystemTime, u64, Arc<PanelHistory>)>;
+
+/// Parsed history per file, reused until the file's size or modified time changes.
+static HISTORY_CACHE: OnceLock<Mutex<HistoryCache>> = OnceLock::new();
+
+/// Load `panel_history.csv`, skipping rows that do not parse or have a bad
+/// timestamp. Parsing is cached, so paging through a large history does not
+/// re-read the file for every page.
+pub fn load_dated_history(data_dir: &Path) -> Result<Arc<PanelHistory>> {
+    let path = data_dir.join("panel_history.csv");
+    if !path.exists() {
+        return Ok(Arc::new(PanelHistory::default()));
+    }
+    let meta = std::fs::metadata(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let modified = meta.modified()
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let cache = HISTORY_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some((cached_modified, cached_len, history)) = cache.lock().unwrap_or_else(|e| e.into_inner()).get(&path) {
+        if *cached_modified == modified && *cached_len == meta.len() {
+            return Ok(history.clone());
+        }
+    }
+
+    let history = Arc::new(parse_panel_history(&path)?);
+    cache.lock().unwrap_or_else(|e| e.into_inner())
+        .insert(path, (modified, meta.len(), history.clone()));
+    Ok(history)
+}
+
+fn parse_panel_history(path: &Path) -> Result<PanelHistory> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .with_context(|| format!("Failed to open CSV: {}", path.display()))?;
+    let headers = rdr.headers()
+        .with_context(|| format!("Failed to read header of {}", path.display()))?
+        .clone();
+
+    let mut history = PanelHistory::default();
+    let mut row = csv::StringRecord::new();
+    loop {
+        let line = rdr.position().line();
+        match rdr.read_record(&mut row) {
+            Ok(false) => break,
+            Ok(true) => {},
+            Err(e) => {
+                history.skipped.push(SkippedRow { line, error: e.to_string() });
+                continue;
+            }
+        }
+        let parsed = row.deserialize::<BuildHistoryRecord>(Some(&headers))
+            .map_err(anyhow::Error::from)
+            .and_then(|record| Ok((record_date(&record)?, record)));
+        match parsed {
+            Ok(dated) => history.records.push(dated),
+            Err(e) => history.skipped.push(SkippedRow { line, error: format!("{e:#}") }),
+        }
+    }
+    Ok(history)
+}
+
+/// The readable records of `panel_history.csv`, without the skipped rows.
+pub fn load_panel_history(data_dir: &Path) -> Result<Vec<BuildHistoryRecord>> {
+    Ok(load_dated_history(data_dir)?.records.iter().map(|(_, record)| record.clone()).collect())
+}
+
+/// Calendar date of a build record's RFC 3339 timestamp.
+pub fn record_date(record: &BuildHistoryRecord) -> Result<NaiveDate> {
+    chrono::DateTime::parse_from_rfc3339(&record.timestamp)
+        .map(|ts| ts.date_naive())
+        .with_context(|| format!("Invalid timestamp '{}' on build {}", record.timestamp, record.id))
+}
+
+/// Units built per operator per day or week from `panel_history.csv`.
+/// Operators written as names or badge numbers are folded into their roster
+/// ID; builds without an operator are reported as "(unassigned)".
+pub fn operator_production(data_dir: &Path, period: Period) -> Result<OperatorProductionReport> {
+    let operators = load_operators(data_dir)?;
+    let history = load_dated_history(data_dir)?;
+    let mut totals: BTreeMap<(String, NaiveDate), (f64, usize)> = BTreeMap::new();
+    for (date, record) in &history.records {
+        let operator = match record.operator.as_deref().map(str::trim).filter(|o| !o.is_empty()) {
+            Some(text) => operators.iter()
+                .find(|o| o.matches(text))
+                .map(|o| o.operator_id.clone())
+                .unwrap_or_else(|| text.to_string()),
+            None => "(unassigned)".to_string(),
+        };
+        let start = period.start_of(*date);
+        let entry = totals.entry((operator, start)).or_insert((0.0, 0));
+        entry.0 += record.quantity_built;
+        entry.1 += 1;
+    }
+
+    Ok(OperatorProductionReport {
+        rows: totals.into_iter()
+            .map(|((operator, start), (units_built, build_count))| OperatorProduction {
+                operator,
+                period_start: start.format("%Y-%m-%d").to_string(),
+                units_built,
+                build_count,
+            })
+            .collect(),
+        skipped_rows: history.skipped.clone(),
+    })
+}
+
+fn parse_filter_date(date: &Option<String>) -> Result<Option<NaiveDate>> {
+    date.as_deref()
+        .filter(|d| !d.trim().is_empty())
+        .map(parse_date)
+        .transpose()
+}
+
+fn text_filter(filter: &Option<String>) -> Option<&str> {
+    filter.as_deref().map(str::trim).filter(|f| !f.is_empty())
+}
+
+/// Filter, sort and page `panel_history.csv`.
+pub fn query_history(data_dir: &Path, query: &HistoryQuery) -> Result<HistoryPage> {
+    let page_size = query.page_size.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let page = query.page.unwrap_or(1);
+    if page == 0 {
+        bail!("Page numbers start at 1");
+    }
+
+    let history = filter_history(data_dir, query)?;
+    let total = history.records.len();
+    let total_pages = total.div_ceil(page_size);
+    let records = history.records.into_iter()
+        .skip((page - 1) * page_size)
+        .take(page_size)
+        .map(|(_, record)| record)
+        .collect();
+
+    Ok(HistoryPage {
+        records,
+        total,
+        page,
+        page_size,
+        total_pages,
+        skipped_rows: history.skipped,
+    })
+}
+
+/// Every readable record of `panel_history.csv` matching the query's filters,
+/// in its sort order, with the rows that were skipped. Paging is ignored.
+pub fn filter_history(data_dir: &Path, query: &HistoryQuery) -> Result<PanelHistory> {
+    let from = parse_filter_date(&query.from_date)?;
+    let to = parse_filter_date(&query.to_date)?;
+
+    // Accept any spelling of a known customer
+    let customers = load_customers(data_dir)?;
+    let customer_filter = text_filter(&query.customer);
+    let customer_match = customer_filter
+        .and_then(|text| customers.iter().find(|c| c.matches(text)));
+    let operator_filter = text_filter(&query.operator);
+    let operators = load_operators(data_dir)?;
+    let operator_match = operator_filter
+        .and_then(|text| operators.iter().find(|o| o.matches(text)));
+
+    let eq = |filter: Option<&str>, value: &str| {
+        filter.map(|f| f.eq_ignore_ascii_case(value.trim())).unwrap_or(true)
+    };
+
+    let history = load_dated_history(data_dir)?;
+    let mut records = Vec::new();
+    for (date, record) in &history.records {
+        if from.map(|f| *date < f).unwrap_or(false) || to.map(|t| *date > t).unwrap_or(false) {
+            continue;
+        }
+        if !eq(text_filter(&query.assembly_sku), &record.assembly_sku)
+            || !eq(text_filter(&query.work_order), &record.work_order)
+            || !eq(text_filter(&query.sales_order), &record.sales_order)
+        {
+            continue;
+        }
+        let customer_ok = match customer_match {
+            Some(c) => c.matches(&record.customer),
+            None => eq(customer_filter, &record.customer),
+        };
+        let record_operator = record.operator.as_deref().unwrap_or("");
+        let operator_ok = match operator_match {
+            Some(o) => o.matches(record_operator),
+            None => eq(operator_filter, record_operator),
+        };
+        if customer_ok && operator_ok {
+            records.push((*date, record.clone()));
+        }
+    }
+
+    records.sort_by(|(_, a), (_, b)| {
+        let ord = match query.sort_by {
+            HistorySortField::Timestamp => a.timestamp.cmp(&b.timestamp),
+            HistorySortField::AssemblySku => a.assembly_sku.cmp(&b.assembly_sku),
+            HistorySortField::Customer => a.customer.to_lowercase().cmp(&b.customer.to_lowercase()),
+            HistorySortField::WorkOrder => a.work_order.cmp(&b.work_order),
+            HistorySortField::SalesOrder => a.sales_order.cmp(&b.sales_order),
+            HistorySortField::Operator => a.operator.cmp(&b.operator),
+            HistorySortField::QuantityBuilt => a.quantity_built.total_cmp(&b.quantity_built),
+        };
+        // Ties fall back to time so pages stay stable
+        let ord = ord.then_with(|| a.timestamp.cmp(&b.timestamp));
+        if query.ascending { ord } else { ord.reverse() }
+    });
+    Ok(PanelHistory { records, skipped: history.skipped.clone() })
+}
+
+/// Records built within the inclusive date range.
+fn in_range(
+    history: &PanelHistory,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Vec<&(NaiveDate, BuildHistoryRecord)> {
+    history.records.iter()
+        .filter(|(date, _)| !from.map(|f| *date < f).unwrap_or(false) && !to.map(|t| *date > t).unwrap_or(false))
+        .collect()
+}
+
+/// Trailing mean over up to `window` values ending at each position.
+pub fn rolling_average(values: &[f64], window: usize) -> Vec<f64> {
+    let window = window.max(1);
+    (0..values.len())
+        .map(|i| {
+            let start = (i + 1).saturating_sub(window);
+            let slice = &values[start..=i];
+            slice.iter().sum::<f64>() / slice.len() as f64
+        })
+        .collect()
+}
+
+/// Aggregate `panel_history.csv` into per-assembly time series, per-customer
+/// totals and rolling averages, optionally within an inclusive date range.
+pub fn production_analytics(
+    data_dir: &Path,
+    period: Period,
+    from_date: Option<String>,
+    to_date: Option<String>,
+    rolling_window: Option<usize>,
+) -> Result<ProductionAnalytics> {
+    let from = parse_filter_date(&from_date)?;
+    let to = parse_filter_date(&to_date)?;
+    let rolling_window = rolling_window.unwrap_or(3).max(1);
+    let customers = load_customers(data_dir)?;
+
+    let history = load_dated_history(data_dir)?;
+    let dated = in_range(&history, from, to);
+
+    let first = from.or_else(|| dated.iter().map(|(d, _)| *d).min());
+    let last = to.or_else(|| dated.iter().map(|(d, _)| *d).max());
+    let starts = match (first, last) {
+        (Some(first), Some(last)) => period.range(first, last),
+        _ => Vec::new(),
+    };
+    let index: BTreeMap<NaiveDate, usize> = starts.iter().enumerate().map(|(i, d)| (*d, i)).collect();
+
+    let mut by_assembly: BTreeMap<String, (Vec<f64>, usize)> = BTreeMap::new();
+    // Keyed by lowercase so spellings differing only in case are one customer
+    let mut by_customer: BTreeMap<String, (String, f64, usize)> = BTreeMap::new();
+    let mut total_units = 0.0;
+    for (date, record) in &dated {
+        let Some(&slot) = index.get(&period.start_of(*date)) else {
+            continue;
+        };
+        let entry = by_assembly.entry(record.assembly_sku.clone())
+            .or_insert_with(|| (vec![0.0; starts.len()], 0));
+        entry.0[slot] += record.quantity_built;
+        entry.1 += 1;
+
+        let customer = customers.iter()
+            .find(|c| c.matches(&record.customer))
+            .map(|c| c.customer_id.clone())
+            .unwrap_or_else(|| record.customer.trim().to_string());
+        let entry = by_customer.entry(customer.to_lowercase()).or_insert((customer, 0.0, 0));
+        entry.1 += record.quantity_built;
+        entry.2 += 1;
+        total_units += record.quantity_built;
+    }
+
+    let series = by_assembly.into_iter()
+        .map(|(assembly_sku, (units, build_count))| AssemblySeries {
+            rolling_average: rolling_average(&units, rolling_window),
+            total_units: units.iter().sum(),
+            assembly_sku,
+            units,
+            build_count,
+        })
+        .collect();
+    let mut customer_totals: Vec<CustomerTotal> = by_customer.into_values()
+        .map(|(customer, units_built, build_count)| CustomerTotal { customer, units_built, build_count })
+        .collect();
+    customer_totals.sort_by(|a, b| b.units_built.total_cmp(&a.units_built));
+
+    Ok(ProductionAnalytics {
+        period,
+        rolling_window,
+        periods: starts.iter().map(|d| d.format("%Y-%m-%d").to_string()).collect(),
+        series,
+        customer_totals,
+        total_units,
+        skipped_rows: history.skipped.clone(),
+    })
+}
+
+/// Leaf requirements per unit for each assembly built, exploded once per
+/// assembly and build date against the BOM revisions effective that day.
+/// `None` marks assemblies without BOM rows. BOM lines whose unit cannot be
+/// converted are left out, with the reason kept in `skipped_lines`.
+pub struct ExplosionCache {
+    pub skipped_lines: Vec<String>,
+    bom_items: Vec<BomItem>,
+    revisions: Vec<BomRevision>,
+    cache: HashMap<(String, NaiveDate), Option<HashMap<String, f64>>>,
+}
+
+impl ExplosionCache {
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        let (bom_items, skipped) = UomTable::load(data_dir)?.usable_lines(load_bom_items(data_dir)?);
+        Ok(ExplosionCache {
+            skipped_lines: skipped.into_iter().map(|(_, reason)| reason).collect(),
+            bom_items,
+            revisions: usable_revisions(load_bom_revisions(data_dir)?).0,
+            cache: HashMap::new(),
+        })
+    }
+
+    pub fn requirements(&mut self, record: &BuildHistoryRecord, date: NaiveDate) -> Result<Option<&HashMap<String, f64>>> {
+        let key = (record.assembly_sku.clone(), date);
+        if !self.cache.contains_key(&key) {
+            let bom = effective_bom(&self.bom_items, &self.revisions, date)?;
+            let req = if bom.iter().any(|b| b.parent_assembly_sku == record.assembly_sku) {
+                Some(explode_bom(&record.assembly_sku, &bom)?)
+            } else {
+                None
+            };
+            self.cache.insert(key.clone(), req);
+        }
+        Ok(self.cache[&key].as_ref())
+    }
+}
+
+/// Explode every build in `panel_history.csv` into component usage and total
+/// it per period, optionally for one component and an inclusive date range.
+pub fn consumption_history(
+    data_dir: &Path,
+    period: Period,
+    from_date: Option<String>,
+    to_date: Option<String>,
+    sku: Option<String>,
+) -> Result<ConsumptionHistory> {
+    let from = parse_filter_date(&from_date)?;
+    let to = parse_filter_date(&to_date)?;
+    let sku_filter = text_filter(&sku).map(str::to_string);
+    let mut explosions = ExplosionCache::load(data_dir)?;
+
+    let history = load_dated_history(data_dir)?;
+    let dated = in_range(&history, from, to);
+
+    let first = from.or_else(|| dated.iter().map(|(d, _)| *d).min());
+    let last = to.or_else(|| dated.iter().map(|(d, _)| *d).max());
+    let starts = match (first, last) {
+        (Some(first), Some(last)) => period.range(first, last),
+        _ => Vec::new(),
+    };
+    let index: BTreeMap<NaiveDate, usize> = starts.iter().enumerate().map(|(i, d)| (*d, i)).collect();
+
+    let mut by_component: BTreeMap<String, Vec<f64>> = BTreeMap::new();
+    let mut unexploded = BTreeSet::new();
+    for (date, record) in &dated {
+        let Some(&slot) = index.get(&period.start_of(*date)) else {
+            continue;
+        };
+        let Some(req) = explosions.requirements(record, *date)? else {
+            unexploded.insert(record.assembly_sku.clone());
+            continue;
+        };
+        for (component, per_unit) in req {
+            if sku_filter.as_deref().map(|f| !f.eq_ignore_ascii_case(component)).unwrap_or(false) {
+                continue;
+            }
+            by_component.entry(component.clone())
+                .or_insert_with(|| vec![0.0; starts.len()])[slot] += per_unit * record.quantity_built;
+        }
+    }
+
+    Ok(ConsumptionHistory {
+        period,
+        periods: starts.iter().map(|d| d.format("%Y-%m-%d").to_string()).collect(),
+        components: by_component.into_iter()
+            .map(|(sku, quantities)| ComponentSeries { total: quantities.iter().sum(), sku, quantities })
+            .collect(),
+        unexploded_assemblies: unexploded.into_iter().collect(),
+        skipped_rows: history.skipped.clone(),
+        skipped_lines: explosions.skipped_lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    const HEADER: &str = "id,timestamp,work_order,sales_order,customer,assembly_sku,quantity_built,operator\n";
+
+    fn fixture(rows: &str) -> TestDir {
+        let dir = TestDir::new();
+        dir.write("assemblies.csv", "assembly_sku,name,uom\nA,Assembly,ea\nB,Other,ea\n");
+        dir.write("parts.csv", "part_sku,name,uom\nP1,Part one,ea\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\nA,P1,2,0,1,false\n");
+        dir.write("panel_history.csv", &format!("{HEADER}{rows}"));
+        dir
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        parse_date(text).unwrap()
+    }
+
+    #[test]
+    fn periods_start_on_monday_and_first_of_month() {
+        assert_eq!(Period::Week.start_of(date("2024-03-07")), date("2024-03-04"));
+        assert_eq!(Period::Month.start_of(date("2024-03-07")), date("2024-03-01"));
+        assert_eq!(Period::Month.range(date("2024-01-31"), date("2024-03-01")).len(), 3);
+        assert_eq!(rolling_average(&[2.0, 4.0, 6.0], 2), vec![2.0, 3.0, 5.0]);
+    }
+
+    #[test]
+    fn unreadable_rows_are_skipped_with_their_line() {
+        let dir = fixture("\
+            B1,2024-03-04T10:00:00Z,WO-1,SO-1,Beacon,A,2,sam\n\
+            B2,yesterday,WO-1,SO-1,Beacon,A,2,sam\n\
+            B3,2024-03-05T10:00:00Z,WO-1,SO-1,Beacon,A,two,sam\n\
+            B4,2024-03-05T10:00:00Z,WO-1\n\
+            B5,2024-03-06T10:00:00Z,WO-2,SO-1,Harbor,B,1,\n");
+        let history = load_dated_history(dir.path()).unwrap();
+        let ids: Vec<&str> = history.records.iter().map(|(_, r)| r.id.as_str()).collect();
+        assert_eq!(ids, ["B1", "B5"]);
+        let lines: Vec<u64> = history.skipped.iter().map(|s| s.line).collect();
+        assert_eq!(lines, [3, 4, 5]);
+        assert!(history.skipped[0].error.contains("yesterday"));
+
+        let page = query_history(dir.path(), &HistoryQuery::default()).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.skipped_rows.len(), 3);
+        let report = operator_production(dir.path(), Period::Day).unwrap();
+        assert_eq!(report.rows.len(), 2);
+        assert_eq!(report.rows[0].operator, "(unassigned)");
+        assert_eq!(report.skipped_rows.len(), 3);
+    }
+
+    #[test]
+    fn parsed_history_is_reused_until_the_file_changes() {
+        let dir = fixture("B1,2024-03-04T10:00:00Z,WO-1,SO-1,Beacon,A,2,sam\n");
+        let first = load_dated_history(dir.path()).unwrap();
+        assert!(Arc::ptr_eq(&first, &load_dated_history(dir.path()).unwrap()));
+
+        crate::data::add_panel_history_record(dir.path(), &first.records[0].1).unwrap();
+        let second = load_dated_history(dir.path()).unwrap();
+        assert_eq!(second.records.len(), 2);
+    }
+
+    #[test]
+    fn query_history_filters_sorts_and_pages() {
+        let dir = fixture("\
+            B1,2024-03-04T10:00:00Z,WO-1,SO-1,Beacon,A,2,sam\n\
+            B2,2024-03-05T10:00:00Z,WO-2,SO-1,beacon,B,5,kim\n\
+            B3,2024-03-06T10:00:00Z,WO-3,SO-2,Harbor,A,1,sam\n\
+            B4,2024-03-07T10:00:00Z,WO-4,SO-2,BEACON,A,3,kim\n");
+        let query = HistoryQuery {
+            customer: Some("beacon".to_string()),
+            from_date: Some("2024-03-05".to_string()),
+            page_size: Some(1),
+            ..HistoryQuery::default()
+        };
+        let page = query_history(dir.path(), &query).unwrap();
+        assert_eq!((page.total, page.total_pages), (2, 2));
+        assert_eq!(page.records[0].id, "B4");
+
+        let query = HistoryQuery {
+            sort_by: HistorySortField::QuantityBuilt,
+            ascending: true,
+            assembly_sku: Some("a".to_string()),
+            ..HistoryQuery::default()
+        };
+        let ids: Vec<String> = query_history(dir.path(), &query).unwrap().records.into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, ["B3", "B1", "B4"]);
+
+        let query = HistoryQuery { page: Some(0), ..HistoryQuery::default() };
+        assert!(query_history(dir.path(), &query).is_err());
+    }
+
+    #[test]
+    fn analytics_bucket_units_without_gaps() {
+        let dir = fixture("\
+            B1,2024-03-04T10:00:00Z,WO-1,SO-1,Beacon,A,2,sam\n\
+            B2,2024-03-20T10:00:00Z,WO-2,SO-1,beacon,A,4,kim\n\
+            B3,2024-03-20T11:00:00Z,WO-3,SO-2,Harbor,B,1,sam\n");
+        let analytics = production_analytics(dir.path(), Period::Week, None, None, Some(2)).unwrap();
+        assert_eq!(analytics.periods, ["2024-03-04", "2024-03-11", "2024-03-18"]);
+        assert_eq!(analytics.series[0].units, vec![2.0, 0.0, 4.0]);
+        assert_eq!(analytics.series[0].rolling_average, vec![2.0, 1.0, 2.0]);
+        assert_eq!(analytics.total_units, 7.0);
+        // Spellings differing only in case are one customer
+        assert_eq!(analytics.customer_totals[0].customer, "Beacon");
+        assert_eq!(analytics.customer_totals[0].units_built, 6.0);
+    }
+
+    #[test]
+    fn consumption_explodes_each_build() {
+        let dir = fixture("\
+            B1,2024-03-04T10:00:00Z,WO-1,SO-1,Beacon,A,2,sam\n\
+            B2,2024-03-05T10:00:00Z,WO-2,SO-1,Beacon,B,1,sam\n");
+        let consumption = consumption_history(dir.path(), Period::Day, None, None, None).unwrap();
+        assert_eq!(consumption.periods.len(), 2);
+        assert_eq!(consumption.components[0].sku, "P1");
+        assert_eq!(consumption.components[0].quantities, vec![4.0, 0.0]);
+        assert_eq!(consumption.unexploded_assemblies, ["B"]);
+    }
+
+    #[test]
+    fn consumption_skips_lines_without_a_unit_conversion() {
+        let dir = fixture("B1,2024-03-04T10:00:00Z,WO-1,SO-1,Beacon,A,2,sam\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom,revision,uom\n\
+            A,P1,2,0,1,false,,\nA,P1,1,0,1,false,,box\n");
+        let consumption = consumption_history(dir.path(), Period::Day, None, None, None).unwrap();
+        assert_eq!(consumption.components[0].quantities, vec![4.0]);
+        assert_eq!(consumption.skipped_lines.len(), 1);
+        assert!(consumption.skipped_lines[0].contains("P1 on A"));
+    }
+}
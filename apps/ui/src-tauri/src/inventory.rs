@@ -0,0 +1,954 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::data::{
+    append_csv, direct_requirements, load_data_dir, load_parts, modify_stock, read_csv_if_exists, with_rollback, write_csv,
+    StockRow,
+};
+use crate::purchasing::{self, PurchaseOrderLine};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerKind {
+    Receipt,
+    Build,
+    Adjustment,
+}
+
+/// Why a manual adjustment or cycle count changed on-hand stock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdjustmentReason {
+    Damage,
+    Miscount,
+    Found,
+}
+
+/// One on-hand movement in `inventory_ledger.csv`. Positive `qty_change`
+/// increases stock, negative decreases it.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LedgerEntry {
+    pub id: String,
+    pub timestamp: String,
+    pub sku: String,
+    pub qty_change: f64,
+    pub kind: LedgerKind,
+    /// PO number for receipts, work order for builds, count session for cycle counts
+    #[serde(default)]
+    pub reference: Option<String>,
+    /// PO line a receipt was booked against
+    #[serde(default)]
+    pub po_line: Option<u32>,
+    #[serde(default)]
+    pub reason: Option<AdjustmentReason>,
+    #[serde(default)]
+    pub lot: Option<String>,
+    #[serde(default)]
+    pub operator: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+impl LedgerEntry {
+    pub fn new(sku: &str, qty_change: f64, kind: LedgerKind) -> Self {
+        LedgerEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            sku: sku.to_string(),
+            qty_change,
+            kind,
+            reference: None,
+            po_line: None,
+            reason: None,
+            lot: None,
+            operator: None,
+            notes: None,
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ReceiptRequest {
+    pub sku: String,
+    pub qty: f64,
+    #[serde(default)]
+    pub po_number: Option<String>,
+    #[serde(default)]
+    pub lot: Option<String>,
+    pub received_by: String,
+    /// Close the PO lines touched by this receipt even if they were short-shipped
+    #[serde(default)]
+    pub close_po_lines: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ReceiptResult {
+    /// One entry per PO line the receipt was applied to, or a single entry without a PO
+    pub entries: Vec<LedgerEntry>,
+    pub on_hand_qty: f64,
+    pub po_lines: Vec<PurchaseOrderLine>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct AdjustmentRequest {
+    pub sku: String,
+    pub qty_change: f64,
+    pub reason: AdjustmentReason,
+    pub operator: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CycleCountStatus {
+    Open,
+    Posted,
+}
+
+/// One SKU on a count sheet in `cycle_counts.csv`. A session is the set of
+/// lines sharing a `session_id`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CycleCountLine {
+    pub session_id: String,
+    pub sku: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub counted_qty: Option<f64>,
+    /// On-hand quantity when the count was entered; the variance is taken against it
+    #[serde(default)]
+    pub system_qty: Option<f64>,
+    #[serde(default)]
+    pub counted_by: Option<String>,
+    #[serde(default)]
+    pub reason: Option<AdjustmentReason>,
+    /// Variance that was posted; only set once the session is posted
+    #[serde(default)]
+    pub posted_variance: Option<f64>,
+    pub status: CycleCountStatus,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CountEntry {
+    pub sku: String,
+    pub counted_qty: f64,
+    #[serde(default)]
+    pub reason: Option<AdjustmentReason>,
+}
+
+/// Counted quantity compared against the on-hand quantity when it was counted.
+#[derive(Debug, serde::Serialize)]
+pub struct CountVariance {
+    pub sku: String,
+    /// Current `StockRow.on_hand_qty`
+    pub on_hand_qty: f64,
+    /// On hand when the count was entered
+    pub system_qty: Option<f64>,
+    pub counted_qty: Option<f64>,
+    pub variance: Option<f64>,
+    pub reason: Option<AdjustmentReason>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReservationStatus {
+    Active,
+    Released,
+    Consumed,
+}
+
+/// Components held for a work order in `reservations.csv`, one row per SKU.
+/// The open quantity is what is still counted in `StockRow.reserved_qty`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Reservation {
+    pub reservation_id: String,
+    pub work_order: String,
+    pub sales_order: String,
+    pub assembly_sku: String,
+    pub sku: String,
+    pub qty_reserved: f64,
+    pub qty_consumed: f64,
+    pub qty_released: f64,
+    pub created_at: String,
+    pub status: ReservationStatus,
+}
+
+impl Reservation {
+    pub fn open_qty(&self) -> f64 {
+        (self.qty_reserved - self.qty_consumed - self.qty_released).max(0.0)
+    }
+}
+
+/// Files a receipt writes.
+const RECEIPT_FILES: &[&str] = &["stock.csv", "main_inventory.csv", "inventory_ledger.csv", "purchase_orders.csv"];
+/// Files an adjustment writes; a cycle count also closes its sheet.
+const ADJUSTMENT_FILES: &[&str] = &["stock.csv", "main_inventory.csv", "inventory_ledger.csv"];
+const CYCLE_COUNT_FILES: &[&str] = &["stock.csv", "main_inventory.csv", "inventory_ledger.csv", "cycle_counts.csv"];
+/// Files a reservation change writes.
+const RESERVATION_FILES: &[&str] = &["stock.csv", "main_inventory.csv", "reservations.csv"];
+
+pub fn load_ledger(data_dir: &Path) -> Result<Vec<LedgerEntry>> {
+    read_csv_if_exists::<LedgerEntry>(&data_dir.join("inventory_ledger.csv"))
+        .context("Reading inventory_ledger.csv")
+}
+
+pub fn append_ledger(data_dir: &Path, entries: &[LedgerEntry]) -> Result<()> {
+    append_csv(&data_dir.join("inventory_ledger.csv"), entries)
+        .context("Writing inventory_ledger.csv")
+}
+
+/// Ledger entries for the parts a build deducted.
+pub fn build_ledger_entries(
+    deducted: &HashMap<String, f64>,
+    work_order: &str,
+    operator: Option<&str>,
+) -> Vec<LedgerEntry> {
+    let mut skus: Vec<&String> = deducted.keys().collect();
+    skus.sort();
+    skus.into_iter()
+        .filter(|sku| deducted[*sku] > 0.0)
+        .map(|sku| LedgerEntry {
+            reference: Some(work_order.to_string()),
+            operator: operator.map(str::to_string),
+            ..LedgerEntry::new(sku, -deducted[sku], LedgerKind::Build)
+        })
+        .collect()
+}
+
+/// Increase on-hand stock for a receipt, write the ledger entries, and apply the
+/// quantity to the referenced purchase order if one is given. A receipt spread
+/// over several PO lines gets one ledger entry per line, so each keeps its price.
+pub fn receive_stock(data_dir: &Path, request: ReceiptRequest) -> Result<ReceiptResult> {
+    if request.sku.trim().is_empty() {
+        bail!("SKU is required");
+    }
+    if request.qty <= 0.0 {
+        bail!("Received quantity must be positive");
+    }
+    if request.received_by.trim().is_empty() {
+        bail!("Received by is required");
+    }
+
+    if !load_parts(data_dir)?.iter().any(|p| p.part_sku == request.sku) {
+        bail!("Unknown part: {}", request.sku);
+    }
+
+    // Check the PO reference before anything is written
+    let mut po_update = None;
+    if let Some(po_number) = request.po_number.as_deref() {
+        let mut lines = purchasing::load_purchase_orders(data_dir)?;
+        let updated = purchasing::apply_receipt(
+            &mut lines, po_number, &request.sku, request.qty, request.close_po_lines,
+        )?;
+        po_update = Some((lines, updated));
+    }
+
+    let receipt_entry = |qty: f64, po_line: Option<u32>| LedgerEntry {
+        reference: request.po_number.clone(),
+        po_line,
+        lot: request.lot.clone(),
+        operator: Some(request.received_by.clone()),
+        ..LedgerEntry::new(&request.sku, qty, LedgerKind::Receipt)
+    };
+    let entries: Vec<LedgerEntry> = match &po_update {
+        Some((_, updated)) => updated.iter()
+            .map(|(line, applied)| receipt_entry(*applied, Some(line.line_no)))
+            .collect(),
+        None => vec![receipt_entry(request.qty, None)],
+    };
+
+    // Stock, ledger and PO land together or not at all, so a retry after a
+    // failure cannot receive the same quantity twice
+    let mut on_hand_qty = 0.0;
+    with_rollback(data_dir, RECEIPT_FILES, || {
+        modify_stock(data_dir, |stock| {
+            match stock.iter_mut().find(|s| s.sku == request.sku) {
+                Some(row) => {
+                    row.on_hand_qty += request.qty;
+                    on_hand_qty = row.on_hand_qty;
+                },
+                None => {
+                    stock.push(StockRow {
+                        sku: request.sku.clone(),
+                        on_hand_qty: request.qty,
+                        reserved_qty: 0.0,
+                    });
+                    on_hand_qty = request.qty;
+                }
+            }
+            Ok(())
+        })?;
+        append_ledger(data_dir, &entries)?;
+        if let Some((lines, _)) = &po_update {
+            purchasing::write_purchase_orders(data_dir, lines)?;
+        }
+        Ok(())
+    })?;
+
+    let po_lines = po_update
+        .map(|(_, updated)| updated.into_iter().map(|(line, _)| line).collect())
+        .unwrap_or_default();
+
+    Ok(ReceiptResult { entries, on_hand_qty, po_lines })
+}
+
+/// Change on-hand quantities and write one ledger entry per adjustment.
+/// Fails without changing stock if any adjustment would go below zero; callers
+/// run it inside `with_rollback` so the ledger write is covered too.
+fn post_adjustments(data_dir: &Path, entries: Vec<LedgerEntry>) -> Result<Vec<LedgerEntry>> {
+    modify_stock(data_dir, |stock| {
+        for entry in &entries {
+            let current = stock.iter().find(|s| s.sku == entry.sku).map(|s| s.on_hand_qty).unwrap_or(0.0);
+            if current + entry.qty_change < -1e-9 {
+                bail!("Adjustment of {} would take {} below zero (on hand {})", entry.qty_change, entry.sku, current);
+            }
+        }
+        for entry in &entries {
+            match stock.iter_mut().find(|s| s.sku == entry.sku) {
+                Some(row) => row.on_hand_qty = (row.on_hand_qty + entry.qty_change).max(0.0),
+                None => stock.push(StockRow {
+                    sku: entry.sku.clone(),
+                    on_hand_qty: entry.qty_change.max(0.0),
+                    reserved_qty: 0.0,
+                }),
+            }
+        }
+        Ok(())
+    })?;
+    append_ledger(data_dir, &entries)?;
+    Ok(entries)
+}
+
+/// Manually adjust on-hand stock by `qty_change` with a reason code.
+pub fn adjust_stock(data_dir: &Path, request: AdjustmentRequest) -> Result<LedgerEntry> {
+    if request.sku.trim().is_empty() {
+        bail!("SKU is required");
+    }
+    if request.qty_change == 0.0 {
+        bail!("Adjustment quantity must not be zero");
+    }
+    if request.operator.trim().is_empty() {
+        bail!("Operator is required");
+    }
+    let entry = LedgerEntry {
+        reason: Some(request.reason),
+        operator: Some(request.operator),
+        notes: request.notes,
+        ..LedgerEntry::new(&request.sku, request.qty_change, LedgerKind::Adjustment)
+    };
+    let mut posted = with_rollback(data_dir, ADJUSTMENT_FILES, || post_adjustments(data_dir, vec![entry]))?;
+    Ok(posted.remove(0))
+}
+
+fn cycle_counts_path(data_dir: &Path) -> std::path::PathBuf {
+    data_dir.join("cycle_counts.csv")
+}
+
+pub fn load_cycle_counts(data_dir: &Path) -> Result<Vec<CycleCountLine>> {
+    read_csv_if_exists::<CycleCountLine>(&cycle_counts_path(data_dir))
+        .context("Reading cycle_counts.csv")
+}
+
+fn write_cycle_counts(data_dir: &Path, lines: &[CycleCountLine]) -> Result<()> {
+    write_csv(&cycle_counts_path(data_dir), lines)
+        .context("Writing cycle_counts.csv")
+}
+
+fn next_session_id(lines: &[CycleCountLine]) -> String {
+    let max = lines.iter()
+        .filter_map(|l| l.session_id.strip_prefix("CC-").and_then(|n| n.parse::<u32>().ok()))
+        .max()
+        .unwrap_or(0);
+    format!("CC-{:05}", max + 1)
+}
+
+/// Generate a count sheet for the given SKUs. Quantities are left blank so
+/// the count is taken without seeing the system figure.
+pub fn start_cycle_count(data_dir: &Path, skus: &[String]) -> Result<Vec<CycleCountLine>> {
+    if skus.is_empty() {
+        bail!("A cycle count needs at least one SKU");
+    }
+    let data = load_data_dir(data_dir)?;
+    for sku in skus {
+        if !data.stock.iter().any(|s| &s.sku == sku) && !data.parts.iter().any(|p| &p.part_sku == sku) {
+            bail!("Unknown SKU: {}", sku);
+        }
+    }
+
+    let mut lines = load_cycle_counts(data_dir)?;
+    let session_id = next_session_id(&lines);
+    let created_at = chrono::Utc::now().to_rfc3339();
+    let mut sheet: Vec<CycleCountLine> = Vec::new();
+    for sku in skus {
+        if sheet.iter().any(|l| &l.sku == sku) {
+            continue;
+        }
+        sheet.push(CycleCountLine {
+            session_id: session_id.clone(),
+            sku: sku.clone(),
+            created_at: created_at.clone(),
+            counted_qty: None,
+            system_qty: None,
+            counted_by: None,
+            reason: None,
+            posted_variance: None,
+            status: CycleCountStatus::Open,
+        });
+    }
+
+    lines.extend(sheet.iter().cloned());
+    write_cycle_counts(data_dir, &lines)?;
+    Ok(sheet)
+}
+
+/// Record counted quantities against an open session.
+pub fn enter_cycle_counts(
+    data_dir: &Path,
+    session_id: &str,
+    counts: &[CountEntry],
+    counted_by: &str,
+) -> Result<Vec<CountVariance>> {
+    if counted_by.trim().is_empty() {
+        bail!("Counted by is required");
+    }
+    let on_hand: HashMap<String, f64> = load_data_dir(data_dir)?.stock.into_iter()
+        .map(|s| (s.sku, s.on_hand_qty))
+        .collect();
+    let mut lines = load_cycle_counts(data_dir)?;
+    for count in counts {
+        if count.counted_qty < 0.0 {
+            bail!("Counted quantity for {} cannot be negative", count.sku);
+        }
+        let line = lines.iter_mut()
+            .find(|l| l.session_id == session_id && l.sku == count.sku)
+            .ok_or_else(|| anyhow!("{} is not on count sheet {}", count.sku, session_id))?;
+        if line.status == CycleCountStatus::Posted {
+            bail!("Count sheet {} has already been posted", session_id);
+        }
+        line.counted_qty = Some(count.counted_qty);
+        line.system_qty = Some(on_hand.get(&count.sku).copied().unwrap_or(0.0));
+        line.counted_by = Some(counted_by.to_string());
+        line.reason = count.reason;
+    }
+    write_cycle_counts(data_dir, &lines)?;
+    cycle_count_variances(data_dir, session_id)
+}
+
+/// Compare each counted line of a session with the on-hand quantity when it
+/// was counted, so builds and receipts since then are not reversed. Lines
+/// counted before that was recorded fall back to the current quantity.
+pub fn cycle_count_variances(data_dir: &Path, session_id: &str) -> Result<Vec<CountVariance>> {
+    let data = load_data_dir(data_dir)?;
+    let on_hand: HashMap<&str, f64> = data.stock.iter()
+        .map(|s| (s.sku.as_str(), s.on_hand_qty))
+        .collect();
+
+    let lines: Vec<CycleCountLine> = load_cycle_counts(data_dir)?.into_iter()
+        .filter(|l| l.session_id == session_id)
+        .collect();
+    if lines.is_empty() {
+        bail!("Count sheet {} not found", session_id);
+    }
+
+    Ok(lines.into_iter().map(|line| {
+        let on_hand_qty = on_hand.get(line.sku.as_str()).copied().unwrap_or(0.0);
+        let system_qty = line.system_qty.unwrap_or(on_hand_qty);
+        CountVariance {
+            variance: line.posted_variance
+                .or_else(|| line.counted_qty.map(|counted| counted - system_qty)),
+            on_hand_qty,
+            system_qty: line.system_qty,
+            counted_qty: line.counted_qty,
+            reason: line.reason,
+            sku: line.sku,
+        }
+    }).collect())
+}
+
+/// Post adjustments for every counted line with a variance and close the session.
+/// Lines without their own reason code use `default_reason`.
+pub fn post_cycle_count(
+    data_dir: &Path,
+    session_id: &str,
+    default_reason: AdjustmentReason,
+    operator: &str,
+) -> Result<Vec<LedgerEntry>> {
+    if operator.trim().is_empty() {
+        bail!("Operator is required");
+    }
+    let variances = cycle_count_variances(data_dir, session_id)?;
+    let mut lines = load_cycle_counts(data_dir)?;
+    if lines.iter().any(|l| l.session_id == session_id && l.status == CycleCountStatus::Posted) {
+        bail!("Count sheet {} has already been posted", session_id);
+    }
+    if let Some(missing) = variances.iter().find(|v| v.counted_qty.is_none()) {
+        bail!("{} has not been counted yet", missing.sku);
+    }
+
+    let entries: Vec<LedgerEntry> = variances.iter()
+        .filter(|v| v.variance.map(|d| d.abs() > 1e-9).unwrap_or(false))
+        .map(|v| LedgerEntry {
+            reference: Some(session_id.to_string()),
+            reason: Some(v.reason.unwrap_or(default_reason)),
+            operator: Some(operator.to_string()),
+            notes: Some("Cycle count".to_string()),
+            ..LedgerEntry::new(&v.sku, v.variance.unwrap_or(0.0), LedgerKind::Adjustment)
+        })
+        .collect();
+    for line in lines.iter_mut().filter(|l| l.session_id == session_id) {
+        line.posted_variance = variances.iter()
+            .find(|v| v.sku == line.sku)
+            .and_then(|v| v.variance);
+        line.status = CycleCountStatus::Posted;
+    }
+    // Closing the sheet with the adjustments stops a second post applying them again
+    with_rollback(data_dir, CYCLE_COUNT_FILES, || {
+        let posted = if entries.is_empty() { Vec::new() } else { post_adjustments(data_dir, entries)? };
+        write_cycle_counts(data_dir, &lines)?;
+        Ok(posted)
+    })
+}
+
+fn reservations_path(data_dir: &Path) -> std::path::PathBuf {
+    data_dir.join("reservations.csv")
+}
+
+pub fn load_reservations(data_dir: &Path) -> Result<Vec<Reservation>> {
+    read_csv_if_exists::<Reservation>(&reservations_path(data_dir))
+        .context("Reading reservations.csv")
+}
+
+fn write_reservations(data_dir: &Path, reservations: &[Reservation]) -> Result<()> {
+    write_csv(&reservations_path(data_dir), reservations)
+        .context("Writing reservations.csv")
+}
+
+/// Add `changes` (SKU -> delta) to `reserved_qty` in the authoritative stock
+/// and save `reservations` to match. Not atomic by itself: callers run it
+/// inside `with_rollback` over `RESERVATION_FILES` or a set including them.
+fn change_reserved(data_dir: &Path, changes: &HashMap<String, f64>, reservations: &[Reservation]) -> Result<()> {
+    modify_stock(data_dir, |stock| {
+        for (sku, delta) in changes {
+            match stock.iter_mut().find(|s| &s.sku == sku) {
+                Some(row) => row.reserved_qty = (row.reserved_qty + delta).max(0.0),
+                None => bail!("No stock record for {}", sku),
+            }
+        }
+        Ok(())
+    })?;
+    write_reservations(data_dir, reservations)
+}
+
+/// Reserve the direct components of `quantity` units of an assembly, through
+/// phantom lines to their components, for the work order. Fails without
+/// reserving anything if any component is short.
+pub fn reserve_for_order(
+    data_dir: &Path,
+    work_order: &str,
+    sales_order: &str,
+    assembly_sku: &str,
+    quantity: f64,
+) -> Result<Vec<Reservation>> {
+    if work_order.trim().is_empty() {
+        bail!("Work order is required");
+    }
+    if quantity <= 0.0 {
+        bail!("Quantity to reserve must be positive");
+    }
+
+    let data = load_data_dir(data_dir)?;
+    if !data.assemblies.iter().any(|a| a.assembly_sku == assembly_sku) {
+        bail!("Unknown assembly: {}", assembly_sku);
+    }
+    data.check_complete(assembly_sku)?;
+    // Reserve what a build deducts, so recording it consumes the reservation
+    let mut required: Vec<(String, f64)> = direct_requirements(assembly_sku, quantity, &data.bom_items)?
+        .into_iter()
+        .collect();
+    if required.is_empty() {
+        bail!("{} has no BOM lines", assembly_sku);
+    }
+    required.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let short: Vec<String> = required.iter()
+        .filter_map(|(sku, qty)| {
+            let available = data.stock.iter()
+                .find(|s| &s.sku == sku)
+                .map(|s| s.on_hand_qty - s.reserved_qty)
+                .unwrap_or(0.0);
+            (available + 1e-9 < *qty).then(|| format!("{} (need {}, available {})", sku, qty, available))
+        })
+        .collect();
+    if !short.is_empty() {
+        bail!("Not enough stock to reserve: {}", short.join(", "));
+    }
+
+    let created_at = chrono::Utc::now().to_rfc3339();
+    let created: Vec<Reservation> = required.iter()
+        .map(|(sku, qty)| Reservation {
+            reservation_id: uuid::Uuid::new_v4().to_string(),
+            work_order: work_order.to_string(),
+            sales_order: sales_order.to_string(),
+            assembly_sku: assembly_sku.to_string(),
+            sku: sku.clone(),
+            qty_reserved: *qty,
+            qty_consumed: 0.0,
+            qty_released: 0.0,
+            created_at: created_at.clone(),
+            status: ReservationStatus::Active,
+        })
+        .collect();
+
+    let mut reservations = load_reservations(data_dir)?;
+    reservations.extend(created.iter().cloned());
+    let changes: HashMap<String, f64> = required.into_iter().collect();
+    with_rollback(data_dir, RESERVATION_FILES, || change_reserved(data_dir, &changes, &reservations))?;
+    Ok(created)
+}
+
+/// Release the open quantity of a work order's active reservations, or only
+/// the one for `sku` when given.
+pub fn release_reservations(data_dir: &Path, work_order: &str, sku: Option<&str>) -> Result<Vec<Reservation>> {
+    let mut reservations = load_reservations(data_dir)?;
+    let mut changes: HashMap<String, f64> = HashMap::new();
+    let mut released = Vec::new();
+    for r in reservations.iter_mut() {
+        if r.work_order != work_order || r.status != ReservationStatus::Active {
+            continue;
+        }
+        if sku.map(|s| s != r.sku).unwrap_or(false) {
+            continue;
+        }
+        let open = r.open_qty();
+        *changes.entry(r.sku.clone()).or_insert(0.0) -= open;
+        r.qty_released += open;
+        r.status = ReservationStatus::Released;
+        released.push(r.clone());
+    }
+    if released.is_empty() {
+        bail!("No active reservations for {}", work_order);
+    }
+
+    with_rollback(data_dir, RESERVATION_FILES, || change_reserved(data_dir, &changes, &reservations))?;
+    Ok(released)
+}
+
+/// Draw down a work order's reservations by the quantities a build consumed,
+/// so the deducted stock is no longer also held as reserved. Runs inside the
+/// build's rollback, which covers `RESERVATION_FILES`.
+pub fn consume_reservations(data_dir: &Path, work_order: &str, consumed: &HashMap<String, f64>) -> Result<()> {
+    let mut reservations = load_reservations(data_dir)?;
+    let mut changes: HashMap<String, f64> = HashMap::new();
+    for r in reservations.iter_mut() {
+        if r.work_order != work_order || r.status != ReservationStatus::Active {
+            continue;
+        }
+        let already = changes.get(&r.sku).copied().unwrap_or(0.0).abs();
+        let remaining = consumed.get(&r.sku).copied().unwrap_or(0.0) - already;
+        let take = remaining.min(r.open_qty());
+        if take <= 0.0 {
+            continue;
+        }
+        r.qty_consumed += take;
+        if r.open_qty() <= 1e-9 {
+            r.status = ReservationStatus::Consumed;
+        }
+        *changes.entry(r.sku.clone()).or_insert(0.0) -= take;
+    }
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    change_reserved(data_dir, &changes, &reservations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    /// A data directory with one assembly `A` built from 2 x `P1` and 1 x `P2`.
+    fn fixture() -> TestDir {
+        let dir = TestDir::new();
+        dir.write("assemblies.csv", "assembly_sku,name,uom\nA,Assembly,ea\n");
+        dir.write("parts.csv", "part_sku,name,uom\nP1,Part one,ea\nP2,Part two,ea\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\n\
+            A,P1,2,0,1,false\n\
+            A,P2,1,0,1,false\n");
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,10,0\nP2,5,0\n");
+        dir
+    }
+
+    fn receipt(sku: &str, qty: f64, po_number: Option<&str>) -> ReceiptRequest {
+        ReceiptRequest {
+            sku: sku.to_string(),
+            qty,
+            po_number: po_number.map(str::to_string),
+            lot: None,
+            received_by: "sam".to_string(),
+            close_po_lines: false,
+        }
+    }
+
+    fn on_hand(dir: &TestDir, sku: &str) -> f64 {
+        load_data_dir(dir.path()).unwrap().stock.iter()
+            .find(|s| s.sku == sku)
+            .map(|s| s.on_hand_qty)
+            .unwrap_or(0.0)
+    }
+
+    const PO_HEADER: &str = "po_number,line_no,supplier_id,part_sku,qty_ordered,qty_received,unit_cost,order_date,expected_date,status\n";
+
+    #[test]
+    fn receive_stock_updates_stock_ledger_and_po() {
+        let dir = fixture();
+        dir.write("purchase_orders.csv", &format!("{PO_HEADER}\
+            PO-00001,1,S1,P1,4,0,1,2024-01-01,2024-02-01,open\n\
+            PO-00001,2,S1,P1,4,0,1,2024-01-01,2024-02-01,open\n"));
+
+        let result = receive_stock(dir.path(), receipt("P1", 6.0, Some("PO-00001"))).unwrap();
+        assert_eq!(result.on_hand_qty, 16.0);
+        assert_eq!(result.po_lines.len(), 2);
+        assert_eq!(result.po_lines[0].status, purchasing::PoLineStatus::Received);
+        assert_eq!(result.po_lines[1].status, purchasing::PoLineStatus::Partial);
+        assert_eq!(result.po_lines[1].qty_received, 2.0);
+
+        // One ledger entry per PO line, so each keeps its own price
+        assert_eq!(result.entries.len(), 2);
+        let ledger = load_ledger(dir.path()).unwrap();
+        let booked: Vec<(f64, Option<u32>)> = ledger.iter().map(|e| (e.qty_change, e.po_line)).collect();
+        assert_eq!(booked, vec![(4.0, Some(1)), (2.0, Some(2))]);
+        assert!(ledger.iter().all(|e| e.reference.as_deref() == Some("PO-00001")));
+    }
+
+    #[test]
+    fn receive_stock_rejects_unknown_parts_and_bad_po_without_writing() {
+        let dir = fixture();
+        let po = format!("{PO_HEADER}PO-00001,1,S1,P1,4,0,1,2024-01-01,2024-02-01,open\n");
+        dir.write("purchase_orders.csv", &po);
+
+        assert!(receive_stock(dir.path(), receipt("NOPE", 1.0, None)).is_err());
+        assert!(receive_stock(dir.path(), receipt("P2", 1.0, Some("PO-00001"))).is_err());
+        assert!(receive_stock(dir.path(), receipt("P1", 1.0, Some("PO-00009"))).is_err());
+
+        assert_eq!(on_hand(&dir, "P1"), 10.0);
+        assert_eq!(on_hand(&dir, "P2"), 5.0);
+        assert!(load_ledger(dir.path()).unwrap().is_empty());
+        assert_eq!(dir.read("purchase_orders.csv"), po);
+    }
+
+    #[test]
+    fn failed_receipt_leaves_stock_and_po_unchanged() {
+        let dir = fixture();
+        let po = format!("{PO_HEADER}PO-00001,1,S1,P1,4,0,1,2024-01-01,2024-02-01,open\n");
+        dir.write("purchase_orders.csv", &po);
+        // A malformed ledger cannot be migrated to the current header
+        let ledger = "id,timestamp\n1,2024-01-01,extra\n";
+        dir.write("inventory_ledger.csv", ledger);
+
+        assert!(receive_stock(dir.path(), receipt("P1", 4.0, Some("PO-00001"))).is_err());
+        assert_eq!(dir.read("stock.csv"), "sku,on_hand_qty,reserved_qty\nP1,10,0\nP2,5,0\n");
+        assert_eq!(dir.read("purchase_orders.csv"), po);
+        assert_eq!(dir.read("inventory_ledger.csv"), ledger);
+    }
+
+    #[test]
+    fn receive_stock_without_po_writes_one_entry() {
+        let dir = fixture();
+        let result = receive_stock(dir.path(), receipt("P2", 3.0, None)).unwrap();
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].po_line, None);
+        assert_eq!(on_hand(&dir, "P2"), 8.0);
+    }
+
+    #[test]
+    fn corrupt_ledger_is_an_error() {
+        let dir = fixture();
+        dir.write("inventory_ledger.csv", "id,timestamp,sku,qty_change,kind
+1,2024-01-01T00:00:00Z,P1,lots,receipt
+");
+        assert!(load_ledger(dir.path()).is_err());
+    }
+
+    #[test]
+    fn adjustments_cannot_take_stock_below_zero() {
+        let dir = fixture();
+        let request = |qty_change| AdjustmentRequest {
+            sku: "P2".to_string(),
+            qty_change,
+            reason: AdjustmentReason::Damage,
+            operator: "sam".to_string(),
+            notes: None,
+        };
+        assert!(adjust_stock(dir.path(), request(-6.0)).is_err());
+        assert_eq!(on_hand(&dir, "P2"), 5.0);
+
+        let entry = adjust_stock(dir.path(), request(-2.0)).unwrap();
+        assert_eq!(entry.reason, Some(AdjustmentReason::Damage));
+        assert_eq!(on_hand(&dir, "P2"), 3.0);
+        assert_eq!(load_ledger(dir.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn cycle_count_posts_variances_and_closes_session() {
+        let dir = fixture();
+        let sheet = start_cycle_count(dir.path(), &["P1".to_string(), "P2".to_string(), "P1".to_string()]).unwrap();
+        assert_eq!(sheet.len(), 2);
+        assert_eq!(sheet[0].session_id, "CC-00001");
+
+        let counts = [
+            CountEntry { sku: "P1".to_string(), counted_qty: 8.0, reason: None },
+            CountEntry { sku: "P2".to_string(), counted_qty: 5.0, reason: Some(AdjustmentReason::Found) },
+        ];
+        let variances = enter_cycle_counts(dir.path(), "CC-00001", &counts, "sam").unwrap();
+        assert_eq!(variances[0].variance, Some(-2.0));
+        assert_eq!(variances[1].variance, Some(0.0));
+
+        let posted = post_cycle_count(dir.path(), "CC-00001", AdjustmentReason::Miscount, "sam").unwrap();
+        assert_eq!(posted.len(), 1);
+        assert_eq!(posted[0].reason, Some(AdjustmentReason::Miscount));
+        assert_eq!(on_hand(&dir, "P1"), 8.0);
+
+        // The posted variance is kept even though on hand now matches the count
+        assert_eq!(cycle_count_variances(dir.path(), "CC-00001").unwrap()[0].variance, Some(-2.0));
+        assert!(post_cycle_count(dir.path(), "CC-00001", AdjustmentReason::Miscount, "sam").is_err());
+        assert!(enter_cycle_counts(dir.path(), "CC-00001", &counts, "sam").is_err());
+    }
+
+    #[test]
+    fn cycle_count_variance_is_taken_when_counted() {
+        let dir = fixture();
+        start_cycle_count(dir.path(), &["P1".to_string()]).unwrap();
+        let counts = [CountEntry { sku: "P1".to_string(), counted_qty: 8.0, reason: None }];
+        let variances = enter_cycle_counts(dir.path(), "CC-00001", &counts, "sam").unwrap();
+        assert_eq!(variances[0].system_qty, Some(10.0));
+
+        // 5 used between counting and posting must not be added back
+        crate::data::deduct_stock(dir.path(), &HashMap::from([("P1".to_string(), 5.0)])).unwrap();
+        let variances = cycle_count_variances(dir.path(), "CC-00001").unwrap();
+        assert_eq!((variances[0].on_hand_qty, variances[0].variance), (5.0, Some(-2.0)));
+
+        post_cycle_count(dir.path(), "CC-00001", AdjustmentReason::Miscount, "sam").unwrap();
+        assert_eq!(on_hand(&dir, "P1"), 3.0);
+    }
+
+    #[test]
+    fn failed_cycle_count_post_can_be_retried() {
+        let dir = fixture();
+        start_cycle_count(dir.path(), &["P1".to_string()]).unwrap();
+        let counts = [CountEntry { sku: "P1".to_string(), counted_qty: 8.0, reason: None }];
+        enter_cycle_counts(dir.path(), "CC-00001", &counts, "sam").unwrap();
+        let sheet = dir.read("cycle_counts.csv");
+
+        // Saving the closed sheet fails after the adjustment is written
+        let blocker = dir.path().join("cycle_counts.csv.tmp");
+        std::fs::create_dir(&blocker).unwrap();
+        assert!(post_cycle_count(dir.path(), "CC-00001", AdjustmentReason::Miscount, "sam").is_err());
+        assert_eq!(on_hand(&dir, "P1"), 10.0);
+        assert!(!dir.path().join("inventory_ledger.csv").exists());
+        assert_eq!(dir.read("cycle_counts.csv"), sheet);
+
+        std::fs::remove_dir(&blocker).unwrap();
+        post_cycle_count(dir.path(), "CC-00001", AdjustmentReason::Miscount, "sam").unwrap();
+        assert_eq!(on_hand(&dir, "P1"), 8.0);
+        assert_eq!(load_ledger(dir.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn cycle_count_cannot_post_uncounted_lines() {
+        let dir = fixture();
+        start_cycle_count(dir.path(), &["P1".to_string(), "P2".to_string()]).unwrap();
+        let counts = [CountEntry { sku: "P1".to_string(), counted_qty: 9.0, reason: None }];
+        enter_cycle_counts(dir.path(), "CC-00001", &counts, "sam").unwrap();
+        assert!(post_cycle_count(dir.path(), "CC-00001", AdjustmentReason::Miscount, "sam").is_err());
+        assert_eq!(on_hand(&dir, "P1"), 10.0);
+    }
+
+    #[test]
+    fn unreadable_cycle_counts_are_not_overwritten() {
+        let dir = fixture();
+        let corrupt = "session_id,sku,created_at,counted_qty,counted_by,reason,posted_variance,status\n\
+            CC-00001,P1,2024-01-01,lots,,,,open\n";
+        dir.write("cycle_counts.csv", corrupt);
+        assert!(start_cycle_count(dir.path(), &["P2".to_string()]).is_err());
+        assert_eq!(dir.read("cycle_counts.csv"), corrupt);
+    }
+
+    fn reserved(dir: &TestDir, sku: &str) -> f64 {
+        load_data_dir(dir.path()).unwrap().stock.iter()
+            .find(|s| s.sku == sku)
+            .map(|s| s.reserved_qty)
+            .unwrap_or(0.0)
+    }
+
+    #[test]
+    fn reservations_hold_components_until_consumed_or_released() {
+        let dir = fixture();
+        let created = reserve_for_order(dir.path(), "WO-1", "SO-1", "A", 3.0).unwrap();
+        assert_eq!(created.len(), 2);
+        assert_eq!(reserved(&dir, "P1"), 6.0);
+        assert_eq!(reserved(&dir, "P2"), 3.0);
+
+        // Only 4 of P1 and 2 of P2 are left available
+        assert!(reserve_for_order(dir.path(), "WO-2", "SO-1", "A", 3.0).is_err());
+        assert_eq!(reserved(&dir, "P1"), 6.0);
+
+        let consumed = HashMap::from([("P1".to_string(), 2.0), ("P2".to_string(), 1.0)]);
+        consume_reservations(dir.path(), "WO-1", &consumed).unwrap();
+        assert_eq!(reserved(&dir, "P1"), 4.0);
+
+        let released = release_reservations(dir.path(), "WO-1", Some("P2")).unwrap();
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].qty_released, 2.0);
+        assert_eq!(reserved(&dir, "P2"), 0.0);
+
+        let statuses: Vec<ReservationStatus> = load_reservations(dir.path()).unwrap().iter().map(|r| r.status).collect();
+        assert_eq!(statuses, [ReservationStatus::Active, ReservationStatus::Released]);
+    }
+
+    #[test]
+    fn reservations_blow_through_phantom_lines() {
+        let dir = fixture();
+        dir.write("assemblies.csv", "assembly_sku,name,uom\nA,Assembly,ea\nKIT,Kit,ea\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\n\
+            A,P1,2,0,1,false\n\
+            A,KIT,1,0,1,true\n\
+            KIT,P2,2,0,1,false\n");
+        let created = reserve_for_order(dir.path(), "WO-1", "SO-1", "A", 2.0).unwrap();
+        let skus: Vec<(&str, f64)> = created.iter().map(|r| (r.sku.as_str(), r.qty_reserved)).collect();
+        assert_eq!(skus, vec![("P1", 4.0), ("P2", 4.0)]);
+        assert_eq!(reserved(&dir, "P2"), 4.0);
+
+        // A build deducts the same components, consuming the reservation
+        let deducted = crate::data::update_stock_after_build(dir.path(), "A", 2.0, &load_data_dir(dir.path()).unwrap().bom_items).unwrap();
+        assert_eq!(deducted.get("P2"), Some(&4.0));
+        assert!(!deducted.contains_key("KIT"));
+    }
+
+    #[test]
+    fn failed_reservation_leaves_reserved_quantities_unchanged() {
+        let dir = fixture();
+        reserve_for_order(dir.path(), "WO-1", "SO-1", "A", 1.0).unwrap();
+        let reservations = dir.read("reservations.csv");
+
+        let blocker = dir.path().join("reservations.csv.tmp");
+        std::fs::create_dir(&blocker).unwrap();
+        assert!(reserve_for_order(dir.path(), "WO-2", "SO-1", "A", 1.0).is_err());
+        assert!(release_reservations(dir.path(), "WO-1", None).is_err());
+        assert_eq!(reserved(&dir, "P1"), 2.0);
+        assert_eq!(reserved(&dir, "P2"), 1.0);
+        assert_eq!(dir.read("reservations.csv"), reservations);
+    }
+
+    #[test]
+    fn unreadable_reservations_are_not_overwritten() {
+        let dir = fixture();
+        let corrupt = "reservation_id,work_order,sales_order,assembly_sku,sku,qty_reserved,qty_consumed,qty_released,created_at,status\n\
+            R1,WO-1,SO-1,A,P1,two,0,0,2024-01-01,active\n";
+        dir.write("reservations.csv", corrupt);
+        assert!(reserve_for_order(dir.path(), "WO-2", "SO-1", "A", 1.0).is_err());
+        assert_eq!(dir.read("reservations.csv"), corrupt);
+        assert_eq!(reserved(&dir, "P1"), 0.0);
+    }
+}
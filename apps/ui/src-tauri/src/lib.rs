@@ -1,11 +1,45 @@
+mod bom;
+mod costs;
 mod data;
+mod eco;
+mod export;
+mod forecast;
+mod history;
+mod inventory;
+mod master;
+mod operators;
+mod orders;
+mod purchasing;
+mod routing;
+mod substitutes;
+mod uom;
+mod valuation;
 
+use std::collections::HashMap;
 use std::path::PathBuf;
-use data::{DataSnapshot, BuildHistoryRecord, InventoryItem, load_data_dir, add_build_record, update_stock_after_build, read_csv_optional, add_panel_history_record};
+use bom::{BomComparison, BomRevision, BomSide, BomTreeRow, WhereUsed};
+use costs::{CostMethod, CostRollup, PartCost, StandardCost};
+use data::{Assembly, BomItem, DataSnapshot, Part, BuildHistoryRecord, InventoryItem, ReconciliationReport, load_data_dir};
+use eco::{EcoAuditEntry, EcoDetail, EcoImpact, EcoRequest};
+use export::ExportFormat;
+use forecast::{CoverageReport, CoverageSortField, DemandForecast, ForecastRequest};
+use history::{OperatorProductionReport, Period, HistoryQuery, HistoryPage, ProductionAnalytics,
+    ConsumptionHistory};
+use inventory::{LedgerEntry, ReceiptRequest, ReceiptResult, AdjustmentRequest, AdjustmentReason,
+    CycleCountLine, CountEntry, CountVariance, Reservation};
+use master::{RenameReport, SkuReference};
+use operators::Operator;
+use orders::{BuildResult, WorkOrder, WorkOrderRequest, WorkOrderStatus, Customer, SalesOrderLine,
+    SalesOrderLineRequest, FulfillmentLine};
+use purchasing::{Supplier, PurchaseOrderLine, Shortage, ShortageLine};
+use routing::{CapacityBuildability, LaborReport, OperationLabor, RoutingOperation, WorkCenter};
+use substitutes::{Substitute, SubstituteBuildability};
+use uom::UomConversion;
+use valuation::{InventoryValuation, ValuationMethod};
 
 #[tauri::command]
-fn load_data(data_dir: String) -> Result<DataSnapshot, String> {
-    println!("🦀 load_data command called with path: {}", data_dir);
+fn load_data(data_dir: String, as_of: Option<String>) -> Result<DataSnapshot, String> {
+    println!("🦀 load_data command called with path: {} (BOM as of {:?})", data_dir, as_of);
     let path = PathBuf::from(data_dir);
     
     if !path.exists() {
@@ -14,9 +48,20 @@ fn load_data(data_dir: String) -> Result<DataSnapshot, String> {
         return Err(error_msg);
     }
     
+    let bom_date = match parse_as_of(as_of) {
+        Ok(date) => date,
+        Err(error_msg) => {
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    
     println!("📂 Directory exists, loading data...");
-    match load_data_dir(&path) {
-        Ok(data) => {
+    match data::load_data_dir_as_of(&path, bom_date) {
+        Ok(mut data) => {
+            if let Some(inventory) = data.inventory.as_mut() {
+                purchasing::apply_on_order(&path, inventory);
+            }
             println!("✅ Data loaded successfully: {} assemblies, {} parts, {} bom_items, {} stock",
                 data.assemblies.len(), data.parts.len(), data.bom_items.len(), data.stock.len());
             Ok(data)
@@ -29,6 +74,15 @@ fn load_data(data_dir: String) -> Result<DataSnapshot, String> {
     }
 }
 
+/// Parse an optional YYYY-MM-DD date argument, defaulting to today.
+fn parse_as_of(as_of: Option<String>) -> Result<chrono::NaiveDate, String> {
+    match as_of.filter(|d| !d.trim().is_empty()) {
+        Some(date) => chrono::NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("❌ Invalid date '{}', expected YYYY-MM-DD", date)),
+        None => Ok(chrono::Local::now().date_naive()),
+    }
+}
+
 #[tauri::command]
 fn load_panel_history(data_dir: String) -> Result<Vec<BuildHistoryRecord>, String> {
     println!("🦀 load_panel_history command called with path: {}", data_dir);
@@ -40,20 +94,16 @@ fn load_panel_history(data_dir: String) -> Result<Vec<BuildHistoryRecord>, Strin
         return Err(error_msg);
     }
     
-    let history_file = path.join("panel_history.csv");
-    if !history_file.exists() {
-        println!("📄 Panel history file doesn't exist yet, returning empty list");
-        return Ok(Vec::new());
-    }
-    
-    match read_csv_optional::<BuildHistoryRecord>(&history_file) {
-        Some(history) => {
+    // Rows that cannot be read are left out rather than hiding the whole history
+    match history::load_panel_history(&path) {
+        Ok(history) => {
             println!("✅ Panel history loaded: {} records", history.len());
             Ok(history)
         },
-        None => {
-            println!("📄 No panel history found, returning empty list");
-            Ok(Vec::new())
+        Err(e) => {
+            let error_msg = format!("❌ Error loading panel history: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
         }
     }
 }
@@ -68,13 +118,90 @@ fn record_build(
     quantity_built: f64,
     operator: Option<String>,
     notes: Option<String>,
-) -> Result<DataSnapshot, String> {
+    labor_minutes: Option<f64>,
+    labor: Option<Vec<OperationLabor>>,
+) -> Result<BuildResult, String> {
     println!("🦀 record_build command called: {} units of {}", quantity_built, assembly_sku);
     let path = PathBuf::from(&data_dir);
     
+    // Check the build against its work order before writing anything
+    if let Err(e) = orders::validate_build(&path, &work_order, &assembly_sku, quantity_built) {
+        let error_msg = format!("❌ Invalid build: {e:#}");
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    // Check the sales order and record the customer under its canonical ID
+    let customer = match orders::validate_build_order(&path, &sales_order, &customer, &assembly_sku) {
+        Ok(customer_id) => customer_id,
+        Err(e) => {
+            let error_msg = format!("❌ Invalid build: {e:#}");
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    
+    // Record the operator under their roster ID
+    let operator = match operators::resolve_operator(&path, operator.as_deref()) {
+        Ok(operator_id) => operator_id,
+        Err(e) => {
+            let error_msg = format!("❌ Invalid build: {e:#}");
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    
     // Generate unique ID and timestamp
     let id = uuid::Uuid::new_v4().to_string();
-    let timestamp = chrono::Utc::now().to_rfc3339();
+    let now = chrono::Utc::now();
+    let timestamp = now.to_rfc3339();
+    
+    // Load current data to plan the stock deduction, substitutes included
+    let build_date = now.date_naive();
+    let data = match data::load_data_dir_as_of(&path, build_date) {
+        Ok(data) => data,
+        Err(e) => {
+            let error_msg = format!("❌ Error loading data for stock update: {e:#}");
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    if let Err(e) = data.check_complete(&assembly_sku) {
+        let error_msg = format!("❌ Invalid build: {e:#}");
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    // The BOM revision effective on the build date drives the deduction
+    let bom_revision = match bom::effective_revision(&data.bom_revisions, &assembly_sku, build_date) {
+        Ok(revision) => revision.map(|r| r.revision.clone()),
+        Err(e) => {
+            let error_msg = format!("❌ Invalid BOM revision: {e:#}");
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    let plan = match substitutes::plan_build(&path, &assembly_sku, &work_order, quantity_built, &data.bom_items, &data.stock) {
+        Ok(plan) => plan,
+        Err(e) => {
+            let error_msg = format!("❌ Error planning stock deduction: {e:#}");
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    
+    // Labor booked to operations must be in the routings the build runs
+    let operations = labor.unwrap_or_default();
+    let (labor_minutes, labor_rows) = match routing::check_build_labor(
+        &path, &id, &assembly_sku, &data.bom_items, labor_minutes, &operations,
+    ) {
+        Ok(labor) => labor,
+        Err(e) => {
+            let error_msg = format!("❌ Invalid build: {e:#}");
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
     
     let record = BuildHistoryRecord {
         id,
@@ -86,53 +213,40 @@ fn record_build(
         quantity_built,
         operator,
         notes,
+        bom_revision,
+        substitutions: substitutes::describe(&plan.substitutions),
+        labor_minutes,
     };
     
-    // Add the build record to both files
-    if let Err(e) = add_build_record(&path, &record) {
-        let error_msg = format!("❌ Error recording build: {e:#}");
-        println!("{}", error_msg);
-        return Err(error_msg);
-    }
-    
-    // Also add to panel_history.csv
-    if let Err(e) = add_panel_history_record(&path, &record) {
-        let error_msg = format!("❌ Error recording panel history: {e:#}");
-        println!("{}", error_msg);
-        return Err(error_msg);
-    }
+    // Deduct stock, update reservations and the work order, then append the
+    // history; any failure puts every file back as it was
+    let work_order = match orders::post_build(&path, &record, &plan.consumed, &labor_rows) {
+        Ok(work_order) => work_order,
+        Err(e) => {
+            let error_msg = format!("❌ Error recording build: {e:#}");
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
     
-    // Load current data to update stock
+    // Reload data to get updated state
     match load_data_dir(&path) {
-        Ok(mut data) => {
-            // Update stock quantities
-            if let Err(e) = update_stock_after_build(
-                &path,
-                &assembly_sku,
-                quantity_built,
-                &data.bom_items,
-                &mut data.stock
-            ) {
-                let error_msg = format!("❌ Error updating stock: {e:#}");
-                println!("{}", error_msg);
-                return Err(error_msg);
+        Ok(mut updated_data) => {
+            if let Some(inventory) = updated_data.inventory.as_mut() {
+                purchasing::apply_on_order(&path, inventory);
             }
-            
-            // Reload data to get updated state
-            match load_data_dir(&path) {
-                Ok(updated_data) => {
-                    println!("✅ Build recorded and stock updated successfully");
-                    Ok(updated_data)
-                },
-                Err(e) => {
-                    let error_msg = format!("❌ Error reloading data: {e:#}");
-                    println!("{}", error_msg);
-                    Err(error_msg)
-                }
+            if let Some(substitutions) = &record.substitutions {
+                println!("🔁 Substitutes used: {}", substitutions);
             }
+            match &work_order {
+                Some(wo) => println!("✅ Build recorded and stock updated, {} remaining on {}",
+                    wo.remaining_qty(), wo.work_order),
+                None => println!("✅ Build recorded and stock updated successfully"),
+            }
+            Ok(BuildResult { snapshot: updated_data, work_order })
         },
         Err(e) => {
-            let error_msg = format!("❌ Error loading data for stock update: {e:#}");
+            let error_msg = format!("❌ Error reloading data: {e:#}");
             println!("{}", error_msg);
             Err(error_msg)
         }
@@ -150,9 +264,10 @@ fn load_main_inventory(data_dir: String) -> Result<Vec<InventoryItem>, String> {
         return Err(error_msg);
     }
     
-    match data::load_main_inventory(&path) {
-        Ok(inventory) => {
-            println!("✅ Main inventory loaded successfully: {} items", inventory.len());
+    match data::load_inventory(&path) {
+        Ok(mut inventory) => {
+            purchasing::apply_on_order(&path, &mut inventory);
+            println!("✅ Inventory loaded from {:?}: {} items", data::inventory_source(&path), inventory.len());
             Ok(inventory)
         },
         Err(e) => {
@@ -163,6 +278,1773 @@ fn load_main_inventory(data_dir: String) -> Result<Vec<InventoryItem>, String> {
     }
 }
 
+#[tauri::command]
+fn reconcile_inventory(data_dir: String) -> Result<ReconciliationReport, String> {
+    println!("🦀 reconcile_inventory command called with path: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match data::reconcile_inventory(&path) {
+        Ok(report) => {
+            println!("✅ Inventory reconciled: {} SKUs compared, {} discrepancies",
+                report.compared_skus, report.discrepancies.len());
+            Ok(report)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error reconciling inventory: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_suppliers(data_dir: String) -> Result<Vec<Supplier>, String> {
+    println!("🦀 load_suppliers command called with path: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match purchasing::load_suppliers(&path) {
+        Ok(suppliers) => {
+            println!("✅ Suppliers loaded: {} records", suppliers.len());
+            Ok(suppliers)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading suppliers: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn save_supplier(data_dir: String, supplier: Supplier) -> Result<Vec<Supplier>, String> {
+    println!("🦀 save_supplier command called: {}", supplier.supplier_id);
+    let path = PathBuf::from(data_dir);
+    
+    match purchasing::save_supplier(&path, supplier) {
+        Ok(suppliers) => {
+            println!("✅ Supplier saved, {} suppliers on file", suppliers.len());
+            Ok(suppliers)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error saving supplier: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_purchase_orders(data_dir: String) -> Result<Vec<PurchaseOrderLine>, String> {
+    println!("🦀 load_purchase_orders command called with path: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match purchasing::load_purchase_orders(&path) {
+        Ok(lines) => {
+            println!("✅ Purchase orders loaded: {} lines", lines.len());
+            Ok(lines)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading purchase orders: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_on_order(data_dir: String, by_date: Option<String>) -> Result<HashMap<String, f64>, String> {
+    println!("🦀 load_on_order command called, by date: {:?}", by_date);
+    let path = PathBuf::from(data_dir);
+    
+    match purchasing::on_order_quantities(&path, by_date.as_deref()) {
+        Ok(on_order) => {
+            println!("✅ On-order quantities computed for {} SKUs", on_order.len());
+            Ok(on_order)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error computing on-order quantities: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn shortage_report(data_dir: String, assembly_sku: String, quantity: f64) -> Result<Vec<Shortage>, String> {
+    println!("🦀 shortage_report command called: {} units of {}", quantity, assembly_sku);
+    let path = PathBuf::from(data_dir);
+    
+    match purchasing::shortage_report(&path, &assembly_sku, quantity) {
+        Ok(shortages) => {
+            println!("✅ Shortage report: {} parts short", shortages.len());
+            Ok(shortages)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error building shortage report: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn create_purchase_order(
+    data_dir: String,
+    supplier_id: String,
+    expected_date: String,
+    lines: Vec<ShortageLine>,
+) -> Result<Vec<PurchaseOrderLine>, String> {
+    println!("🦀 create_purchase_order command called: {} lines for {}", lines.len(), supplier_id);
+    let path = PathBuf::from(data_dir);
+    
+    match purchasing::create_purchase_order(&path, &supplier_id, &expected_date, &lines) {
+        Ok(created) => {
+            println!("✅ Purchase order created with {} lines", created.len());
+            Ok(created)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error creating purchase order: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn receive_stock(data_dir: String, receipt: ReceiptRequest) -> Result<ReceiptResult, String> {
+    println!("🦀 receive_stock command called: {} x {}", receipt.qty, receipt.sku);
+    let path = PathBuf::from(data_dir);
+    let sku = receipt.sku.clone();
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match inventory::receive_stock(&path, receipt) {
+        Ok(result) => {
+            println!("✅ Stock received: {} now {} on hand, {} PO lines updated",
+                sku, result.on_hand_qty, result.po_lines.len());
+            Ok(result)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error receiving stock: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_inventory_ledger(data_dir: String) -> Result<Vec<LedgerEntry>, String> {
+    println!("🦀 load_inventory_ledger command called with path: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match inventory::load_ledger(&path) {
+        Ok(ledger) => {
+            println!("✅ Inventory ledger loaded: {} entries", ledger.len());
+            Ok(ledger)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading inventory ledger: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn adjust_stock(data_dir: String, adjustment: AdjustmentRequest) -> Result<LedgerEntry, String> {
+    println!("🦀 adjust_stock command called: {} {:+} ({:?})",
+        adjustment.sku, adjustment.qty_change, adjustment.reason);
+    let path = PathBuf::from(data_dir);
+    
+    match inventory::adjust_stock(&path, adjustment) {
+        Ok(entry) => {
+            println!("✅ Stock adjusted: {} {:+}", entry.sku, entry.qty_change);
+            Ok(entry)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error adjusting stock: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_cycle_counts(data_dir: String) -> Result<Vec<CycleCountLine>, String> {
+    println!("🦀 load_cycle_counts command called with path: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match inventory::load_cycle_counts(&path) {
+        Ok(lines) => {
+            println!("✅ Cycle counts loaded: {} lines", lines.len());
+            Ok(lines)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading cycle counts: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn start_cycle_count(data_dir: String, skus: Vec<String>) -> Result<Vec<CycleCountLine>, String> {
+    println!("🦀 start_cycle_count command called for {} SKUs", skus.len());
+    let path = PathBuf::from(data_dir);
+    
+    match inventory::start_cycle_count(&path, &skus) {
+        Ok(sheet) => {
+            println!("✅ Count sheet created with {} lines", sheet.len());
+            Ok(sheet)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error starting cycle count: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn enter_cycle_counts(
+    data_dir: String,
+    session_id: String,
+    counts: Vec<CountEntry>,
+    counted_by: String,
+) -> Result<Vec<CountVariance>, String> {
+    println!("🦀 enter_cycle_counts command called: {} counts for {}", counts.len(), session_id);
+    let path = PathBuf::from(data_dir);
+    
+    match inventory::enter_cycle_counts(&path, &session_id, &counts, &counted_by) {
+        Ok(variances) => {
+            println!("✅ Counts recorded for {}", session_id);
+            Ok(variances)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error entering cycle counts: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn cycle_count_variances(data_dir: String, session_id: String) -> Result<Vec<CountVariance>, String> {
+    println!("🦀 cycle_count_variances command called for {}", session_id);
+    let path = PathBuf::from(data_dir);
+    
+    match inventory::cycle_count_variances(&path, &session_id) {
+        Ok(variances) => {
+            println!("✅ Variances computed for {} lines", variances.len());
+            Ok(variances)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error computing count variances: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn post_cycle_count(
+    data_dir: String,
+    session_id: String,
+    default_reason: AdjustmentReason,
+    operator: String,
+) -> Result<Vec<LedgerEntry>, String> {
+    println!("🦀 post_cycle_count command called for {}", session_id);
+    let path = PathBuf::from(data_dir);
+    
+    match inventory::post_cycle_count(&path, &session_id, default_reason, &operator) {
+        Ok(entries) => {
+            println!("✅ Cycle count {} posted with {} adjustments", session_id, entries.len());
+            Ok(entries)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error posting cycle count: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_reservations(data_dir: String) -> Result<Vec<Reservation>, String> {
+    println!("🦀 load_reservations command called with path: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match inventory::load_reservations(&path) {
+        Ok(reservations) => {
+            println!("✅ Reservations loaded: {} records", reservations.len());
+            Ok(reservations)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading reservations: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn reserve_for_order(
+    data_dir: String,
+    work_order: String,
+    sales_order: String,
+    assembly_sku: String,
+    quantity: f64,
+) -> Result<Vec<Reservation>, String> {
+    println!("🦀 reserve_for_order command called: {} units of {} for {}", quantity, assembly_sku, work_order);
+    let path = PathBuf::from(data_dir);
+    
+    match inventory::reserve_for_order(&path, &work_order, &sales_order, &assembly_sku, quantity) {
+        Ok(reservations) => {
+            println!("✅ Reserved {} components for {}", reservations.len(), work_order);
+            Ok(reservations)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error reserving stock: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn release_reservations(
+    data_dir: String,
+    work_order: String,
+    sku: Option<String>,
+) -> Result<Vec<Reservation>, String> {
+    println!("🦀 release_reservations command called for {}", work_order);
+    let path = PathBuf::from(data_dir);
+    
+    match inventory::release_reservations(&path, &work_order, sku.as_deref()) {
+        Ok(released) => {
+            println!("✅ Released {} reservations for {}", released.len(), work_order);
+            Ok(released)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error releasing reservations: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_work_orders(data_dir: String) -> Result<Vec<WorkOrder>, String> {
+    println!("🦀 load_work_orders command called with path: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match orders::load_work_orders(&path) {
+        Ok(work_orders) => {
+            println!("✅ Work orders loaded: {} records", work_orders.len());
+            Ok(work_orders)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading work orders: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn create_work_order(data_dir: String, work_order: WorkOrderRequest) -> Result<WorkOrder, String> {
+    println!("🦀 create_work_order command called: {}", work_order.work_order);
+    let path = PathBuf::from(data_dir);
+    
+    match orders::create_work_order(&path, work_order) {
+        Ok(created) => {
+            println!("✅ Work order {} created", created.work_order);
+            Ok(created)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error creating work order: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn transition_work_order(
+    data_dir: String,
+    work_order: String,
+    status: WorkOrderStatus,
+) -> Result<WorkOrder, String> {
+    println!("🦀 transition_work_order command called: {} -> {:?}", work_order, status);
+    let path = PathBuf::from(data_dir);
+    
+    match orders::transition_work_order(&path, &work_order, status) {
+        Ok(updated) => {
+            println!("✅ Work order {} is now {:?}", updated.work_order, updated.status);
+            Ok(updated)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error updating work order: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_customers(data_dir: String) -> Result<Vec<Customer>, String> {
+    println!("🦀 load_customers command called with path: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match orders::load_customers(&path) {
+        Ok(customers) => {
+            println!("✅ Customers loaded: {} records", customers.len());
+            Ok(customers)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading customers: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn save_customer(data_dir: String, customer: Customer) -> Result<Vec<Customer>, String> {
+    println!("🦀 save_customer command called: {}", customer.customer_id);
+    let path = PathBuf::from(data_dir);
+    
+    match orders::save_customer(&path, customer) {
+        Ok(customers) => {
+            println!("✅ Customer saved, {} customers on file", customers.len());
+            Ok(customers)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error saving customer: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_sales_orders(data_dir: String) -> Result<Vec<SalesOrderLine>, String> {
+    println!("🦀 load_sales_orders command called with path: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match orders::load_sales_orders(&path) {
+        Ok(lines) => {
+            println!("✅ Sales orders loaded: {} lines", lines.len());
+            Ok(lines)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading sales orders: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn create_sales_order(
+    data_dir: String,
+    sales_order: String,
+    customer: String,
+    lines: Vec<SalesOrderLineRequest>,
+) -> Result<Vec<SalesOrderLine>, String> {
+    println!("🦀 create_sales_order command called: {} for {}", sales_order, customer);
+    let path = PathBuf::from(data_dir);
+    
+    match orders::create_sales_order(&path, &sales_order, &customer, &lines) {
+        Ok(created) => {
+            println!("✅ Sales order {} created with {} lines", sales_order, created.len());
+            Ok(created)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error creating sales order: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn sales_order_fulfillment(data_dir: String, sales_order: Option<String>) -> Result<Vec<FulfillmentLine>, String> {
+    println!("🦀 sales_order_fulfillment command called for {:?}", sales_order);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match orders::sales_order_fulfillment(&path, sales_order.as_deref()) {
+        Ok(lines) => {
+            println!("✅ Fulfillment computed for {} sales order lines", lines.len());
+            Ok(lines)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error computing fulfillment: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_operators(data_dir: String) -> Result<Vec<Operator>, String> {
+    println!("🦀 load_operators command called with path: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match operators::load_operators(&path) {
+        Ok(roster) => {
+            println!("✅ Operators loaded: {} records", roster.len());
+            Ok(roster)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading operators: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn save_operator(data_dir: String, operator: Operator) -> Result<Vec<Operator>, String> {
+    println!("🦀 save_operator command called: {}", operator.operator_id);
+    let path = PathBuf::from(data_dir);
+    
+    match operators::save_operator(&path, operator) {
+        Ok(roster) => {
+            println!("✅ Operator saved, {} operators on file", roster.len());
+            Ok(roster)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error saving operator: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn operator_production(data_dir: String, period: Period) -> Result<OperatorProductionReport, String> {
+    println!("🦀 operator_production command called by {:?}", period);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match history::operator_production(&path, period) {
+        Ok(rows) => {
+            println!("✅ Operator production computed: {} rows", rows.rows.len());
+            Ok(rows)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error computing operator production: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn query_history(data_dir: String, query: HistoryQuery) -> Result<HistoryPage, String> {
+    println!("🦀 query_history command called: {:?}", query);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match history::query_history(&path, &query) {
+        Ok(page) => {
+            println!("✅ History query matched {} records, returning page {} of {}",
+                page.total, page.page, page.total_pages);
+            Ok(page)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error querying history: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn production_analytics(
+    data_dir: String,
+    period: Period,
+    from_date: Option<String>,
+    to_date: Option<String>,
+    rolling_window: Option<usize>,
+) -> Result<ProductionAnalytics, String> {
+    println!("🦀 production_analytics command called by {:?}", period);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match history::production_analytics(&path, period, from_date, to_date, rolling_window) {
+        Ok(analytics) => {
+            println!("✅ Production analytics: {} periods, {} assemblies, {} units",
+                analytics.periods.len(), analytics.series.len(), analytics.total_units);
+            Ok(analytics)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error computing production analytics: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn consumption_history(
+    data_dir: String,
+    period: Period,
+    from_date: Option<String>,
+    to_date: Option<String>,
+    sku: Option<String>,
+) -> Result<ConsumptionHistory, String> {
+    println!("🦀 consumption_history command called by {:?} for {:?}", period, sku);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match history::consumption_history(&path, period, from_date, to_date, sku) {
+        Ok(consumption) => {
+            println!("✅ Consumption history: {} components over {} periods",
+                consumption.components.len(), consumption.periods.len());
+            Ok(consumption)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error computing consumption history: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn forecast_demand(data_dir: String, request: ForecastRequest) -> Result<DemandForecast, String> {
+    println!("🦀 forecast_demand command called: {:?}", request);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match forecast::forecast_demand(&path, &request) {
+        Ok(forecast) => {
+            println!("✅ Forecast: {} assemblies, {} components, {} reorder suggestions",
+                forecast.assemblies.len(), forecast.components.len(), forecast.reorder_points.len());
+            Ok(forecast)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error forecasting demand: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn inventory_coverage(
+    data_dir: String,
+    lookback_days: Option<u32>,
+    sort_by: Option<CoverageSortField>,
+    descending: Option<bool>,
+) -> Result<CoverageReport, String> {
+    println!("🦀 inventory_coverage command called: lookback={:?} sort={:?}", lookback_days, sort_by);
+    let path = PathBuf::from(data_dir);
+    
+    if !path.exists() {
+        let error_msg = format!("❌ Data directory does not exist: {}", path.display());
+        println!("{}", error_msg);
+        return Err(error_msg);
+    }
+    
+    match forecast::inventory_coverage(
+        &path,
+        lookback_days.unwrap_or(30),
+        sort_by.unwrap_or_default(),
+        descending.unwrap_or(false),
+    ) {
+        Ok(coverage) => {
+            let at_risk = coverage.items.iter().filter(|c| c.days_of_cover.is_some()).count();
+            println!("✅ Coverage computed for {} items ({} consuming stock)", coverage.items.len(), at_risk);
+            Ok(coverage)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error computing inventory coverage: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_bom_revisions(data_dir: String) -> Result<Vec<BomRevision>, String> {
+    println!("🦀 load_bom_revisions command called with path: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    match bom::load_bom_revisions(&path) {
+        Ok(revisions) => {
+            println!("✅ Loaded {} BOM revisions", revisions.len());
+            Ok(revisions)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading BOM revisions: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn save_bom_revision(data_dir: String, revision: BomRevision) -> Result<Vec<BomRevision>, String> {
+    println!("🦀 save_bom_revision command called: {} rev {}", revision.assembly_sku, revision.revision);
+    let path = PathBuf::from(data_dir);
+    
+    match bom::save_bom_revision(&path, revision) {
+        Ok(revisions) => {
+            println!("✅ BOM revision saved");
+            Ok(revisions)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error saving BOM revision: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn release_bom_revision(
+    data_dir: String,
+    assembly_sku: String,
+    revision: String,
+    effective_from: Option<String>,
+) -> Result<Vec<BomRevision>, String> {
+    println!("🦀 release_bom_revision command called: {} rev {} from {:?}", assembly_sku, revision, effective_from);
+    let path = PathBuf::from(data_dir);
+    
+    match bom::release_bom_revision(&path, &assembly_sku, &revision, effective_from) {
+        Ok(revisions) => {
+            println!("✅ Released {} rev {}", assembly_sku, revision);
+            Ok(revisions)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error releasing BOM revision: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn explode_assembly(data_dir: String, assembly_sku: String, as_of: Option<String>) -> Result<HashMap<String, f64>, String> {
+    println!("🦀 explode_assembly command called: {} as of {:?}", assembly_sku, as_of);
+    let path = PathBuf::from(data_dir);
+    let bom_date = match parse_as_of(as_of) {
+        Ok(date) => date,
+        Err(error_msg) => {
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    
+    match data::load_data_dir_as_of(&path, bom_date)
+        .and_then(|data| {
+            data.check_complete(&assembly_sku)?;
+            bom::explode_bom(&assembly_sku, &data.bom_items)
+        })
+    {
+        Ok(requirements) => {
+            println!("✅ {} exploded into {} parts", assembly_sku, requirements.len());
+            Ok(requirements)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error exploding BOM: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_ecos(data_dir: String) -> Result<Vec<EcoDetail>, String> {
+    println!("🦀 load_ecos command called with path: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    match eco::load_eco_details(&path) {
+        Ok(ecos) => {
+            println!("✅ Loaded {} ECOs", ecos.len());
+            Ok(ecos)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading ECOs: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_eco_audit(data_dir: String) -> Result<Vec<EcoAuditEntry>, String> {
+    println!("🦀 load_eco_audit command called with path: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    match eco::load_eco_audit(&path) {
+        Ok(entries) => {
+            println!("✅ Loaded {} ECO audit entries", entries.len());
+            Ok(entries)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading ECO audit: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn propose_eco(data_dir: String, request: EcoRequest) -> Result<EcoDetail, String> {
+    println!("🦀 propose_eco command called: {} ({} changes)", request.title, request.changes.len());
+    let path = PathBuf::from(data_dir);
+    
+    match eco::propose_eco(&path, request) {
+        Ok(detail) => {
+            println!("✅ Proposed {}", detail.eco.eco_number);
+            Ok(detail)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error proposing ECO: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn preview_eco(data_dir: String, eco_number: String) -> Result<EcoImpact, String> {
+    println!("🦀 preview_eco command called: {}", eco_number);
+    let path = PathBuf::from(data_dir);
+    
+    match eco::preview_eco(&path, &eco_number) {
+        Ok(impact) => {
+            println!("✅ {} affects {} assemblies, obsoletes {} stocked parts",
+                eco_number, impact.affected_assemblies.len(), impact.obsolete_stock.len());
+            Ok(impact)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error previewing ECO: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn approve_eco(
+    data_dir: String,
+    eco_number: String,
+    approved_by: String,
+    effective_from: Option<String>,
+) -> Result<EcoDetail, String> {
+    println!("🦀 approve_eco command called: {} by {}", eco_number, approved_by);
+    let path = PathBuf::from(data_dir);
+    
+    match eco::approve_eco(&path, &eco_number, &approved_by, effective_from) {
+        Ok(detail) => {
+            println!("✅ Approved {}: {}", eco_number, detail.eco.new_revisions.as_deref().unwrap_or(""));
+            Ok(detail)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error approving ECO: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn reject_eco(
+    data_dir: String,
+    eco_number: String,
+    rejected_by: String,
+    reason: Option<String>,
+) -> Result<EcoDetail, String> {
+    println!("🦀 reject_eco command called: {} by {}", eco_number, rejected_by);
+    let path = PathBuf::from(data_dir);
+    
+    match eco::reject_eco(&path, &eco_number, &rejected_by, reason) {
+        Ok(detail) => {
+            println!("✅ Rejected {}", eco_number);
+            Ok(detail)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error rejecting ECO: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn add_bom_item(data_dir: String, item: BomItem) -> Result<Vec<BomItem>, String> {
+    println!("🦀 add_bom_item command called: {} -> {} x{}", item.parent_assembly_sku, item.component_sku, item.qty_per);
+    let path = PathBuf::from(data_dir);
+    
+    match bom::add_bom_item(&path, item) {
+        Ok(bom_items) => {
+            println!("✅ BOM line added ({} lines)", bom_items.len());
+            Ok(bom_items)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error adding BOM line: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn update_bom_item(data_dir: String, item: BomItem) -> Result<Vec<BomItem>, String> {
+    println!("🦀 update_bom_item command called: {} -> {} x{}", item.parent_assembly_sku, item.component_sku, item.qty_per);
+    let path = PathBuf::from(data_dir);
+    
+    match bom::update_bom_item(&path, item) {
+        Ok(bom_items) => {
+            println!("✅ BOM line updated");
+            Ok(bom_items)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error updating BOM line: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn remove_bom_item(
+    data_dir: String,
+    parent_assembly_sku: String,
+    component_sku: String,
+    revision: Option<String>,
+) -> Result<Vec<BomItem>, String> {
+    println!("🦀 remove_bom_item command called: {} -> {} (rev {:?})", parent_assembly_sku, component_sku, revision);
+    let path = PathBuf::from(data_dir);
+    
+    match bom::remove_bom_item(&path, &parent_assembly_sku, &component_sku, revision.as_deref()) {
+        Ok(bom_items) => {
+            println!("✅ BOM line removed ({} lines left)", bom_items.len());
+            Ok(bom_items)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error removing BOM line: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn create_assembly(data_dir: String, assembly: Assembly) -> Result<Vec<Assembly>, String> {
+    println!("🦀 create_assembly command called: {}", assembly.assembly_sku);
+    let path = PathBuf::from(data_dir);
+    
+    match master::create_assembly(&path, assembly) {
+        Ok(assemblies) => {
+            println!("✅ Assembly created ({} assemblies)", assemblies.len());
+            Ok(assemblies)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error creating assembly: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn create_part(data_dir: String, part: Part) -> Result<Vec<Part>, String> {
+    println!("🦀 create_part command called: {}", part.part_sku);
+    let path = PathBuf::from(data_dir);
+    
+    match master::create_part(&path, part) {
+        Ok(result) => {
+            println!("✅ Part created");
+            Ok(result)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error creating part: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn update_part(data_dir: String, part: Part) -> Result<Vec<Part>, String> {
+    println!("🦀 update_part command called: {}", part.part_sku);
+    let path = PathBuf::from(data_dir);
+    
+    match master::update_part(&path, part) {
+        Ok(result) => {
+            println!("✅ Part updated");
+            Ok(result)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error updating part: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn delete_part(data_dir: String, part_sku: String) -> Result<Vec<Part>, String> {
+    println!("🦀 delete_part command called: {}", part_sku);
+    let path = PathBuf::from(data_dir);
+    
+    match master::delete_part(&path, &part_sku) {
+        Ok(result) => {
+            println!("✅ Part deleted");
+            Ok(result)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error deleting part: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn update_assembly(data_dir: String, assembly: Assembly) -> Result<Vec<Assembly>, String> {
+    println!("🦀 update_assembly command called: {}", assembly.assembly_sku);
+    let path = PathBuf::from(data_dir);
+    
+    match master::update_assembly(&path, assembly) {
+        Ok(result) => {
+            println!("✅ Assembly updated");
+            Ok(result)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error updating assembly: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn delete_assembly(data_dir: String, assembly_sku: String) -> Result<Vec<Assembly>, String> {
+    println!("🦀 delete_assembly command called: {}", assembly_sku);
+    let path = PathBuf::from(data_dir);
+    
+    match master::delete_assembly(&path, &assembly_sku) {
+        Ok(result) => {
+            println!("✅ Assembly deleted");
+            Ok(result)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error deleting assembly: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn set_sku_active(data_dir: String, sku: String, active: bool) -> Result<(), String> {
+    println!("🦀 set_sku_active command called: {}", sku);
+    let path = PathBuf::from(data_dir);
+    
+    match master::set_active(&path, &sku, active) {
+        Ok(result) => {
+            println!("✅ Active flag updated");
+            Ok(result)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error updating active flag: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn sku_references(data_dir: String, sku: String) -> Result<Vec<SkuReference>, String> {
+    println!("🦀 sku_references command called: {}", sku);
+    let path = PathBuf::from(data_dir);
+    
+    match master::sku_references(&path, &sku) {
+        Ok(result) => {
+            println!("✅ References found");
+            Ok(result)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error finding references: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn rename_sku(data_dir: String, old_sku: String, new_sku: String) -> Result<RenameReport, String> {
+    println!("🦀 rename_sku command called: {}", old_sku);
+    let path = PathBuf::from(data_dir);
+    
+    match master::rename_sku(&path, &old_sku, &new_sku) {
+        Ok(result) => {
+            println!("✅ SKU renamed");
+            Ok(result)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error renaming SKU: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn where_used(data_dir: String, sku: String, as_of: Option<String>) -> Result<Vec<WhereUsed>, String> {
+    println!("🦀 where_used command called: {} as of {:?}", sku, as_of);
+    let path = PathBuf::from(data_dir);
+    let bom_date = match parse_as_of(as_of) {
+        Ok(date) => date,
+        Err(error_msg) => {
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    
+    match data::load_data_dir_as_of(&path, bom_date)
+        .and_then(|data| bom::where_used(&sku, &data.bom_items))
+    {
+        Ok(used_in) => {
+            println!("✅ {} is used in {} top-level assemblies", sku, used_in.len());
+            Ok(used_in)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error tracing where {} is used: {e:#}", sku);
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn bom_tree(
+    data_dir: String,
+    assembly_sku: String,
+    quantity: Option<f64>,
+    as_of: Option<String>,
+) -> Result<Vec<BomTreeRow>, String> {
+    println!("🦀 bom_tree command called: {} x{:?} as of {:?}", assembly_sku, quantity, as_of);
+    let path = PathBuf::from(data_dir);
+    let bom_date = match parse_as_of(as_of) {
+        Ok(date) => date,
+        Err(error_msg) => {
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    
+    match bom::load_bom_tree(&path, &assembly_sku, quantity.unwrap_or(1.0), bom_date) {
+        Ok(rows) => {
+            println!("✅ BOM tree for {}: {} lines", assembly_sku, rows.len());
+            Ok(rows)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error building BOM tree: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+/// Returns the exported text, and also writes it to `output_path` when given.
+#[tauri::command]
+fn export_bom_tree(
+    data_dir: String,
+    assembly_sku: String,
+    format: ExportFormat,
+    quantity: Option<f64>,
+    as_of: Option<String>,
+    output_path: Option<String>,
+) -> Result<String, String> {
+    println!("🦀 export_bom_tree command called: {} as {:?} to {:?}", assembly_sku, format, output_path);
+    let path = PathBuf::from(data_dir);
+    let bom_date = match parse_as_of(as_of) {
+        Ok(date) => date,
+        Err(error_msg) => {
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    
+    match export::export_bom_tree(&path, &assembly_sku, quantity.unwrap_or(1.0), bom_date, format)
+        .and_then(|content| export::finish_export(content, output_path.as_deref()))
+    {
+        Ok(content) => {
+            println!("✅ BOM tree exported ({} bytes)", content.len());
+            Ok(content)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error exporting BOM tree: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn compare_bom(data_dir: String, a: BomSide, b: BomSide) -> Result<BomComparison, String> {
+    println!("🦀 compare_bom command called: {:?} vs {:?}", a, b);
+    let path = PathBuf::from(data_dir);
+    
+    match bom::compare_bom(&path, &a, &b) {
+        Ok(comparison) => {
+            println!("✅ {} leaf and {} structure differences",
+                comparison.leaf.len(), comparison.structure.len());
+            Ok(comparison)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error comparing BOMs: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_substitutes(data_dir: String) -> Result<Vec<Substitute>, String> {
+    println!("🦀 load_substitutes command called with: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    match substitutes::load_substitutes(&path) {
+        Ok(substitutes) => {
+            println!("✅ Loaded {} substitutes", substitutes.len());
+            Ok(substitutes)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading substitutes: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn save_substitute(data_dir: String, substitute: Substitute) -> Result<Vec<Substitute>, String> {
+    println!("🦀 save_substitute command called: {} for {}", substitute.substitute_sku, substitute.primary_sku);
+    let path = PathBuf::from(data_dir);
+    
+    match substitutes::save_substitute(&path, substitute) {
+        Ok(substitutes) => {
+            println!("✅ Substitute saved");
+            Ok(substitutes)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error saving substitute: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn remove_substitute(
+    data_dir: String,
+    primary_sku: String,
+    substitute_sku: String,
+    assembly_sku: Option<String>,
+    group: Option<String>,
+) -> Result<Vec<Substitute>, String> {
+    println!("🦀 remove_substitute command called: {} for {}", substitute_sku, primary_sku);
+    let path = PathBuf::from(data_dir);
+    
+    match substitutes::remove_substitute(&path, &primary_sku, &substitute_sku, assembly_sku.as_deref(), group.as_deref()) {
+        Ok(substitutes) => {
+            println!("✅ Substitute removed");
+            Ok(substitutes)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error removing substitute: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn buildable_with_substitutes(
+    data_dir: String,
+    assembly_sku: String,
+    as_of: Option<String>,
+) -> Result<SubstituteBuildability, String> {
+    println!("🦀 buildable_with_substitutes command called for {}", assembly_sku);
+    let path = PathBuf::from(data_dir);
+    let bom_date = match parse_as_of(as_of) {
+        Ok(date) => date,
+        Err(error_msg) => {
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    
+    match substitutes::buildable_with_substitutes(&path, &assembly_sku, bom_date) {
+        Ok(buildability) => {
+            println!("✅ {} buildable, {} with substitutes",
+                buildability.primary_only, buildability.with_substitutes);
+            Ok(buildability)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error computing buildability: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_uom_conversions(data_dir: String) -> Result<Vec<UomConversion>, String> {
+    println!("🦀 load_uom_conversions command called with: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    match uom::load_uom_conversions(&path) {
+        Ok(conversions) => {
+            println!("✅ Loaded {} unit conversions", conversions.len());
+            Ok(conversions)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading unit conversions: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn save_uom_conversion(data_dir: String, conversion: UomConversion) -> Result<Vec<UomConversion>, String> {
+    println!("🦀 save_uom_conversion command called: {} -> {}", conversion.from_uom, conversion.to_uom);
+    let path = PathBuf::from(data_dir);
+    
+    match uom::save_uom_conversion(&path, conversion) {
+        Ok(conversions) => {
+            println!("✅ Unit conversion saved");
+            Ok(conversions)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error saving unit conversion: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn remove_uom_conversion(
+    data_dir: String,
+    from_uom: String,
+    to_uom: String,
+    sku: Option<String>,
+) -> Result<Vec<UomConversion>, String> {
+    println!("🦀 remove_uom_conversion command called: {} -> {}", from_uom, to_uom);
+    let path = PathBuf::from(data_dir);
+    
+    match uom::remove_uom_conversion(&path, &from_uom, &to_uom, sku.as_deref()) {
+        Ok(conversions) => {
+            println!("✅ Unit conversion removed");
+            Ok(conversions)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error removing unit conversion: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn part_costs(data_dir: String) -> Result<Vec<PartCost>, String> {
+    println!("🦀 part_costs command called with: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    
+    match costs::part_costs(&path) {
+        Ok(costs) => {
+            println!("✅ Loaded costs for {} SKUs", costs.len());
+            Ok(costs)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading part costs: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn import_standard_costs(data_dir: String, source_path: String) -> Result<Vec<StandardCost>, String> {
+    println!("🦀 import_standard_costs command called: {}", source_path);
+    let path = PathBuf::from(data_dir);
+    
+    match costs::import_standard_costs(&path, &PathBuf::from(source_path)) {
+        Ok(costs) => {
+            println!("✅ {} standard costs on file", costs.len());
+            Ok(costs)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error importing standard costs: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn cost_rollup(
+    data_dir: String,
+    assembly_sku: String,
+    method: Option<CostMethod>,
+    as_of: Option<String>,
+) -> Result<CostRollup, String> {
+    println!("🦀 cost_rollup command called for {} ({:?})", assembly_sku, method);
+    let path = PathBuf::from(data_dir);
+    let bom_date = match parse_as_of(as_of) {
+        Ok(date) => date,
+        Err(error_msg) => {
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    
+    match costs::cost_rollup(&path, &assembly_sku, method.unwrap_or_default(), bom_date) {
+        Ok(rollup) => {
+            println!("✅ {} material cost {:.2} per unit, {} parts without a cost",
+                rollup.assembly_sku, rollup.material_cost, rollup.missing_costs.len());
+            Ok(rollup)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error rolling up cost: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+/// Returns the exported text, and also writes it to `output_path` when given.
+#[tauri::command]
+fn export_build_history(
+    data_dir: String,
+    query: HistoryQuery,
+    format: ExportFormat,
+    method: Option<CostMethod>,
+    output_path: Option<String>,
+) -> Result<String, String> {
+    println!("🦀 export_build_history command called: {:?} as {:?} to {:?}", query, format, output_path);
+    let path = PathBuf::from(data_dir);
+    
+    match export::export_build_history(&path, &query, method.unwrap_or_default(), format)
+        .and_then(|content| export::finish_export(content, output_path.as_deref()))
+    {
+        Ok(content) => {
+            println!("✅ Build history exported ({} bytes)", content.len());
+            Ok(content)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error exporting build history: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn inventory_valuation(
+    data_dir: String,
+    method: Option<ValuationMethod>,
+    as_of: Option<String>,
+) -> Result<InventoryValuation, String> {
+    println!("🦀 inventory_valuation command called: {:?} as of {:?}", method, as_of);
+    let path = PathBuf::from(data_dir);
+    let as_of = match parse_as_of(as_of) {
+        Ok(date) => date,
+        Err(error_msg) => {
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    
+    match valuation::inventory_valuation(&path, method.unwrap_or_default(), as_of) {
+        Ok(valuation) => {
+            println!("✅ Inventory valued at {:.2} across {} SKUs", valuation.total_value, valuation.lines.len());
+            Ok(valuation)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error valuing inventory: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+/// Returns the exported text, and also writes it to `output_path` when given.
+#[tauri::command]
+fn export_inventory_valuation(
+    data_dir: String,
+    format: ExportFormat,
+    method: Option<ValuationMethod>,
+    as_of: Option<String>,
+    output_path: Option<String>,
+) -> Result<String, String> {
+    println!("🦀 export_inventory_valuation command called: {:?} as {:?} to {:?}", method, format, output_path);
+    let path = PathBuf::from(data_dir);
+    let as_of = match parse_as_of(as_of) {
+        Ok(date) => date,
+        Err(error_msg) => {
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    
+    match export::export_inventory_valuation(&path, method.unwrap_or_default(), as_of, format)
+        .and_then(|content| export::finish_export(content, output_path.as_deref()))
+    {
+        Ok(content) => {
+            println!("✅ Inventory valuation exported ({} bytes)", content.len());
+            Ok(content)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error exporting inventory valuation: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_work_centers(data_dir: String) -> Result<Vec<WorkCenter>, String> {
+    println!("🦀 load_work_centers command called with: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    match routing::load_work_centers(&path) {
+        Ok(work_centers) => {
+            println!("✅ Loaded {} work centers", work_centers.len());
+            Ok(work_centers)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading work centers: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn save_work_center(data_dir: String, work_center: WorkCenter) -> Result<Vec<WorkCenter>, String> {
+    println!("🦀 save_work_center command called: {}", work_center.work_center);
+    let path = PathBuf::from(data_dir);
+    
+    match routing::save_work_center(&path, work_center) {
+        Ok(work_centers) => {
+            println!("✅ Work center saved");
+            Ok(work_centers)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error saving work center: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn load_routings(data_dir: String) -> Result<Vec<RoutingOperation>, String> {
+    println!("🦀 load_routings command called with: {}", data_dir);
+    let path = PathBuf::from(data_dir);
+    match routing::load_routings(&path) {
+        Ok(routings) => {
+            println!("✅ Loaded {} routing operations", routings.len());
+            Ok(routings)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error loading routings: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn save_routing_operation(data_dir: String, operation: RoutingOperation) -> Result<Vec<RoutingOperation>, String> {
+    println!("🦀 save_routing_operation command called: {} op {}", operation.assembly_sku, operation.operation_seq);
+    let path = PathBuf::from(data_dir);
+    
+    match routing::save_routing_operation(&path, operation) {
+        Ok(routings) => {
+            println!("✅ Routing operation saved");
+            Ok(routings)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error saving routing operation: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn remove_routing_operation(
+    data_dir: String,
+    assembly_sku: String,
+    operation_seq: u32,
+) -> Result<Vec<RoutingOperation>, String> {
+    println!("🦀 remove_routing_operation command called: {} op {}", assembly_sku, operation_seq);
+    let path = PathBuf::from(data_dir);
+    
+    match routing::remove_routing_operation(&path, &assembly_sku, operation_seq) {
+        Ok(routings) => {
+            println!("✅ Routing operation removed");
+            Ok(routings)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error removing routing operation: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn labor_report(data_dir: String, query: HistoryQuery) -> Result<LaborReport, String> {
+    println!("🦀 labor_report command called: {:?}", query);
+    let path = PathBuf::from(data_dir);
+    
+    match routing::labor_report(&path, &query) {
+        Ok(report) => {
+            println!("✅ Labor report over {} builds", report.builds.len());
+            Ok(report)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error building labor report: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
+#[tauri::command]
+fn capacity_buildable(
+    data_dir: String,
+    assembly_sku: String,
+    days: Option<f64>,
+    as_of: Option<String>,
+) -> Result<CapacityBuildability, String> {
+    println!("🦀 capacity_buildable command called for {} over {:?} days", assembly_sku, days);
+    let path = PathBuf::from(data_dir);
+    let bom_date = match parse_as_of(as_of) {
+        Ok(date) => date,
+        Err(error_msg) => {
+            println!("{}", error_msg);
+            return Err(error_msg);
+        }
+    };
+    
+    match routing::capacity_buildable(&path, &assembly_sku, days.unwrap_or(1.0), bom_date) {
+        Ok(capacity) => {
+            println!("✅ {} buildable ({} by material), limited by {}",
+                capacity.buildable, capacity.material_limited, capacity.limited_by);
+            Ok(capacity)
+        },
+        Err(e) => {
+            let error_msg = format!("❌ Error computing capacity: {e:#}");
+            println!("{}", error_msg);
+            Err(error_msg)
+        }
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
   println!("🦀 Tauri app starting...");
@@ -180,7 +2062,27 @@ pub fn run() {
       println!("✅ Tauri setup complete");
       Ok(())
     })
-    .invoke_handler(tauri::generate_handler![load_data, record_build, load_panel_history, load_main_inventory])
+    .plugin(tauri_plugin_updater::Builder::new().build())
+    .invoke_handler(tauri::generate_handler![load_data, record_build, load_panel_history, load_main_inventory, reconcile_inventory,
+      load_suppliers, save_supplier, load_purchase_orders, load_on_order, shortage_report,
+      create_purchase_order, receive_stock, load_inventory_ledger,
+      adjust_stock, load_cycle_counts, start_cycle_count, enter_cycle_counts, cycle_count_variances,
+      post_cycle_count, load_reservations, reserve_for_order, release_reservations,
+      load_work_orders, create_work_order, transition_work_order,
+      load_customers, save_customer, load_sales_orders, create_sales_order, sales_order_fulfillment,
+      load_operators, save_operator, operator_production, query_history,
+      production_analytics, consumption_history, forecast_demand,
+      inventory_coverage, load_bom_revisions, save_bom_revision, release_bom_revision, explode_assembly,
+      load_ecos, load_eco_audit, propose_eco, preview_eco, approve_eco, reject_eco,
+      add_bom_item, update_bom_item, remove_bom_item, create_assembly,
+      create_part, update_part, delete_part, update_assembly, delete_assembly, set_sku_active,
+      sku_references, rename_sku, where_used, bom_tree, export_bom_tree,
+      compare_bom, load_substitutes, save_substitute, remove_substitute, buildable_with_substitutes,
+      load_uom_conversions, save_uom_conversion, remove_uom_conversion,
+      part_costs, import_standard_costs, cost_rollup, export_build_history,
+      inventory_valuation, export_inventory_valuation,
+      load_work_centers, save_work_center, load_routings, save_routing_operation, remove_routing_operation,
+      labor_report, capacity_buildable])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
 }
@@ -0,0 +1,478 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+use crate::bom::KnownSkus;
+use crate::data::{load_assemblies, load_parts, with_rollback, write_csv, Assembly, Part};
+use crate::uom::{check_bom_units, load_uom_conversions, UomTable};
+
+/// Every CSV column that stores a part or assembly SKU. Renames rewrite all
+/// of them; the where-used check reads them.
+const SKU_COLUMNS: &[(&str, &[&str])] = &[
+    ("assemblies.csv", &["assembly_sku"]),
+    ("parts.csv", &["part_sku"]),
+    ("bom_items.csv", &["parent_assembly_sku", "component_sku"]),
+    ("bom_revisions.csv", &["assembly_sku"]),
+    ("stock.csv", &["sku"]),
+    ("main_inventory.csv", &["sku"]),
+    ("purchase_orders.csv", &["part_sku"]),
+    ("inventory_ledger.csv", &["sku"]),
+    ("cycle_counts.csv", &["sku"]),
+    ("reservations.csv", &["assembly_sku", "sku"]),
+    ("work_orders.csv", &["assembly_sku"]),
+    ("sales_orders.csv", &["assembly_sku"]),
+    ("build_history.csv", &["assembly_sku"]),
+    ("panel_history.csv", &["assembly_sku"]),
+    ("eco_changes.csv", &["parent_assembly_sku", "component_sku"]),
+    ("substitutes.csv", &["primary_sku", "substitute_sku", "assembly_sku"]),
+    ("uom_conversions.csv", &["sku"]),
+    ("part_costs.csv", &["sku"]),
+    ("routings.csv", &["assembly_sku"]),
+    ("build_labor.csv", &["assembly_sku"]),
+];
+
+/// Rewrites `(text, old_sku, new_sku)` with the SKU renamed inside the text.
+type RenameInText = fn(&str, &str, &str) -> String;
+
+/// Text columns with SKUs embedded in them, and how to rename one inside the
+/// text. Renames rewrite these along with `SKU_COLUMNS`.
+const SKU_TEXT_COLUMNS: &[(&str, &str, RenameInText)] = &[
+    ("build_history.csv", "substitutions", rename_in_substitutions),
+    ("panel_history.csv", "substitutions", rename_in_substitutions),
+    ("ecos.csv", "new_revisions", rename_in_revisions),
+];
+
+/// Rename within a build's substitutions, e.g. `B2 for B x4; C2 for C x1`.
+fn rename_in_substitutions(text: &str, old_sku: &str, new_sku: &str) -> String {
+    let rename = |sku: &str| if sku == old_sku { new_sku.to_string() } else { sku.to_string() };
+    text.split("; ")
+        .map(|entry| {
+            let Some((substitute, rest)) = entry.split_once(" for ") else {
+                return entry.to_string();
+            };
+            let Some((primary, qty)) = rest.rsplit_once(" x") else {
+                return entry.to_string();
+            };
+            format!("{} for {} x{}", rename(substitute), rename(primary), qty)
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Rename within an ECO's released revisions, e.g. `A:B;C:A`.
+fn rename_in_revisions(text: &str, old_sku: &str, new_sku: &str) -> String {
+    text.split(';')
+        .map(|entry| match entry.rsplit_once(':') {
+            Some((assembly, revision)) if assembly == old_sku => format!("{}:{}", new_sku, revision),
+            _ => entry.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Rows of one file that mention a SKU.
+#[derive(Debug, serde::Serialize)]
+pub struct SkuReference {
+    pub file: String,
+    pub rows: usize,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct RenameReport {
+    pub old_sku: String,
+    pub new_sku: String,
+    pub files: Vec<SkuReference>,
+}
+
+fn read_records(path: &Path) -> Result<(csv::StringRecord, Vec<csv::StringRecord>)> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .with_context(|| format!("Failed to open CSV: {}", path.display()))?;
+    let header = rdr.headers()
+        .with_context(|| format!("Failed to read header of {}", path.display()))?
+        .clone();
+    let records = rdr.records()
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok((header, records))
+}
+
+fn column_indexes(header: &csv::StringRecord, columns: &[&str]) -> Vec<usize> {
+    header.iter()
+        .enumerate()
+        .filter(|(_, name)| columns.contains(name))
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// Number of rows per file that mention `sku` in a SKU column.
+pub fn sku_references(data_dir: &Path, sku: &str) -> Result<Vec<SkuReference>> {
+    let mut references = Vec::new();
+    for (file, columns) in SKU_COLUMNS {
+        let path = data_dir.join(file);
+        if !path.exists() {
+            continue;
+        }
+        let (header, records) = read_records(&path)?;
+        let indexes = column_indexes(&header, columns);
+        let rows = records.iter()
+            .filter(|r| indexes.iter().any(|&i| r.get(i) == Some(sku)))
+            .count();
+        if rows > 0 {
+            references.push(SkuReference { file: file.to_string(), rows });
+        }
+    }
+    Ok(references)
+}
+
+/// Refuse to delete a SKU that a BOM line uses or that still has stock.
+fn check_deletable(data_dir: &Path, sku: &str) -> Result<()> {
+    let references = sku_references(data_dir, sku)?;
+    let rows_in = |file: &str| references.iter().find(|r| r.file == file).map(|r| r.rows).unwrap_or(0);
+    let bom_rows = rows_in("bom_items.csv");
+    if bom_rows > 0 {
+        bail!("{} is used on {} BOM lines; remove them or deactivate it instead", sku, bom_rows);
+    }
+    let substitute_rows = rows_in("substitutes.csv");
+    if substitute_rows > 0 {
+        bail!("{} is in {} substitute groups; remove them or deactivate it instead", sku, substitute_rows);
+    }
+    let routing_rows = rows_in("routings.csv");
+    if routing_rows > 0 {
+        bail!("{} has {} routing operations; remove them or deactivate it instead", sku, routing_rows);
+    }
+    for file in ["stock.csv", "main_inventory.csv"] {
+        let path = data_dir.join(file);
+        if !path.exists() {
+            continue;
+        }
+        let (header, records) = read_records(&path)?;
+        let sku_idx = column_indexes(&header, &["sku"]);
+        let qty_idx = column_indexes(&header, &["on_hand_qty", "reserved_qty"]);
+        let stocked = records.iter()
+            .filter(|r| sku_idx.iter().any(|&i| r.get(i) == Some(sku)))
+            .any(|r| qty_idx.iter().any(|&i| r.get(i).and_then(|q| q.parse::<f64>().ok()).unwrap_or(0.0) != 0.0));
+        if stocked {
+            bail!("{} still has stock in {}; deactivate it instead", sku, file);
+        }
+    }
+    Ok(())
+}
+
+fn check_fields(sku: &str, name: &str, uom: &str) -> Result<()> {
+    if sku.is_empty() {
+        bail!("SKU is required");
+    }
+    if name.is_empty() {
+        bail!("Name is required");
+    }
+    if uom.is_empty() {
+        bail!("Unit of measure is required");
+    }
+    Ok(())
+}
+
+fn write_parts(data_dir: &Path, parts: &[Part]) -> Result<()> {
+    write_csv(&data_dir.join("parts.csv"), parts)
+        .context("Writing parts.csv")
+}
+
+fn write_assemblies(data_dir: &Path, assemblies: &[Assembly]) -> Result<()> {
+    write_csv(&data_dir.join("assemblies.csv"), assemblies)
+        .context("Writing assemblies.csv")
+}
+
+/// Add a part. Its SKU must not already be a part or assembly.
+pub fn create_part(data_dir: &Path, part: Part) -> Result<Vec<Part>> {
+    let part = Part {
+        part_sku: part.part_sku.trim().to_string(),
+        name: part.name.trim().to_string(),
+        uom: part.uom.trim().to_string(),
+        active: part.active,
+        category: part.category.map(|c| c.trim().to_string()).filter(|c| !c.is_empty()),
+    };
+    check_fields(&part.part_sku, &part.name, &part.uom)?;
+    if KnownSkus::load(data_dir)?.contains(&part.part_sku) {
+        bail!("SKU {} already exists", part.part_sku);
+    }
+    let mut parts = load_parts(data_dir)?;
+    parts.push(part);
+    write_parts(data_dir, &parts)?;
+    Ok(parts)
+}
+
+/// Replace the name, unit, active flag and category of an existing part.
+pub fn update_part(data_dir: &Path, part: Part) -> Result<Vec<Part>> {
+    check_fields(part.part_sku.trim(), part.name.trim(), part.uom.trim())?;
+    let mut parts = load_parts(data_dir)?;
+    let existing = parts.iter_mut()
+        .find(|p| p.part_sku == part.part_sku.trim())
+        .ok_or_else(|| anyhow!("Part {} not found", part.part_sku))?;
+    existing.name = part.name.trim().to_string();
+    existing.uom = part.uom.trim().to_string();
+    existing.active = part.active;
+    existing.category = part.category.map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+    check_bom_units(data_dir, &UomTable::new(load_uom_conversions(data_dir)?, &load_assemblies(data_dir)?, &parts))?;
+    write_parts(data_dir, &parts)?;
+    Ok(parts)
+}
+
+/// Delete a part that no BOM uses and that has no stock.
+pub fn delete_part(data_dir: &Path, part_sku: &str) -> Result<Vec<Part>> {
+    let mut parts = load_parts(data_dir)?;
+    if !parts.iter().any(|p| p.part_sku == part_sku) {
+        bail!("Part {} not found", part_sku);
+    }
+    check_deletable(data_dir, part_sku)?;
+    parts.retain(|p| p.part_sku != part_sku);
+    write_parts(data_dir, &parts)?;
+    Ok(parts)
+}
+
+/// Add an assembly. Its SKU must not already be an assembly or part.
+pub fn create_assembly(data_dir: &Path, assembly: Assembly) -> Result<Vec<Assembly>> {
+    let assembly = Assembly {
+        assembly_sku: assembly.assembly_sku.trim().to_string(),
+        name: assembly.name.trim().to_string(),
+        uom: assembly.uom.trim().to_string(),
+        active: assembly.active,
+    };
+    check_fields(&assembly.assembly_sku, &assembly.name, &assembly.uom)?;
+    if KnownSkus::load(data_dir)?.contains(&assembly.assembly_sku) {
+        bail!("SKU {} already exists", assembly.assembly_sku);
+    }
+    let mut assemblies = load_assemblies(data_dir)?;
+    assemblies.push(assembly);
+    write_assemblies(data_dir, &assemblies)?;
+    Ok(assemblies)
+}
+
+/// Replace the name, unit and active flag of an existing assembly.
+pub fn update_assembly(data_dir: &Path, assembly: Assembly) -> Result<Vec<Assembly>> {
+    check_fields(assembly.assembly_sku.trim(), assembly.name.trim(), assembly.uom.trim())?;
+    let mut assemblies = load_assemblies(data_dir)?;
+    let existing = assemblies.iter_mut()
+        .find(|a| a.assembly_sku == assembly.assembly_sku.trim())
+        .ok_or_else(|| anyhow!("Assembly {} not found", assembly.assembly_sku))?;
+    existing.name = assembly.name.trim().to_string();
+    existing.uom = assembly.uom.trim().to_string();
+    existing.active = assembly.active;
+    check_bom_units(data_dir, &UomTable::new(load_uom_conversions(data_dir)?, &assemblies, &load_parts(data_dir)?))?;
+    write_assemblies(data_dir, &assemblies)?;
+    Ok(assemblies)
+}
+
+/// Delete an assembly that has no BOM lines of its own, is used by no other
+/// BOM and has no stock.
+pub fn delete_assembly(data_dir: &Path, assembly_sku: &str) -> Result<Vec<Assembly>> {
+    let mut assemblies = load_assemblies(data_dir)?;
+    if !assemblies.iter().any(|a| a.assembly_sku == assembly_sku) {
+        bail!("Assembly {} not found", assembly_sku);
+    }
+    check_deletable(data_dir, assembly_sku)?;
+    assemblies.retain(|a| a.assembly_sku != assembly_sku);
+    write_assemblies(data_dir, &assemblies)?;
+    Ok(assemblies)
+}
+
+/// Deactivate or reactivate a part or assembly by SKU.
+pub fn set_active(data_dir: &Path, sku: &str, active: bool) -> Result<()> {
+    let mut parts = load_parts(data_dir)?;
+    if let Some(part) = parts.iter_mut().find(|p| p.part_sku == sku) {
+        part.active = active;
+        return write_parts(data_dir, &parts);
+    }
+    let mut assemblies = load_assemblies(data_dir)?;
+    if let Some(assembly) = assemblies.iter_mut().find(|a| a.assembly_sku == sku) {
+        assembly.active = active;
+        return write_assemblies(data_dir, &assemblies);
+    }
+    bail!("Unknown SKU: {}", sku)
+}
+
+/// Rename a part or assembly SKU in every file that stores SKUs, including
+/// SKUs embedded in text. All files are rewritten to temporary copies first
+/// and only swapped in once every one has been written; a failed swap puts
+/// back the files already replaced.
+pub fn rename_sku(data_dir: &Path, old_sku: &str, new_sku: &str) -> Result<RenameReport> {
+    let new_sku = new_sku.trim();
+    if new_sku.is_empty() {
+        bail!("New SKU is required");
+    }
+    let known = KnownSkus::load(data_dir)?;
+    if !known.contains(old_sku) {
+        bail!("Unknown SKU: {}", old_sku);
+    }
+    if known.contains(new_sku) {
+        bail!("SKU {} already exists", new_sku);
+    }
+
+    let mut names: Vec<&'static str> = SKU_COLUMNS.iter().map(|(file, _)| *file).collect();
+    for (file, _, _) in SKU_TEXT_COLUMNS {
+        if !names.contains(file) {
+            names.push(file);
+        }
+    }
+
+    let mut staged: Vec<(PathBuf, &str)> = Vec::new();
+    let mut files = Vec::new();
+    let result = (|| -> Result<()> {
+        for file in names {
+            let path = data_dir.join(file);
+            if !path.exists() {
+                continue;
+            }
+            let (header, records) = read_records(&path)?;
+            let columns = SKU_COLUMNS.iter()
+                .find(|(f, _)| *f == file)
+                .map(|(_, columns)| *columns)
+                .unwrap_or_default();
+            let indexes = column_indexes(&header, columns);
+            let text_columns: Vec<(usize, RenameInText)> = SKU_TEXT_COLUMNS.iter()
+                .filter(|(f, _, _)| *f == file)
+                .flat_map(|(_, column, rename)| column_indexes(&header, &[column]).into_iter().map(move |i| (i, *rename)))
+                .collect();
+
+            let mut rows = 0;
+            let records: Vec<csv::StringRecord> = records.into_iter()
+                .map(|record| {
+                    let renamed: csv::StringRecord = record.iter()
+                        .enumerate()
+                        .map(|(i, value)| {
+                            if indexes.contains(&i) && value == old_sku {
+                                return new_sku.to_string();
+                            }
+                            match text_columns.iter().find(|(idx, _)| *idx == i) {
+                                Some((_, rename)) => rename(value, old_sku, new_sku),
+                                None => value.to_string(),
+                            }
+                        })
+                        .collect();
+                    if renamed != record {
+                        rows += 1;
+                    }
+                    renamed
+                })
+                .collect();
+            if rows == 0 {
+                continue;
+            }
+
+            let tmp_path = path.with_extension("csv.tmp");
+            staged.push((tmp_path.clone(), file));
+            let mut wtr = csv::Writer::from_path(&tmp_path)
+                .with_context(|| format!("Failed to open {} for writing", tmp_path.display()))?;
+            wtr.write_record(&header)
+                .and_then(|_| records.iter().try_for_each(|r| wtr.write_record(r)))
+                .and_then(|_| wtr.flush().map_err(csv::Error::from))
+                .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+            files.push(SkuReference { file: file.to_string(), rows });
+        }
+        Ok(())
+    })();
+
+    let swapped = result.and_then(|_| {
+        let targets: Vec<&str> = staged.iter().map(|(_, file)| *file).collect();
+        with_rollback(data_dir, &targets, || {
+            for (tmp_path, file) in &staged {
+                let path = data_dir.join(file);
+                std::fs::rename(tmp_path, &path)
+                    .with_context(|| format!("Failed to replace {}", path.display()))?;
+            }
+            Ok(())
+        })
+    });
+    if let Err(e) = swapped {
+        for (tmp_path, _) in &staged {
+            let _ = std::fs::remove_file(tmp_path);
+        }
+        return Err(e);
+    }
+
+    Ok(RenameReport {
+        old_sku: old_sku.to_string(),
+        new_sku: new_sku.to_string(),
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    fn fixture() -> TestDir {
+        let dir = TestDir::new();
+        dir.write("assemblies.csv", "assembly_sku,name,uom,active\nA,Assembly,ea,true\n");
+        dir.write("parts.csv", "part_sku,name,uom\nP1,Part one,ea\nP2,Part two,ea\nP3,Part three,ea\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\nA,P1,2,0,1,false\n");
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,10,0\nP3,0,0\n");
+        dir
+    }
+
+    fn part(sku: &str) -> Part {
+        Part {
+            part_sku: sku.to_string(),
+            name: format!("Part {}", sku),
+            uom: "ea".to_string(),
+            active: true,
+            category: None,
+        }
+    }
+
+    #[test]
+    fn skus_must_be_unique_across_parts_and_assemblies() {
+        let dir = fixture();
+        assert!(create_part(dir.path(), part("A")).is_err());
+        assert!(create_part(dir.path(), part("P1")).is_err());
+        let parts = create_part(dir.path(), part(" P4 ")).unwrap();
+        assert!(parts.iter().any(|p| p.part_sku == "P4"));
+        assert!(create_part(dir.path(), Part { name: " ".to_string(), ..part("P5") }).is_err());
+    }
+
+    #[test]
+    fn used_or_stocked_skus_cannot_be_deleted() {
+        let dir = fixture();
+        assert!(delete_part(dir.path(), "P1").unwrap_err().to_string().contains("BOM lines"));
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP2,1,0\n");
+        assert!(delete_part(dir.path(), "P2").unwrap_err().to_string().contains("still has stock"));
+        let parts = delete_part(dir.path(), "P3").unwrap();
+        assert!(!parts.iter().any(|p| p.part_sku == "P3"));
+
+        set_active(dir.path(), "P1", false).unwrap();
+        assert!(!load_parts(dir.path()).unwrap().iter().find(|p| p.part_sku == "P1").unwrap().active);
+    }
+
+    #[test]
+    fn rename_rewrites_sku_columns_and_embedded_skus() {
+        let dir = fixture();
+        dir.write("panel_history.csv", "id,timestamp,assembly_sku,quantity_built,substitutions\n\
+            b1,2024-01-01T00:00:00Z,A,1,P2 for P1 x4; P1 for P3 x1\n");
+        dir.write("ecos.csv", "eco_number,title,status,proposed_by,proposed_at,new_revisions\n\
+            ECO-00001,Change,approved,dana,2024-01-01T00:00:00Z,A:B;AB:C\n");
+
+        let report = rename_sku(dir.path(), "P1", "P1-NEW").unwrap();
+        assert!(dir.read("bom_items.csv").contains("A,P1-NEW,2"));
+        assert!(dir.read("panel_history.csv").contains("P2 for P1-NEW x4; P1-NEW for P3 x1"));
+        let files: Vec<&str> = report.files.iter().map(|f| f.file.as_str()).collect();
+        assert!(files.contains(&"panel_history.csv"));
+
+        rename_sku(dir.path(), "A", "ASM").unwrap();
+        assert!(dir.read("ecos.csv").contains("ASM:B;AB:C"));
+        assert!(dir.read("panel_history.csv").contains(",ASM,"));
+    }
+
+    #[test]
+    fn failed_rename_changes_nothing() {
+        let dir = fixture();
+        let corrupt = "sku,on_hand_qty,reserved_qty\nP1,10\n";
+        dir.write("stock.csv", corrupt);
+        let bom = dir.read("bom_items.csv");
+
+        assert!(rename_sku(dir.path(), "P1", "P9").is_err());
+        assert_eq!(dir.read("bom_items.csv"), bom);
+        assert_eq!(dir.read("stock.csv"), corrupt);
+        assert!(!dir.path().join("bom_items.csv.tmp").exists());
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+
+use crate::data::{read_csv_if_exists, write_csv};
+
+/// A row of `operators.csv`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Operator {
+    pub operator_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub badge_number: Option<String>,
+    pub active: bool,
+}
+
+impl Operator {
+    /// Whether `text` names this operator by ID, name or badge number, ignoring case.
+    pub fn matches(&self, text: &str) -> bool {
+        let text = text.trim();
+        self.operator_id.eq_ignore_ascii_case(text)
+            || self.name.eq_ignore_ascii_case(text)
+            || self.badge_number.as_deref().map(|b| b.eq_ignore_ascii_case(text)).unwrap_or(false)
+    }
+}
+
+pub fn load_operators(data_dir: &Path) -> Result<Vec<Operator>> {
+    read_csv_if_exists::<Operator>(&data_dir.join("operators.csv"))
+        .context("Reading operators.csv")
+}
+
+/// Insert an operator, or replace the one with the same `operator_id`.
+/// Badge numbers must be unique across the roster.
+pub fn save_operator(data_dir: &Path, operator: Operator) -> Result<Vec<Operator>> {
+    if operator.operator_id.trim().is_empty() || operator.name.trim().is_empty() {
+        bail!("Operator ID and name are required");
+    }
+    let mut operators = load_operators(data_dir)?;
+    if let Some(badge) = operator.badge_number.as_deref().filter(|b| !b.trim().is_empty()) {
+        if let Some(other) = operators.iter().find(|o| {
+            o.operator_id != operator.operator_id
+                && o.badge_number.as_deref().map(|b| b.eq_ignore_ascii_case(badge)).unwrap_or(false)
+        }) {
+            bail!("Badge {} is already assigned to {}", badge, other.operator_id);
+        }
+    }
+
+    match operators.iter_mut().find(|o| o.operator_id == operator.operator_id) {
+        Some(existing) => *existing = operator,
+        None => operators.push(operator),
+    }
+    write_csv(&data_dir.join("operators.csv"), &operators)
+        .context("Writing operators.csv")?;
+    Ok(operators)
+}
+
+/// Map the operator entered on a build to a canonical operator ID. Without an
+/// `operators.csv` the text is accepted unchanged; blank means no operator.
+pub fn resolve_operator(data_dir: &Path, text: Option<&str>) -> Result<Option<String>> {
+    let Some(text) = text.map(str::trim).filter(|t| !t.is_empty()) else {
+        return Ok(None);
+    };
+    let operators = load_operators(data_dir)?;
+    if operators.is_empty() {
+        return Ok(Some(text.to_string()));
+    }
+    match operators.iter().find(|o| o.matches(text)) {
+        Some(o) if o.active => Ok(Some(o.operator_id.clone())),
+        Some(o) => bail!("Operator {} is inactive", o.operator_id),
+        None => bail!("Unknown operator: {}", text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    fn operator(operator_id: &str, name: &str, badge_number: Option<&str>, active: bool) -> Operator {
+        Operator {
+            operator_id: operator_id.to_string(),
+            name: name.to_string(),
+            badge_number: badge_number.map(str::to_string),
+            active,
+        }
+    }
+
+    #[test]
+    fn operators_resolve_by_id_name_or_badge() {
+        let dir = TestDir::new();
+        assert_eq!(resolve_operator(dir.path(), Some(" sam ")).unwrap().as_deref(), Some("sam"));
+        assert_eq!(resolve_operator(dir.path(), Some("  ")).unwrap(), None);
+
+        save_operator(dir.path(), operator("OP1", "Sam Lee", Some("B-17"), true)).unwrap();
+        save_operator(dir.path(), operator("OP2", "Kim Park", None, false)).unwrap();
+        assert_eq!(resolve_operator(dir.path(), Some("b-17")).unwrap().as_deref(), Some("OP1"));
+        assert_eq!(resolve_operator(dir.path(), Some("sam lee")).unwrap().as_deref(), Some("OP1"));
+        assert!(resolve_operator(dir.path(), Some("Kim Park")).is_err());
+        assert!(resolve_operator(dir.path(), Some("Nobody")).is_err());
+    }
+
+    #[test]
+    fn badge_numbers_are_unique() {
+        let dir = TestDir::new();
+        save_operator(dir.path(), operator("OP1", "Sam Lee", Some("B-17"), true)).unwrap();
+        assert!(save_operator(dir.path(), operator("OP2", "Kim Park", Some("b-17"), true)).is_err());
+        // Re-saving the same operator keeps its own badge
+        assert_eq!(save_operator(dir.path(), operator("OP1", "Sam Lee", Some("B-17"), false)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn unreadable_operators_are_not_overwritten() {
+        let dir = TestDir::new();
+        let corrupt = "operator_id,name,badge_number,active\nOP1,Sam Lee,,sometimes\n";
+        dir.write("operators.csv", corrupt);
+        assert!(save_operator(dir.path(), operator("OP2", "Kim Park", None, true)).is_err());
+        assert!(resolve_operator(dir.path(), Some("Sam Lee")).is_err());
+        assert_eq!(dir.read("operators.csv"), corrupt);
+    }
+}
@@ -0,0 +1,653 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::data::{
+    add_build_record, add_panel_history_record, deduct_stock, default_true, load_assemblies, load_data_dir,
+    parse_date, read_csv_if_exists, with_rollback, write_csv, BuildHistoryRecord, DataSnapshot,
+};
+use crate::history::load_panel_history;
+use crate::inventory::{append_ledger, build_ledger_entries, consume_reservations, load_reservations};
+use crate::routing::{append_build_labor, BuildLabor};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkOrderStatus {
+    Planned,
+    Released,
+    InProgress,
+    Completed,
+    Closed,
+}
+
+impl WorkOrderStatus {
+    /// Allowed lifecycle moves: planned -> released -> in_progress -> completed -> closed.
+    /// A released order can go back to planned, and a completed one can be reopened.
+    pub fn can_transition_to(self, next: WorkOrderStatus) -> bool {
+        use WorkOrderStatus::*;
+        matches!(
+            (self, next),
+            (Planned, Released)
+                | (Released, Planned)
+                | (Released, InProgress)
+                | (InProgress, Completed)
+                | (Completed, InProgress)
+                | (Completed, Closed)
+        )
+    }
+
+    /// Whether builds can be recorded against an order in this state.
+    pub fn accepts_builds(self) -> bool {
+        matches!(self, WorkOrderStatus::Released | WorkOrderStatus::InProgress)
+    }
+}
+
+/// A row of `work_orders.csv`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorkOrder {
+    pub work_order: String,
+    pub assembly_sku: String,
+    pub quantity: f64,
+    pub quantity_completed: f64,
+    pub due_date: String,
+    pub customer: String,
+    #[serde(default)]
+    pub sales_order: Option<String>,
+    pub status: WorkOrderStatus,
+    pub created_at: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+impl WorkOrder {
+    pub fn remaining_qty(&self) -> f64 {
+        (self.quantity - self.quantity_completed).max(0.0)
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct WorkOrderRequest {
+    pub work_order: String,
+    pub assembly_sku: String,
+    pub quantity: f64,
+    pub due_date: String,
+    pub customer: String,
+    #[serde(default)]
+    pub sales_order: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// A row of `customers.csv`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Customer {
+    pub customer_id: String,
+    pub name: String,
+    /// Other spellings seen in history, separated by ';' (e.g. "Beacon;beacon inc")
+    #[serde(default)]
+    pub aliases: Option<String>,
+    #[serde(default = "default_true")]
+    pub active: bool,
+}
+
+impl Customer {
+    /// Whether `text` names this customer by ID, name or alias, ignoring case.
+    pub fn matches(&self, text: &str) -> bool {
+        let text = text.trim();
+        self.customer_id.eq_ignore_ascii_case(text)
+            || self.name.eq_ignore_ascii_case(text)
+            || self.aliases.as_deref().unwrap_or("")
+                .split(';')
+                .any(|alias| alias.trim().eq_ignore_ascii_case(text))
+    }
+}
+
+/// One line of `sales_orders.csv`. A sales order is the set of lines sharing
+/// a `sales_order` number.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SalesOrderLine {
+    pub sales_order: String,
+    pub line_no: u32,
+    pub customer_id: String,
+    pub assembly_sku: String,
+    pub qty_ordered: f64,
+    pub order_date: String,
+    #[serde(default)]
+    pub due_date: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SalesOrderLineRequest {
+    pub assembly_sku: String,
+    pub qty_ordered: f64,
+    #[serde(default)]
+    pub due_date: Option<String>,
+}
+
+/// Built (shipped) against ordered quantity for one sales order line, counted
+/// from `panel_history.csv`.
+#[derive(Debug, serde::Serialize)]
+pub struct FulfillmentLine {
+    pub sales_order: String,
+    pub line_no: u32,
+    pub customer_id: String,
+    pub assembly_sku: String,
+    pub qty_ordered: f64,
+    pub qty_shipped: f64,
+    pub qty_remaining: f64,
+}
+
+/// What `record_build` returns: the refreshed data plus the work order the
+/// build was recorded against, if it is a managed one.
+#[derive(Debug, serde::Serialize)]
+pub struct BuildResult {
+    #[serde(flatten)]
+    pub snapshot: DataSnapshot,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub work_order: Option<WorkOrder>,
+}
+
+pub fn load_work_orders(data_dir: &Path) -> Result<Vec<WorkOrder>> {
+    read_csv_if_exists::<WorkOrder>(&data_dir.join("work_orders.csv"))
+        .context("Reading work_orders.csv")
+}
+
+fn write_work_orders(data_dir: &Path, orders: &[WorkOrder]) -> Result<()> {
+    write_csv(&data_dir.join("work_orders.csv"), orders)
+        .context("Writing work_orders.csv")
+}
+
+/// Create a work order in the planned state.
+pub fn create_work_order(data_dir: &Path, request: WorkOrderRequest) -> Result<WorkOrder> {
+    if request.work_order.trim().is_empty() {
+        bail!("Work order number is required");
+    }
+    if request.quantity <= 0.0 {
+        bail!("Work order quantity must be positive");
+    }
+    parse_date(&request.due_date)?;
+
+    let data = load_data_dir(data_dir)?;
+    if !data.assemblies.iter().any(|a| a.assembly_sku == request.assembly_sku) {
+        bail!("Unknown assembly: {}", request.assembly_sku);
+    }
+    let customer = match &request.sales_order {
+        Some(so) => validate_build_order(data_dir, so, &request.customer, &request.assembly_sku)?,
+        None => resolve_customer(data_dir, &request.customer)?,
+    };
+    let mut orders = load_work_orders(data_dir)?;
+    if orders.iter().any(|o| o.work_order == request.work_order) {
+        bail!("Work order {} already exists", request.work_order);
+    }
+
+    let order = WorkOrder {
+        work_order: request.work_order,
+        assembly_sku: request.assembly_sku,
+        quantity: request.quantity,
+        quantity_completed: 0.0,
+        due_date: request.due_date,
+        customer,
+        sales_order: request.sales_order,
+        status: WorkOrderStatus::Planned,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        notes: request.notes,
+    };
+    orders.push(order.clone());
+    write_work_orders(data_dir, &orders)?;
+    Ok(order)
+}
+
+/// Move a work order to `status` if the lifecycle allows it.
+pub fn transition_work_order(data_dir: &Path, work_order: &str, status: WorkOrderStatus) -> Result<WorkOrder> {
+    let mut orders = load_work_orders(data_dir)?;
+    let order = orders.iter_mut()
+        .find(|o| o.work_order == work_order)
+        .ok_or_else(|| anyhow!("Work order {} not found", work_order))?;
+    if !order.status.can_transition_to(status) {
+        bail!("Work order {} cannot move from {:?} to {:?}", work_order, order.status, status);
+    }
+    order.status = status;
+    let updated = order.clone();
+    write_work_orders(data_dir, &orders)?;
+    Ok(updated)
+}
+
+/// Check a build against its work order before anything is written.
+/// Work order numbers not in `work_orders.csv` are accepted as free text.
+pub fn validate_build(data_dir: &Path, work_order: &str, assembly_sku: &str, quantity_built: f64) -> Result<()> {
+    if quantity_built <= 0.0 {
+        bail!("Quantity built must be positive");
+    }
+    if let Some(assembly) = load_assemblies(data_dir)?.iter().find(|a| a.assembly_sku == assembly_sku) {
+        if !assembly.active {
+            bail!("Assembly {} is inactive", assembly_sku);
+        }
+    }
+    let orders = load_work_orders(data_dir)?;
+    let Some(order) = orders.iter().find(|o| o.work_order == work_order) else {
+        return Ok(());
+    };
+    if !order.status.accepts_builds() {
+        bail!("Work order {} is {:?} and cannot accept builds", work_order, order.status);
+    }
+    if order.assembly_sku != assembly_sku {
+        bail!("Work order {} is for {}, not {}", work_order, order.assembly_sku, assembly_sku);
+    }
+    if quantity_built > order.remaining_qty() + 1e-9 {
+        bail!("Work order {} only has {} remaining", work_order, order.remaining_qty());
+    }
+    Ok(())
+}
+
+/// Files a recorded build writes, all restored if any step of it fails.
+const BUILD_FILES: &[&str] = &[
+    "stock.csv",
+    "main_inventory.csv",
+    "inventory_ledger.csv",
+    "reservations.csv",
+    "work_orders.csv",
+    "build_labor.csv",
+    "build_history.csv",
+    "panel_history.csv",
+];
+
+/// Write a checked build: deduct `consumed` from stock, log it in the ledger,
+/// draw down the work order's reservations and completion, book `labor` to
+/// its operations, and append the history record last. If any step fails
+/// every file is put back as it was.
+pub fn post_build(
+    data_dir: &Path,
+    record: &BuildHistoryRecord,
+    consumed: &HashMap<String, f64>,
+    labor: &[BuildLabor],
+) -> Result<Option<WorkOrder>> {
+    // Read everything that gets rewritten before the first write
+    load_work_orders(data_dir)?;
+    load_reservations(data_dir)?;
+
+    with_rollback(data_dir, BUILD_FILES, || {
+        let deducted = deduct_stock(data_dir, consumed)?;
+        let entries = build_ledger_entries(&deducted, &record.work_order, record.operator.as_deref());
+        append_ledger(data_dir, &entries)?;
+        // Stock reserved for this work order has now been used
+        consume_reservations(data_dir, &record.work_order, &deducted)?;
+        let work_order = apply_build(data_dir, &record.work_order, record.quantity_built)?;
+        append_build_labor(data_dir, labor)?;
+        add_build_record(data_dir, record)?;
+        add_panel_history_record(data_dir, record)?;
+        Ok(work_order)
+    })
+}
+
+/// Add a (possibly partial) completion to a managed work order, moving it to
+/// in progress or completed. Returns `None` for free-text work orders.
+pub fn apply_build(data_dir: &Path, work_order: &str, quantity_built: f64) -> Result<Option<WorkOrder>> {
+    let mut orders = load_work_orders(data_dir)?;
+    let Some(order) = orders.iter_mut().find(|o| o.work_order == work_order) else {
+        return Ok(None);
+    };
+    order.quantity_completed += quantity_built;
+    order.status = if order.remaining_qty() <= 1e-9 {
+        WorkOrderStatus::Completed
+    } else {
+        WorkOrderStatus::InProgress
+    };
+    let updated = order.clone();
+    write_work_orders(data_dir, &orders)?;
+    Ok(Some(updated))
+}
+
+pub fn load_customers(data_dir: &Path) -> Result<Vec<Customer>> {
+    read_csv_if_exists::<Customer>(&data_dir.join("customers.csv"))
+        .context("Reading customers.csv")
+}
+
+/// Insert a customer, or replace the one with the same `customer_id`.
+/// Names and aliases must not already identify a different customer.
+pub fn save_customer(data_dir: &Path, customer: Customer) -> Result<Vec<Customer>> {
+    if customer.customer_id.trim().is_empty() || customer.name.trim().is_empty() {
+        bail!("Customer ID and name are required");
+    }
+    let mut customers = load_customers(data_dir)?;
+    let spellings = std::iter::once(customer.name.as_str())
+        .chain(customer.aliases.as_deref().unwrap_or("").split(';'))
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    for spelling in spellings {
+        if let Some(other) = customers.iter()
+            .find(|c| c.customer_id != customer.customer_id && c.matches(spelling))
+        {
+            bail!("'{}' already identifies customer {}", spelling, other.customer_id);
+        }
+    }
+
+    match customers.iter_mut().find(|c| c.customer_id == customer.customer_id) {
+        Some(existing) => *existing = customer,
+        None => customers.push(customer),
+    }
+    write_csv(&data_dir.join("customers.csv"), &customers)
+        .context("Writing customers.csv")?;
+    Ok(customers)
+}
+
+/// Map free text to a canonical customer ID. Without a `customers.csv` the
+/// text is accepted unchanged.
+pub fn resolve_customer(data_dir: &Path, text: &str) -> Result<String> {
+    let customers = load_customers(data_dir)?;
+    if customers.is_empty() {
+        return Ok(text.to_string());
+    }
+    match customers.iter().find(|c| c.matches(text)) {
+        Some(c) if c.active => Ok(c.customer_id.clone()),
+        Some(c) => bail!("Customer {} is inactive", c.customer_id),
+        None => bail!("Unknown customer: {}", text),
+    }
+}
+
+pub fn load_sales_orders(data_dir: &Path) -> Result<Vec<SalesOrderLine>> {
+    read_csv_if_exists::<SalesOrderLine>(&data_dir.join("sales_orders.csv"))
+        .context("Reading sales_orders.csv")
+}
+
+/// Create a sales order with one line per requested assembly.
+pub fn create_sales_order(
+    data_dir: &Path,
+    sales_order: &str,
+    customer: &str,
+    lines: &[SalesOrderLineRequest],
+) -> Result<Vec<SalesOrderLine>> {
+    if sales_order.trim().is_empty() {
+        bail!("Sales order number is required");
+    }
+    if lines.is_empty() {
+        bail!("A sales order needs at least one line");
+    }
+    if load_customers(data_dir)?.is_empty() {
+        bail!("Add customers before creating sales orders");
+    }
+    let customer_id = resolve_customer(data_dir, customer)?;
+    let data = load_data_dir(data_dir)?;
+    let mut all_lines = load_sales_orders(data_dir)?;
+    if all_lines.iter().any(|l| l.sales_order == sales_order) {
+        bail!("Sales order {} already exists", sales_order);
+    }
+
+    let order_date = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let mut created = Vec::new();
+    for (idx, line) in lines.iter().enumerate() {
+        if !data.assemblies.iter().any(|a| a.assembly_sku == line.assembly_sku) {
+            bail!("Unknown assembly: {}", line.assembly_sku);
+        }
+        if line.qty_ordered <= 0.0 {
+            bail!("Ordered quantity for {} must be positive", line.assembly_sku);
+        }
+        if let Some(due) = &line.due_date {
+            parse_date(due)?;
+        }
+        created.push(SalesOrderLine {
+            sales_order: sales_order.to_string(),
+            line_no: idx as u32 + 1,
+            customer_id: customer_id.clone(),
+            assembly_sku: line.assembly_sku.clone(),
+            qty_ordered: line.qty_ordered,
+            order_date: order_date.clone(),
+            due_date: line.due_date.clone(),
+        });
+    }
+
+    all_lines.extend(created.iter().cloned());
+    write_csv(&data_dir.join("sales_orders.csv"), &all_lines)
+        .context("Writing sales_orders.csv")?;
+    Ok(created)
+}
+
+/// Validate the customer and sales order of a build and return the canonical
+/// customer ID to record. Checks only apply once the master files exist.
+pub fn validate_build_order(
+    data_dir: &Path,
+    sales_order: &str,
+    customer: &str,
+    assembly_sku: &str,
+) -> Result<String> {
+    let customer_id = resolve_customer(data_dir, customer)?;
+    let so_lines = load_sales_orders(data_dir)?;
+    if so_lines.is_empty() {
+        return Ok(customer_id);
+    }
+
+    let lines: Vec<&SalesOrderLine> = so_lines.iter()
+        .filter(|l| l.sales_order == sales_order)
+        .collect();
+    if lines.is_empty() {
+        bail!("Unknown sales order: {}", sales_order);
+    }
+    if !lines.iter().any(|l| l.customer_id == customer_id) {
+        bail!("Sales order {} belongs to {}, not {}", sales_order, lines[0].customer_id, customer_id);
+    }
+    if !lines.iter().any(|l| l.assembly_sku == assembly_sku) {
+        bail!("Sales order {} has no line for {}", sales_order, assembly_sku);
+    }
+    Ok(customer_id)
+}
+
+/// Shipped versus ordered quantity per sales order line, optionally for one
+/// sales order. Builds are allocated to an order's lines for the same
+/// assembly in line order.
+pub fn sales_order_fulfillment(data_dir: &Path, sales_order: Option<&str>) -> Result<Vec<FulfillmentLine>> {
+    let history = load_panel_history(data_dir)?;
+    let mut shipped: HashMap<(String, String), f64> = HashMap::new();
+    for record in history {
+        *shipped.entry((record.sales_order, record.assembly_sku)).or_insert(0.0) += record.quantity_built;
+    }
+
+    let mut lines = load_sales_orders(data_dir)?;
+    lines.retain(|l| sales_order.map(|so| l.sales_order == so).unwrap_or(true));
+    lines.sort_by(|a, b| a.sales_order.cmp(&b.sales_order).then(a.line_no.cmp(&b.line_no)));
+
+    let last_line_for: HashMap<(String, String), u32> = lines.iter()
+        .map(|l| ((l.sales_order.clone(), l.assembly_sku.clone()), l.line_no))
+        .collect();
+
+    Ok(lines.into_iter().map(|line| {
+        let key = (line.sales_order.clone(), line.assembly_sku.clone());
+        let pool = shipped.entry(key.clone()).or_insert(0.0);
+        // The last line for an assembly takes any overshipment so nothing is lost
+        let qty_shipped = if last_line_for.get(&key) == Some(&line.line_no) {
+            *pool
+        } else {
+            pool.min(line.qty_ordered)
+        };
+        *pool -= qty_shipped;
+        FulfillmentLine {
+            qty_remaining: (line.qty_ordered - qty_shipped).max(0.0),
+            qty_shipped,
+            sales_order: line.sales_order,
+            line_no: line.line_no,
+            customer_id: line.customer_id,
+            assembly_sku: line.assembly_sku,
+            qty_ordered: line.qty_ordered,
+        }
+    }).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    fn fixture() -> TestDir {
+        let dir = TestDir::new();
+        dir.write("assemblies.csv", "assembly_sku,name,uom,active\nA,Assembly,ea,true\nOLD,Retired,ea,false\n");
+        dir.write("parts.csv", "part_sku,name,uom\nP1,Part one,ea\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\nA,P1,2,0,1,false\n");
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,10,0\n");
+        dir
+    }
+
+    fn work_order(number: &str, quantity: f64) -> WorkOrderRequest {
+        WorkOrderRequest {
+            work_order: number.to_string(),
+            assembly_sku: "A".to_string(),
+            quantity,
+            due_date: "2024-06-01".to_string(),
+            customer: "Beacon".to_string(),
+            sales_order: None,
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn work_order_lifecycle_only_allows_listed_moves() {
+        use WorkOrderStatus::*;
+        assert!(Planned.can_transition_to(Released));
+        assert!(Completed.can_transition_to(InProgress));
+        assert!(!Planned.can_transition_to(InProgress));
+        assert!(!Closed.can_transition_to(Completed));
+        assert!(Released.accepts_builds() && InProgress.accepts_builds());
+        assert!(!Planned.accepts_builds() && !Completed.accepts_builds());
+    }
+
+    #[test]
+    fn builds_are_checked_against_and_applied_to_work_orders() {
+        let dir = fixture();
+        create_work_order(dir.path(), work_order("WO-1", 5.0)).unwrap();
+        assert!(create_work_order(dir.path(), work_order("WO-1", 5.0)).is_err());
+
+        assert!(validate_build(dir.path(), "WO-1", "A", 1.0).is_err());
+        transition_work_order(dir.path(), "WO-1", WorkOrderStatus::Released).unwrap();
+        assert!(validate_build(dir.path(), "WO-1", "A", 6.0).is_err());
+        assert!(validate_build(dir.path(), "WO-1", "OTHER", 1.0).is_err());
+        assert!(validate_build(dir.path(), "FREE-TEXT", "OLD", 1.0).is_err());
+        validate_build(dir.path(), "FREE-TEXT", "A", 1.0).unwrap();
+
+        let order = apply_build(dir.path(), "WO-1", 2.0).unwrap().unwrap();
+        assert_eq!(order.status, WorkOrderStatus::InProgress);
+        let order = apply_build(dir.path(), "WO-1", 3.0).unwrap().unwrap();
+        assert_eq!(order.status, WorkOrderStatus::Completed);
+        assert!(apply_build(dir.path(), "FREE-TEXT", 1.0).unwrap().is_none());
+    }
+
+    #[test]
+    fn unreadable_work_orders_are_not_overwritten() {
+        let dir = fixture();
+        let corrupt = "work_order,assembly_sku,quantity,quantity_completed,due_date,customer,sales_order,status,created_at,notes\n\
+            WO-1,A,five,0,2024-06-01,Beacon,,planned,2024-01-01,\n";
+        dir.write("work_orders.csv", corrupt);
+        assert!(create_work_order(dir.path(), work_order("WO-2", 1.0)).is_err());
+        assert!(validate_build(dir.path(), "WO-1", "A", 1.0).is_err());
+        assert_eq!(dir.read("work_orders.csv"), corrupt);
+    }
+
+    fn build(work_order: &str, quantity_built: f64) -> BuildHistoryRecord {
+        BuildHistoryRecord {
+            id: "B1".to_string(),
+            timestamp: "2024-03-01T10:00:00+00:00".to_string(),
+            work_order: work_order.to_string(),
+            sales_order: "SO-1".to_string(),
+            customer: "Beacon".to_string(),
+            assembly_sku: "A".to_string(),
+            quantity_built,
+            operator: None,
+            notes: None,
+            bom_revision: None,
+            substitutions: None,
+            labor_minutes: None,
+        }
+    }
+
+    #[test]
+    fn post_build_writes_stock_ledger_work_order_and_history() {
+        let dir = fixture();
+        create_work_order(dir.path(), work_order("WO-1", 5.0)).unwrap();
+        transition_work_order(dir.path(), "WO-1", WorkOrderStatus::Released).unwrap();
+        crate::inventory::reserve_for_order(dir.path(), "WO-1", "SO-1", "A", 2.0).unwrap();
+
+        let consumed = HashMap::from([("P1".to_string(), 4.0)]);
+        let order = post_build(dir.path(), &build("WO-1", 2.0), &consumed, &[]).unwrap().unwrap();
+        assert_eq!(order.quantity_completed, 2.0);
+        assert_eq!(dir.read("stock.csv"), "sku,on_hand_qty,reserved_qty\nP1,6,0\n");
+        assert_eq!(crate::inventory::load_ledger(dir.path()).unwrap().len(), 1);
+        assert!(dir.read("panel_history.csv").contains("WO-1"));
+        assert!(dir.read("build_history.csv").contains("WO-1"));
+    }
+
+    #[test]
+    fn failed_post_build_leaves_every_file_unchanged() {
+        let dir = fixture();
+        create_work_order(dir.path(), work_order("WO-1", 5.0)).unwrap();
+        transition_work_order(dir.path(), "WO-1", WorkOrderStatus::Released).unwrap();
+        let work_orders = dir.read("work_orders.csv");
+        // A malformed panel history makes the last write fail
+        dir.write("panel_history.csv", "id,timestamp\nB0,2024-01-01,extra\n");
+
+        let consumed = HashMap::from([("P1".to_string(), 4.0)]);
+        let labor = BuildLabor { build_id: "B1".to_string(), operation_seq: 10, minutes: 30.0, assembly_sku: None };
+        assert!(post_build(dir.path(), &build("WO-1", 2.0), &consumed, &[labor]).is_err());
+        assert_eq!(dir.read("stock.csv"), "sku,on_hand_qty,reserved_qty\nP1,10,0\n");
+        assert_eq!(dir.read("work_orders.csv"), work_orders);
+        assert!(!dir.path().join("inventory_ledger.csv").exists());
+        assert!(!dir.path().join("build_labor.csv").exists());
+        assert!(!dir.path().join("build_history.csv").exists());
+        assert_eq!(dir.read("panel_history.csv"), "id,timestamp\nB0,2024-01-01,extra\n");
+    }
+
+    fn customer(customer_id: &str, name: &str, aliases: Option<&str>) -> Customer {
+        Customer {
+            customer_id: customer_id.to_string(),
+            name: name.to_string(),
+            aliases: aliases.map(str::to_string),
+            active: true,
+        }
+    }
+
+    #[test]
+    fn customers_resolve_by_id_name_or_alias() {
+        let dir = fixture();
+        assert_eq!(resolve_customer(dir.path(), "anyone").unwrap(), "anyone");
+
+        save_customer(dir.path(), customer("C1", "Beacon", Some("beacon inc; BCN"))).unwrap();
+        assert_eq!(resolve_customer(dir.path(), "bcn").unwrap(), "C1");
+        assert_eq!(resolve_customer(dir.path(), " BEACON ").unwrap(), "C1");
+        assert!(resolve_customer(dir.path(), "Harbor").is_err());
+        assert!(save_customer(dir.path(), customer("C2", "Harbor", Some("Beacon Inc"))).is_err());
+    }
+
+    #[test]
+    fn fulfillment_allocates_builds_to_lines_in_order() {
+        let dir = fixture();
+        save_customer(dir.path(), customer("C1", "Beacon", None)).unwrap();
+        let request = |qty_ordered| SalesOrderLineRequest {
+            assembly_sku: "A".to_string(),
+            qty_ordered,
+            due_date: None,
+        };
+        create_sales_order(dir.path(), "SO-1", "Beacon", &[request(3.0), request(2.0)]).unwrap();
+        assert!(create_sales_order(dir.path(), "SO-1", "Beacon", &[request(1.0)]).is_err());
+        assert!(validate_build_order(dir.path(), "SO-1", "Beacon", "A").is_ok());
+        assert!(validate_build_order(dir.path(), "SO-2", "Beacon", "A").is_err());
+
+        dir.write("panel_history.csv", "id,timestamp,work_order,sales_order,customer,assembly_sku,quantity_built\n\
+            B1,2024-03-01T10:00:00+00:00,WO-1,SO-1,C1,A,4\n");
+        let lines = sales_order_fulfillment(dir.path(), Some("SO-1")).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].qty_shipped, lines[0].qty_remaining), (3.0, 0.0));
+        assert_eq!((lines[1].qty_shipped, lines[1].qty_remaining), (1.0, 1.0));
+    }
+
+    #[test]
+    fn unreadable_customers_and_sales_orders_are_not_overwritten() {
+        let dir = fixture();
+        let corrupt_customers = "customer_id,name,aliases,active\nC1,Beacon,,maybe\n";
+        dir.write("customers.csv", corrupt_customers);
+        assert!(save_customer(dir.path(), customer("C2", "Harbor", None)).is_err());
+        assert_eq!(dir.read("customers.csv"), corrupt_customers);
+
+        dir.write("customers.csv", "customer_id,name,aliases,active\nC1,Beacon,,true\n");
+        let corrupt_orders = "sales_order,line_no,customer_id,assembly_sku,qty_ordered,order_date,due_date\n\
+            SO-1,first,C1,A,1,2024-01-01,\n";
+        dir.write("sales_orders.csv", corrupt_orders);
+        let line = SalesOrderLineRequest { assembly_sku: "A".to_string(), qty_ordered: 1.0, due_date: None };
+        assert!(create_sales_order(dir.path(), "SO-2", "C1", &[line]).is_err());
+        assert_eq!(dir.read("sales_orders.csv"), corrupt_orders);
+    }
+}
@@ -0,0 +1,357 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::bom::explode_bom;
+use crate::data::{load_data_dir, parse_date, read_csv_if_exists, write_csv, InventoryItem};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Supplier {
+    pub supplier_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub contact: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub lead_time_days: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoLineStatus {
+    Open,
+    Partial,
+    Received,
+    Cancelled,
+}
+
+/// One line of `purchase_orders.csv`. A purchase order is the set of lines
+/// sharing a `po_number`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PurchaseOrderLine {
+    pub po_number: String,
+    pub line_no: u32,
+    pub supplier_id: String,
+    pub part_sku: String,
+    pub qty_ordered: f64,
+    pub qty_received: f64,
+    pub unit_cost: f64,
+    pub order_date: String,
+    pub expected_date: String,
+    pub status: PoLineStatus,
+}
+
+impl PurchaseOrderLine {
+    /// Quantity still expected from the supplier.
+    pub fn open_qty(&self) -> f64 {
+        match self.status {
+            PoLineStatus::Open | PoLineStatus::Partial => (self.qty_ordered - self.qty_received).max(0.0),
+            PoLineStatus::Received | PoLineStatus::Cancelled => 0.0,
+        }
+    }
+}
+
+/// A part that cannot cover the requested build from available plus on-order stock.
+#[derive(Debug, serde::Serialize)]
+pub struct Shortage {
+    pub sku: String,
+    pub required_qty: f64,
+    pub available_qty: f64,
+    pub on_order_qty: f64,
+    pub shortage_qty: f64,
+}
+
+/// A line to order, typically taken from a shortage report.
+#[derive(Debug, serde::Deserialize)]
+pub struct ShortageLine {
+    pub sku: String,
+    pub qty: f64,
+    #[serde(default)]
+    pub unit_cost: Option<f64>,
+}
+
+pub fn load_suppliers(data_dir: &Path) -> Result<Vec<Supplier>> {
+    read_csv_if_exists::<Supplier>(&data_dir.join("suppliers.csv"))
+        .context("Reading suppliers.csv")
+}
+
+/// Insert a supplier, or replace the one with the same `supplier_id`.
+pub fn save_supplier(data_dir: &Path, supplier: Supplier) -> Result<Vec<Supplier>> {
+    if supplier.supplier_id.trim().is_empty() {
+        bail!("Supplier ID is required");
+    }
+    let mut suppliers = load_suppliers(data_dir)?;
+    match suppliers.iter_mut().find(|s| s.supplier_id == supplier.supplier_id) {
+        Some(existing) => *existing = supplier,
+        None => suppliers.push(supplier),
+    }
+    write_csv(&data_dir.join("suppliers.csv"), &suppliers)
+        .context("Writing suppliers.csv")?;
+    Ok(suppliers)
+}
+
+pub fn load_purchase_orders(data_dir: &Path) -> Result<Vec<PurchaseOrderLine>> {
+    read_csv_if_exists::<PurchaseOrderLine>(&data_dir.join("purchase_orders.csv"))
+        .context("Reading purchase_orders.csv")
+}
+
+pub fn write_purchase_orders(data_dir: &Path, lines: &[PurchaseOrderLine]) -> Result<()> {
+    write_csv(&data_dir.join("purchase_orders.csv"), lines)
+        .context("Writing purchase_orders.csv")
+}
+
+/// Open quantity per SKU across all purchase orders, optionally limited to
+/// lines expected on or before `by_date`.
+pub fn on_order_quantities(data_dir: &Path, by_date: Option<&str>) -> Result<HashMap<String, f64>> {
+    let by_date = by_date.map(parse_date).transpose()?;
+    let mut on_order = HashMap::new();
+    for line in load_purchase_orders(data_dir)? {
+        if let Some(limit) = by_date {
+            match parse_date(&line.expected_date) {
+                Ok(expected) if expected <= limit => {},
+                _ => continue,
+            }
+        }
+        let qty = line.open_qty();
+        if qty > 0.0 {
+            *on_order.entry(line.part_sku).or_insert(0.0) += qty;
+        }
+    }
+    Ok(on_order)
+}
+
+/// Fill `on_order_qty` on each inventory item from open purchase order lines.
+/// Display only, so an unreadable `purchase_orders.csv` leaves it at zero.
+pub fn apply_on_order(data_dir: &Path, items: &mut [InventoryItem]) {
+    let on_order = on_order_quantities(data_dir, None).unwrap_or_default();
+    for item in items {
+        item.on_order_qty = on_order.get(&item.sku).copied().unwrap_or(0.0);
+    }
+}
+
+/// Compare the leaf requirements for `quantity` units of an assembly against
+/// available and on-order stock, returning only the parts that fall short.
+pub fn shortage_report(data_dir: &Path, assembly_sku: &str, quantity: f64) -> Result<Vec<Shortage>> {
+    let data = load_data_dir(data_dir)?;
+    data.check_complete(assembly_sku)?;
+    let req_per_unit = explode_bom(assembly_sku, &data.bom_items)?;
+    let on_order = on_order_quantities(data_dir, None)?;
+    let available: HashMap<&str, f64> = data.stock.iter()
+        .map(|s| (s.sku.as_str(), (s.on_hand_qty - s.reserved_qty).max(0.0)))
+        .collect();
+
+    let mut shortages: Vec<Shortage> = req_per_unit.into_iter()
+        .filter_map(|(sku, per_unit)| {
+            let required_qty = per_unit * quantity;
+            let available_qty = available.get(sku.as_str()).copied().unwrap_or(0.0);
+            let on_order_qty = on_order.get(&sku).copied().unwrap_or(0.0);
+            let shortage_qty = required_qty - available_qty - on_order_qty;
+            (shortage_qty > 1e-9).then_some(Shortage {
+                sku,
+                required_qty,
+                available_qty,
+                on_order_qty,
+                shortage_qty,
+            })
+        })
+        .collect();
+    shortages.sort_by(|a, b| a.sku.cmp(&b.sku));
+    Ok(shortages)
+}
+
+fn next_po_number(lines: &[PurchaseOrderLine]) -> String {
+    let max = lines.iter()
+        .filter_map(|l| l.po_number.strip_prefix("PO-").and_then(|n| n.parse::<u32>().ok()))
+        .max()
+        .unwrap_or(0);
+    format!("PO-{:05}", max + 1)
+}
+
+/// Create a purchase order for one supplier with a line per shortage.
+pub fn create_purchase_order(
+    data_dir: &Path,
+    supplier_id: &str,
+    expected_date: &str,
+    shortages: &[ShortageLine],
+) -> Result<Vec<PurchaseOrderLine>> {
+    if !load_suppliers(data_dir)?.iter().any(|s| s.supplier_id == supplier_id) {
+        bail!("Unknown supplier: {}", supplier_id);
+    }
+    parse_date(expected_date)?;
+    if shortages.is_empty() {
+        bail!("A purchase order needs at least one line");
+    }
+
+    let mut lines = load_purchase_orders(data_dir)?;
+    let po_number = next_po_number(&lines);
+    let order_date = chrono::Local::now().format("%Y-%m-%d").to_string();
+
+    let mut created = Vec::new();
+    for (idx, shortage) in shortages.iter().enumerate() {
+        if shortage.qty <= 0.0 {
+            bail!("Order quantity for {} must be positive", shortage.sku);
+        }
+        created.push(PurchaseOrderLine {
+            po_number: po_number.clone(),
+            line_no: idx as u32 + 1,
+            supplier_id: supplier_id.to_string(),
+            part_sku: shortage.sku.clone(),
+            qty_ordered: shortage.qty,
+            qty_received: 0.0,
+            unit_cost: shortage.unit_cost.unwrap_or(0.0),
+            order_date: order_date.clone(),
+            expected_date: expected_date.to_string(),
+            status: PoLineStatus::Open,
+        });
+    }
+
+    lines.extend(created.iter().cloned());
+    write_purchase_orders(data_dir, &lines)?;
+    Ok(created)
+}
+
+/// Apply a receipt of `qty` units of `sku` to the open lines of a purchase
+/// order, oldest line first. With `close_lines`, every line touched is closed
+/// even if it was short-shipped. Only `lines` is changed; the caller writes it
+/// back once the stock movement has been recorded. Returns the lines that
+/// changed, each with the quantity applied to it.
+pub fn apply_receipt(
+    lines: &mut [PurchaseOrderLine],
+    po_number: &str,
+    sku: &str,
+    qty: f64,
+    close_lines: bool,
+) -> Result<Vec<(PurchaseOrderLine, f64)>> {
+    let mut open: Vec<&mut PurchaseOrderLine> = lines.iter_mut()
+        .filter(|l| l.po_number == po_number && l.part_sku == sku && l.open_qty() > 0.0)
+        .collect();
+    if open.is_empty() {
+        bail!("No open lines for {} on {}", sku, po_number);
+    }
+    open.sort_by_key(|l| l.line_no);
+
+    let mut remaining = qty;
+    let mut updated = Vec::new();
+    let last = open.len() - 1;
+    for (idx, line) in open.into_iter().enumerate() {
+        // Over-receipts land on the last line rather than being dropped
+        let applied = if idx == last { remaining } else { remaining.min(line.open_qty()) };
+        if applied <= 0.0 {
+            break;
+        }
+        line.qty_received += applied;
+        remaining -= applied;
+        line.status = if close_lines || line.qty_received + 1e-9 >= line.qty_ordered {
+            PoLineStatus::Received
+        } else {
+            PoLineStatus::Partial
+        };
+        updated.push((line.clone(), applied));
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    const PO_HEADER: &str = "po_number,line_no,supplier_id,part_sku,qty_ordered,qty_received,unit_cost,order_date,expected_date,status\n";
+
+    fn line(po_number: &str, qty_ordered: f64, qty_received: f64, status: PoLineStatus) -> PurchaseOrderLine {
+        PurchaseOrderLine {
+            po_number: po_number.to_string(),
+            line_no: 1,
+            supplier_id: "S1".to_string(),
+            part_sku: "P1".to_string(),
+            qty_ordered,
+            qty_received,
+            unit_cost: 1.0,
+            order_date: "2024-01-01".to_string(),
+            expected_date: "2024-02-01".to_string(),
+            status,
+        }
+    }
+
+    #[test]
+    fn open_qty_is_zero_for_closed_lines() {
+        assert_eq!(line("PO-1", 10.0, 4.0, PoLineStatus::Partial).open_qty(), 6.0);
+        assert_eq!(line("PO-1", 10.0, 12.0, PoLineStatus::Partial).open_qty(), 0.0);
+        assert_eq!(line("PO-1", 10.0, 4.0, PoLineStatus::Received).open_qty(), 0.0);
+        assert_eq!(line("PO-1", 10.0, 0.0, PoLineStatus::Cancelled).open_qty(), 0.0);
+    }
+
+    #[test]
+    fn next_po_number_follows_highest_numbered_order() {
+        assert_eq!(next_po_number(&[]), "PO-00001");
+        let lines = [
+            line("PO-00007", 1.0, 0.0, PoLineStatus::Open),
+            line("PO-00002", 1.0, 0.0, PoLineStatus::Open),
+            line("MANUAL", 1.0, 0.0, PoLineStatus::Open),
+        ];
+        assert_eq!(next_po_number(&lines), "PO-00008");
+    }
+
+    #[test]
+    fn on_order_quantities_respects_expected_date() {
+        let dir = TestDir::new();
+        dir.write("purchase_orders.csv", &format!("{PO_HEADER}\
+            PO-00001,1,S1,P1,10,4,1,2024-01-01,2024-02-01,partial\n\
+            PO-00001,2,S1,P1,5,0,1,2024-01-01,2024-03-01,open\n\
+            PO-00002,1,S1,P2,5,5,1,2024-01-01,2024-02-01,received\n"));
+
+        let all = on_order_quantities(dir.path(), None).unwrap();
+        assert_eq!(all.get("P1"), Some(&11.0));
+        assert_eq!(all.get("P2"), None);
+
+        let by_feb = on_order_quantities(dir.path(), Some("2024-02-15")).unwrap();
+        assert_eq!(by_feb.get("P1"), Some(&6.0));
+    }
+
+    #[test]
+    fn create_purchase_order_numbers_lines_and_keeps_existing_orders() {
+        let dir = TestDir::new();
+        dir.write("suppliers.csv", "supplier_id,name,contact,email,phone,lead_time_days\nS1,Acme,,,,7\n");
+        dir.write("purchase_orders.csv", &format!("{PO_HEADER}PO-00003,1,S1,P1,1,0,1,2024-01-01,2024-02-01,open\n"));
+
+        let shortages = [
+            ShortageLine { sku: "P1".into(), qty: 2.0, unit_cost: Some(3.5) },
+            ShortageLine { sku: "P2".into(), qty: 1.0, unit_cost: None },
+        ];
+        let created = create_purchase_order(dir.path(), "S1", "2024-05-01", &shortages).unwrap();
+        assert_eq!(created.len(), 2);
+        assert!(created.iter().all(|l| l.po_number == "PO-00004"));
+        assert_eq!(created[1].line_no, 2);
+        assert_eq!(load_purchase_orders(dir.path()).unwrap().len(), 3);
+
+        assert!(create_purchase_order(dir.path(), "S9", "2024-05-01", &shortages).is_err());
+        assert!(create_purchase_order(dir.path(), "S1", "05/01/2024", &shortages).is_err());
+    }
+
+    #[test]
+    fn unreadable_files_are_not_overwritten() {
+        let dir = TestDir::new();
+        let corrupt_po = format!("{PO_HEADER}PO-00001,one,S1,P1,1,0,1,2024-01-01,2024-02-01,open\n");
+        dir.write("suppliers.csv", "supplier_id,name,contact,email,phone,lead_time_days\nS1,Acme,,,,7\n");
+        dir.write("purchase_orders.csv", &corrupt_po);
+
+        let shortages = [ShortageLine { sku: "P1".into(), qty: 1.0, unit_cost: None }];
+        assert!(create_purchase_order(dir.path(), "S1", "2024-05-01", &shortages).is_err());
+        assert_eq!(dir.read("purchase_orders.csv"), corrupt_po);
+
+        dir.write("suppliers.csv", "supplier_id,name\nS1\n");
+        let supplier = Supplier {
+            supplier_id: "S2".into(),
+            name: "Other".into(),
+            contact: None,
+            email: None,
+            phone: None,
+            lead_time_days: None,
+        };
+        assert!(save_supplier(dir.path(), supplier).is_err());
+        assert_eq!(dir.read("suppliers.csv"), "supplier_id,name\nS1\n");
+    }
+}
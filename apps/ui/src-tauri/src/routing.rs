@@ -0,0 +1,728 @@
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::bom::{available_stock, explode_assemblies, explode_bom, max_buildable, KnownSkus};
+use crate::data::{append_csv, load_data_dir, load_data_dir_as_of, read_csv_if_exists, write_csv, BomItem};
+use crate::history::{filter_history, HistoryQuery, SkippedRow};
+
+/// One step of an assembly's routing in `routings.csv`. Setup is incurred
+/// once per build, run time once per unit.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RoutingOperation {
+    pub assembly_sku: String,
+    pub operation_seq: u32,
+    pub operation: String,
+    pub work_center: String,
+    pub setup_minutes: f64,
+    pub run_minutes: f64,
+}
+
+/// A work center in `work_centers.csv` with the hours it is staffed per day.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorkCenter {
+    pub work_center: String,
+    pub name: String,
+    pub hours_per_day: f64,
+}
+
+/// Actual minutes booked to one routing operation of a build in `build_labor.csv`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct BuildLabor {
+    pub build_id: String,
+    pub operation_seq: u32,
+    pub minutes: f64,
+    /// Sub-assembly whose routing has the operation; empty for the assembly built
+    #[serde(default)]
+    pub assembly_sku: Option<String>,
+}
+
+impl BuildLabor {
+    /// Assembly whose routing has the operation, for a build of `built_sku`.
+    pub fn routing_sku<'a>(&'a self, built_sku: &'a str) -> &'a str {
+        self.assembly_sku.as_deref().unwrap_or(built_sku)
+    }
+}
+
+/// Minutes spent on one routing operation, as captured with a build.
+#[derive(Debug, serde::Deserialize)]
+pub struct OperationLabor {
+    pub operation_seq: u32,
+    /// Sub-assembly whose routing has the operation; empty for the assembly built
+    #[serde(default)]
+    pub assembly_sku: Option<String>,
+    pub minutes: f64,
+}
+
+/// Standard against actual labor for one routing operation of a build.
+#[derive(Debug, serde::Serialize)]
+pub struct OperationVariance {
+    /// Assembly whose routing has the operation
+    pub assembly_sku: String,
+    pub operation_seq: u32,
+    /// Missing when the operation is no longer in the routing
+    pub operation: Option<String>,
+    pub work_center: Option<String>,
+    pub standard_minutes: Option<f64>,
+    /// Missing when no labor was booked to the operation
+    pub actual_minutes: Option<f64>,
+    pub variance_minutes: Option<f64>,
+}
+
+/// Standard against actual labor for one recorded build.
+#[derive(Debug, serde::Serialize)]
+pub struct LaborVariance {
+    pub id: String,
+    pub timestamp: String,
+    pub work_order: String,
+    pub assembly_sku: String,
+    pub quantity_built: f64,
+    pub operator: Option<String>,
+    /// Missing when the assembly has no routing
+    pub standard_minutes: Option<f64>,
+    /// Missing when no labor was recorded with the build
+    pub actual_minutes: Option<f64>,
+    /// Actual less standard; positive means the build took longer
+    pub variance_minutes: Option<f64>,
+    /// Standard as a percentage of actual
+    pub efficiency_pct: Option<f64>,
+    /// Empty unless labor was booked to the build's operations
+    pub operations: Vec<OperationVariance>,
+}
+
+/// Totals per assembly over builds that have both standard and actual labor.
+#[derive(Debug, serde::Serialize)]
+pub struct LaborSummary {
+    pub assembly_sku: String,
+    pub builds: usize,
+    pub quantity_built: f64,
+    pub standard_minutes: f64,
+    pub actual_minutes: f64,
+    pub efficiency_pct: Option<f64>,
+}
+
+/// Totals per routing operation over builds with labor booked to it.
+#[derive(Debug, serde::Serialize)]
+pub struct OperationSummary {
+    pub assembly_sku: String,
+    pub operation_seq: u32,
+    pub operation: String,
+    pub work_center: String,
+    pub builds: usize,
+    pub standard_minutes: f64,
+    pub actual_minutes: f64,
+    pub variance_minutes: f64,
+    pub efficiency_pct: Option<f64>,
+}
+
+/// Totals per work center over the operations in `OperationSummary`.
+#[derive(Debug, serde::Serialize)]
+pub struct WorkCenterSummary {
+    pub work_center: String,
+    pub builds: usize,
+    pub standard_minutes: f64,
+    pub actual_minutes: f64,
+    pub variance_minutes: f64,
+    pub efficiency_pct: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct LaborReport {
+    pub builds: Vec<LaborVariance>,
+    pub by_assembly: Vec<LaborSummary>,
+    pub by_operation: Vec<OperationSummary>,
+    pub by_work_center: Vec<WorkCenterSummary>,
+    pub skipped_rows: Vec<SkippedRow>,
+}
+
+/// Load on one work center for a build of the assembly.
+#[derive(Debug, serde::Serialize)]
+pub struct WorkCenterLoad {
+    pub work_center: String,
+    /// Missing when the work center is not in `work_centers.csv`
+    pub available_minutes: Option<f64>,
+    pub setup_minutes: f64,
+    pub run_minutes_per_unit: f64,
+    pub max_units: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct CapacityBuildability {
+    pub assembly_sku: String,
+    pub days: f64,
+    pub material_limited: f64,
+    /// Missing when no routing covers the assembly
+    pub capacity_limited: Option<f64>,
+    pub buildable: f64,
+    /// "material" or the bottleneck work center
+    pub limited_by: String,
+    pub work_centers: Vec<WorkCenterLoad>,
+}
+
+pub fn load_routings(data_dir: &Path) -> Result<Vec<RoutingOperation>> {
+    read_csv_if_exists::<RoutingOperation>(&data_dir.join("routings.csv"))
+        .context("Reading routings.csv")
+}
+
+fn write_routings(data_dir: &Path, routings: &mut [RoutingOperation]) -> Result<()> {
+    routings.sort_by(|a, b| a.assembly_sku.cmp(&b.assembly_sku).then(a.operation_seq.cmp(&b.operation_seq)));
+    write_csv(&data_dir.join("routings.csv"), routings)
+        .context("Writing routings.csv")
+}
+
+pub fn load_work_centers(data_dir: &Path) -> Result<Vec<WorkCenter>> {
+    read_csv_if_exists::<WorkCenter>(&data_dir.join("work_centers.csv"))
+        .context("Reading work_centers.csv")
+}
+
+pub fn load_build_labor(data_dir: &Path) -> Result<Vec<BuildLabor>> {
+    read_csv_if_exists::<BuildLabor>(&data_dir.join("build_labor.csv"))
+        .context("Reading build_labor.csv")
+}
+
+pub fn append_build_labor(data_dir: &Path, labor: &[BuildLabor]) -> Result<()> {
+    append_csv(&data_dir.join("build_labor.csv"), labor)
+        .context("Writing build_labor.csv")
+}
+
+pub fn save_work_center(data_dir: &Path, work_center: WorkCenter) -> Result<Vec<WorkCenter>> {
+    if work_center.work_center.trim().is_empty() {
+        bail!("Work center ID is required");
+    }
+    if !(0.0..=24.0).contains(&work_center.hours_per_day) {
+        bail!("Hours per day must be between 0 and 24");
+    }
+    let mut work_centers = load_work_centers(data_dir)?;
+    match work_centers.iter_mut().find(|w| w.work_center == work_center.work_center) {
+        Some(existing) => *existing = work_center,
+        None => work_centers.push(work_center),
+    }
+    write_csv(&data_dir.join("work_centers.csv"), &work_centers)
+        .context("Writing work_centers.csv")?;
+    Ok(work_centers)
+}
+
+/// Add or replace an operation, keyed by assembly and sequence number.
+pub fn save_routing_operation(data_dir: &Path, operation: RoutingOperation) -> Result<Vec<RoutingOperation>> {
+    if !KnownSkus::load(data_dir)?.assemblies.contains(&operation.assembly_sku) {
+        bail!("Unknown assembly: {}", operation.assembly_sku);
+    }
+    if operation.operation.trim().is_empty() {
+        bail!("Operation {} needs a description", operation.operation_seq);
+    }
+    if !load_work_centers(data_dir)?.iter().any(|w| w.work_center == operation.work_center) {
+        bail!("Unknown work center: {}", operation.work_center);
+    }
+    for (label, minutes) in [("Setup", operation.setup_minutes), ("Run", operation.run_minutes)] {
+        if minutes < 0.0 || !minutes.is_finite() {
+            bail!("{} minutes for operation {} must not be negative", label, operation.operation_seq);
+        }
+    }
+
+    let mut routings = load_routings(data_dir)?;
+    match routings.iter_mut()
+        .find(|r| r.assembly_sku == operation.assembly_sku && r.operation_seq == operation.operation_seq)
+    {
+        Some(existing) => *existing = operation,
+        None => routings.push(operation),
+    }
+    write_routings(data_dir, &mut routings)?;
+    Ok(routings)
+}
+
+pub fn remove_routing_operation(data_dir: &Path, assembly_sku: &str, operation_seq: u32) -> Result<Vec<RoutingOperation>> {
+    let mut routings = load_routings(data_dir)?;
+    let before = routings.len();
+    routings.retain(|r| !(r.assembly_sku == assembly_sku && r.operation_seq == operation_seq));
+    if routings.len() == before {
+        bail!("{} has no operation {}", assembly_sku, operation_seq);
+    }
+    write_routings(data_dir, &mut routings)?;
+    Ok(routings)
+}
+
+/// The operations run to build an assembly, including the routings of
+/// sub-assemblies made along with it, each with the units of its assembly
+/// made per unit built. Ordered by assembly and sequence.
+fn routing_operations<'a>(
+    assembly_sku: &str,
+    bom_items: &[BomItem],
+    routings: &'a [RoutingOperation],
+) -> Result<Vec<(&'a RoutingOperation, f64)>> {
+    let per_unit = explode_assemblies(assembly_sku, bom_items)?;
+    let mut operations: Vec<(&RoutingOperation, f64)> = routings.iter()
+        .filter_map(|op| per_unit.get(&op.assembly_sku).map(|units| (op, *units)))
+        .collect();
+    operations.sort_by(|(a, _), (b, _)| a.assembly_sku.cmp(&b.assembly_sku).then(a.operation_seq.cmp(&b.operation_seq)));
+    Ok(operations)
+}
+
+/// Setup and per-unit run minutes per work center for `operations`. Empty
+/// when no routing applies.
+fn work_center_minutes(operations: &[(&RoutingOperation, f64)]) -> BTreeMap<String, (f64, f64)> {
+    let mut minutes: BTreeMap<String, (f64, f64)> = BTreeMap::new();
+    for (op, per_unit) in operations {
+        let (setup, run) = minutes.entry(op.work_center.clone()).or_default();
+        *setup += op.setup_minutes;
+        *run += op.run_minutes * per_unit;
+    }
+    minutes
+}
+
+/// Standard minutes for one operation of a build of `quantity` units.
+fn operation_standard(op: &RoutingOperation, per_unit: f64, quantity: f64) -> f64 {
+    op.setup_minutes + op.run_minutes * per_unit * quantity
+}
+
+fn efficiency(standard: f64, actual: f64) -> Option<f64> {
+    (actual > 0.0).then(|| standard / actual * 100.0)
+}
+
+/// Check labor captured with a build of `assembly_sku` and turn it into
+/// `build_labor.csv` rows. Each operation must be in the routings the build
+/// runs and may be booked once. Returns the build's total labor, which is the
+/// sum of the operations unless only `labor_minutes` was given.
+pub fn check_build_labor(
+    data_dir: &Path,
+    build_id: &str,
+    assembly_sku: &str,
+    bom_items: &[BomItem],
+    labor_minutes: Option<f64>,
+    operations: &[OperationLabor],
+) -> Result<(Option<f64>, Vec<BuildLabor>)> {
+    if let Some(minutes) = labor_minutes.filter(|m| *m < 0.0 || !m.is_finite()) {
+        bail!("Labor of {} minutes", minutes);
+    }
+    if operations.is_empty() {
+        return Ok((labor_minutes, Vec::new()));
+    }
+
+    let routings = load_routings(data_dir)?;
+    let routed = routing_operations(assembly_sku, bom_items, &routings)?;
+    let mut rows: Vec<BuildLabor> = Vec::new();
+    for entry in operations {
+        let owner = entry.assembly_sku.as_deref().unwrap_or(assembly_sku);
+        if entry.minutes < 0.0 || !entry.minutes.is_finite() {
+            bail!("Labor of {} minutes on operation {} of {}", entry.minutes, entry.operation_seq, owner);
+        }
+        if !routed.iter().any(|(op, _)| op.assembly_sku == owner && op.operation_seq == entry.operation_seq) {
+            bail!("Operation {} of {} is not in the routing for {}", entry.operation_seq, owner, assembly_sku);
+        }
+        if rows.iter().any(|r| r.routing_sku(assembly_sku) == owner && r.operation_seq == entry.operation_seq) {
+            bail!("Operation {} of {} is booked more than once", entry.operation_seq, owner);
+        }
+        rows.push(BuildLabor {
+            build_id: build_id.to_string(),
+            operation_seq: entry.operation_seq,
+            minutes: entry.minutes,
+            assembly_sku: (owner != assembly_sku).then(|| owner.to_string()),
+        });
+    }
+
+    let booked: f64 = rows.iter().map(|r| r.minutes).sum();
+    if let Some(total) = labor_minutes.filter(|total| (total - booked).abs() > 1e-6) {
+        bail!("Labor of {} minutes does not match the {} minutes booked to operations", total, booked);
+    }
+    Ok((Some(booked), rows))
+}
+
+/// Standard against booked minutes for each operation of a build, plus any
+/// booked to operations no longer in the routing.
+fn operation_variances(
+    built_sku: &str,
+    quantity: f64,
+    operations: &[(&RoutingOperation, f64)],
+    booked: &[&BuildLabor],
+) -> Vec<OperationVariance> {
+    let booked_to = |sku: &str, seq: u32| booked.iter()
+        .filter(|b| b.routing_sku(built_sku) == sku && b.operation_seq == seq)
+        .map(|b| b.minutes)
+        .reduce(|a, b| a + b);
+
+    let mut variances: Vec<OperationVariance> = operations.iter()
+        .map(|(op, per_unit)| {
+            let standard = operation_standard(op, *per_unit, quantity);
+            let actual = booked_to(&op.assembly_sku, op.operation_seq);
+            OperationVariance {
+                assembly_sku: op.assembly_sku.clone(),
+                operation_seq: op.operation_seq,
+                operation: Some(op.operation.clone()),
+                work_center: Some(op.work_center.clone()),
+                standard_minutes: Some(standard),
+                actual_minutes: actual,
+                variance_minutes: actual.map(|a| a - standard),
+            }
+        })
+        .collect();
+    for entry in booked {
+        let sku = entry.routing_sku(built_sku);
+        if !variances.iter().any(|v| v.assembly_sku == sku && v.operation_seq == entry.operation_seq) {
+            variances.push(OperationVariance {
+                assembly_sku: sku.to_string(),
+                operation_seq: entry.operation_seq,
+                operation: None,
+                work_center: None,
+                standard_minutes: None,
+                actual_minutes: booked_to(sku, entry.operation_seq),
+                variance_minutes: None,
+            });
+        }
+    }
+    variances
+}
+
+/// Standard minutes to build `quantity` units, or `None` without a routing.
+fn standard_minutes(minutes: &BTreeMap<String, (f64, f64)>, quantity: f64) -> Option<f64> {
+    (!minutes.is_empty()).then(|| minutes.values().map(|(setup, run)| setup + run * quantity).sum())
+}
+
+/// Standard labor from current routings against the labor recorded with each
+/// build matching `query`, per build, assembly, operation and work center.
+/// Operation and work center totals cover the labor booked to operations.
+pub fn labor_report(data_dir: &Path, query: &HistoryQuery) -> Result<LaborReport> {
+    let data = load_data_dir(data_dir)?;
+    let routings = load_routings(data_dir)?;
+    let labor = load_build_labor(data_dir)?;
+    let mut labor_by_build: HashMap<&str, Vec<&BuildLabor>> = HashMap::new();
+    for row in &labor {
+        labor_by_build.entry(row.build_id.as_str()).or_default().push(row);
+    }
+    let mut by_assembly_operations: HashMap<String, Vec<(&RoutingOperation, f64)>> = HashMap::new();
+
+    let mut builds = Vec::new();
+    let mut summaries: BTreeMap<String, LaborSummary> = BTreeMap::new();
+    let mut operation_summaries: BTreeMap<(String, u32), OperationSummary> = BTreeMap::new();
+    let mut work_center_summaries: BTreeMap<String, WorkCenterSummary> = BTreeMap::new();
+    let history = filter_history(data_dir, query)?;
+    for (_, record) in history.records {
+        if !by_assembly_operations.contains_key(&record.assembly_sku) {
+            let operations = routing_operations(&record.assembly_sku, &data.bom_items, &routings)?;
+            by_assembly_operations.insert(record.assembly_sku.clone(), operations);
+        }
+        let operations_run = &by_assembly_operations[&record.assembly_sku];
+        let standard = standard_minutes(&work_center_minutes(operations_run), record.quantity_built);
+        let actual = record.labor_minutes;
+        if let (Some(standard), Some(actual)) = (standard, actual) {
+            let summary = summaries.entry(record.assembly_sku.clone()).or_insert(LaborSummary {
+                assembly_sku: record.assembly_sku.clone(),
+                builds: 0,
+                quantity_built: 0.0,
+                standard_minutes: 0.0,
+                actual_minutes: 0.0,
+                efficiency_pct: None,
+            });
+            summary.builds += 1;
+            summary.quantity_built += record.quantity_built;
+            summary.standard_minutes += standard;
+            summary.actual_minutes += actual;
+        }
+
+        let operations = match labor_by_build.get(record.id.as_str()) {
+            Some(booked) => operation_variances(&record.assembly_sku, record.quantity_built, operations_run, booked),
+            None => Vec::new(),
+        };
+        let mut centers_seen: Vec<&str> = Vec::new();
+        for variance in &operations {
+            let (Some(operation), Some(work_center), Some(standard), Some(actual)) =
+                (&variance.operation, &variance.work_center, variance.standard_minutes, variance.actual_minutes)
+            else {
+                continue;
+            };
+            let summary = operation_summaries
+                .entry((variance.assembly_sku.clone(), variance.operation_seq))
+                .or_insert(OperationSummary {
+                    assembly_sku: variance.assembly_sku.clone(),
+                    operation_seq: variance.operation_seq,
+                    operation: operation.clone(),
+                    work_center: work_center.clone(),
+                    builds: 0,
+                    standard_minutes: 0.0,
+                    actual_minutes: 0.0,
+                    variance_minutes: 0.0,
+                    efficiency_pct: None,
+                });
+            summary.builds += 1;
+            summary.standard_minutes += standard;
+            summary.actual_minutes += actual;
+
+            let center = work_center_summaries.entry(work_center.clone()).or_insert(WorkCenterSummary {
+                work_center: work_center.clone(),
+                builds: 0,
+                standard_minutes: 0.0,
+                actual_minutes: 0.0,
+                variance_minutes: 0.0,
+                efficiency_pct: None,
+            });
+            if !centers_seen.contains(&work_center.as_str()) {
+                centers_seen.push(work_center);
+                center.builds += 1;
+            }
+            center.standard_minutes += standard;
+            center.actual_minutes += actual;
+        }
+
+        builds.push(LaborVariance {
+            variance_minutes: standard.zip(actual).map(|(s, a)| a - s),
+            efficiency_pct: standard.zip(actual).and_then(|(s, a)| efficiency(s, a)),
+            standard_minutes: standard,
+            actual_minutes: actual,
+            id: record.id,
+            timestamp: record.timestamp,
+            work_order: record.work_order,
+            assembly_sku: record.assembly_sku,
+            quantity_built: record.quantity_built,
+            operator: record.operator,
+            operations,
+        });
+    }
+
+    let by_assembly = summaries.into_values()
+        .map(|s| LaborSummary {
+            efficiency_pct: efficiency(s.standard_minutes, s.actual_minutes),
+            ..s
+        })
+        .collect();
+    let by_operation = operation_summaries.into_values()
+        .map(|s| OperationSummary {
+            variance_minutes: s.actual_minutes - s.standard_minutes,
+            efficiency_pct: efficiency(s.standard_minutes, s.actual_minutes),
+            ..s
+        })
+        .collect();
+    let by_work_center = work_center_summaries.into_values()
+        .map(|s| WorkCenterSummary {
+            variance_minutes: s.actual_minutes - s.standard_minutes,
+            efficiency_pct: efficiency(s.standard_minutes, s.actual_minutes),
+            ..s
+        })
+        .collect();
+    Ok(LaborReport { builds, by_assembly, by_operation, by_work_center, skipped_rows: history.skipped })
+}
+
+/// Whole units buildable within `days` of work center time, alongside the
+/// units the available material allows.
+pub fn capacity_buildable(
+    data_dir: &Path,
+    assembly_sku: &str,
+    days: f64,
+    bom_date: chrono::NaiveDate,
+) -> Result<CapacityBuildability> {
+    if days <= 0.0 {
+        bail!("Days must be positive");
+    }
+    let data = load_data_dir_as_of(data_dir, bom_date)?;
+    data.check_complete(assembly_sku)?;
+    let material_limited = max_buildable(&explode_bom(assembly_sku, &data.bom_items)?, &available_stock(&data.stock));
+
+    let centers: HashMap<String, f64> = load_work_centers(data_dir)?.into_iter()
+        .map(|w| (w.work_center, w.hours_per_day * 60.0 * days))
+        .collect();
+    let routings = load_routings(data_dir)?;
+    let work_centers: Vec<WorkCenterLoad> = work_center_minutes(&routing_operations(assembly_sku, &data.bom_items, &routings)?)
+        .into_iter()
+        .map(|(work_center, (setup, run))| {
+            let available_minutes = centers.get(&work_center).copied();
+            let max_units = available_minutes.and_then(|available| {
+                if run > 0.0 {
+                    Some(((available - setup) / run).floor().max(0.0))
+                } else if setup > available {
+                    Some(0.0)
+                } else {
+                    None
+                }
+            });
+            WorkCenterLoad {
+                work_center,
+                available_minutes,
+                setup_minutes: setup,
+                run_minutes_per_unit: run,
+                max_units,
+            }
+        })
+        .collect();
+
+    let bottleneck = work_centers.iter()
+        .filter_map(|w| w.max_units.map(|units| (units, &w.work_center)))
+        .min_by(|a, b| a.0.total_cmp(&b.0));
+    let capacity_limited = bottleneck.map(|(units, _)| units);
+    let (buildable, limited_by) = match bottleneck {
+        Some((units, work_center)) if units < material_limited => (units, work_center.clone()),
+        _ => (material_limited, "material".to_string()),
+    };
+
+    Ok(CapacityBuildability {
+        assembly_sku: assembly_sku.to_string(),
+        days,
+        material_limited,
+        capacity_limited,
+        buildable,
+        limited_by,
+        work_centers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    const HISTORY_HEADER: &str = "id,timestamp,work_order,sales_order,customer,assembly_sku,quantity_built,operator,notes,bom_revision,substitutions,labor_minutes\n";
+
+    /// `A` is made from 2 x `SUB` and 1 x `P1`, `SUB` from 1 x `P1`. A's
+    /// operations run on ASSY and WIRE, SUB's on WIRE.
+    fn fixture() -> TestDir {
+        let dir = TestDir::new();
+        dir.write("assemblies.csv", "assembly_sku,name,uom\nA,Assembly,ea\nSUB,Sub-assembly,ea\n");
+        dir.write("parts.csv", "part_sku,name,uom\nP1,Part one,ea\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\n\
+            A,SUB,2,0,1,false\nA,P1,1,0,1,false\nSUB,P1,1,0,1,false\n");
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,100,0\n");
+        dir.write("work_centers.csv", "work_center,name,hours_per_day\nASSY,Assembly,8\nWIRE,Wiring,1\n");
+        dir.write("routings.csv", "assembly_sku,operation_seq,operation,work_center,setup_minutes,run_minutes\n\
+            A,10,Mount,ASSY,30,10\nA,20,Test,WIRE,0,5\nSUB,10,Wire,WIRE,10,2\n");
+        dir
+    }
+
+    fn date() -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()
+    }
+
+    fn labor(operation_seq: u32, assembly_sku: Option<&str>, minutes: f64) -> OperationLabor {
+        OperationLabor { operation_seq, assembly_sku: assembly_sku.map(str::to_string), minutes }
+    }
+
+    fn bom_items(dir: &TestDir) -> Vec<BomItem> {
+        load_data_dir(dir.path()).unwrap().bom_items
+    }
+
+    #[test]
+    fn capacity_reports_the_bottleneck_work_center() {
+        let dir = fixture();
+        let capacity = capacity_buildable(dir.path(), "A", 1.0, date()).unwrap();
+        // Each A takes 1 + 2 x 1 of P1
+        assert_eq!(capacity.material_limited, 33.0);
+
+        let wire = capacity.work_centers.iter().find(|w| w.work_center == "WIRE").unwrap();
+        // Setup 0 + 10, run 5 + 2 x 2 per unit of A, in 60 minutes
+        assert_eq!((wire.setup_minutes, wire.run_minutes_per_unit), (10.0, 9.0));
+        assert_eq!(wire.max_units, Some(5.0));
+        let assy = capacity.work_centers.iter().find(|w| w.work_center == "ASSY").unwrap();
+        assert_eq!(assy.max_units, Some(45.0));
+
+        assert_eq!(capacity.capacity_limited, Some(5.0));
+        assert_eq!(capacity.buildable, 5.0);
+        assert_eq!(capacity.limited_by, "WIRE");
+    }
+
+    #[test]
+    fn capacity_falls_back_to_material_and_ignores_unknown_work_centers() {
+        let dir = fixture();
+        let capacity = capacity_buildable(dir.path(), "A", 10.0, date()).unwrap();
+        assert_eq!(capacity.capacity_limited, Some(65.0));
+        assert_eq!((capacity.buildable, capacity.limited_by.as_str()), (33.0, "material"));
+
+        dir.write("routings.csv", "assembly_sku,operation_seq,operation,work_center,setup_minutes,run_minutes\nA,10,Paint,PAINT,0,100\n");
+        let capacity = capacity_buildable(dir.path(), "A", 1.0, date()).unwrap();
+        assert_eq!(capacity.work_centers[0].available_minutes, None);
+        assert_eq!(capacity.capacity_limited, None);
+        assert_eq!(capacity.limited_by, "material");
+
+        assert!(capacity_buildable(dir.path(), "A", 0.0, date()).is_err());
+    }
+
+    #[test]
+    fn build_labor_must_match_the_routing() {
+        let dir = fixture();
+        let items = bom_items(&dir);
+        let operations = [labor(10, None, 60.0), labor(10, Some("SUB"), 35.0)];
+        let (total, rows) = check_build_labor(dir.path(), "B1", "A", &items, None, &operations).unwrap();
+        assert_eq!(total, Some(95.0));
+        assert_eq!(rows[1].assembly_sku.as_deref(), Some("SUB"));
+        assert_eq!(rows[0].assembly_sku, None);
+
+        // Whole-build labor alone is passed through
+        assert_eq!(check_build_labor(dir.path(), "B1", "A", &items, Some(50.0), &[]).unwrap().0, Some(50.0));
+
+        for bad in [
+            vec![labor(30, None, 5.0)],
+            vec![labor(10, None, 5.0), labor(10, None, 5.0)],
+            vec![labor(10, None, -5.0)],
+            vec![labor(10, Some("OTHER"), 5.0)],
+        ] {
+            assert!(check_build_labor(dir.path(), "B1", "A", &items, None, &bad).is_err());
+        }
+        assert!(check_build_labor(dir.path(), "B1", "A", &items, Some(90.0), &operations).is_err());
+        assert!(check_build_labor(dir.path(), "B1", "A", &items, Some(-1.0), &[]).is_err());
+    }
+
+    #[test]
+    fn labor_report_breaks_variance_down_by_operation_and_work_center() {
+        let dir = fixture();
+        dir.write("panel_history.csv", &format!("{HISTORY_HEADER}\
+            B1,2024-03-01T10:00:00+00:00,WO-1,SO-1,Beacon,A,2,sam,,,,110\n\
+            B2,2024-03-02T10:00:00+00:00,WO-2,SO-1,Beacon,A,1,sam,,,,40\n"));
+        dir.write("build_labor.csv", "build_id,operation_seq,minutes,assembly_sku\n\
+            B1,10,60,\nB1,20,10,\nB1,10,35,SUB\nB1,99,5,\n");
+        let report = labor_report(dir.path(), &HistoryQuery { ascending: true, ..HistoryQuery::default() }).unwrap();
+
+        let b1 = report.builds.iter().find(|b| b.id == "B1").unwrap();
+        assert_eq!(b1.standard_minutes, Some(78.0));
+        assert_eq!(b1.variance_minutes, Some(32.0));
+        let ops: Vec<(&str, u32, Option<f64>, Option<f64>)> = b1.operations.iter()
+            .map(|o| (o.assembly_sku.as_str(), o.operation_seq, o.standard_minutes, o.actual_minutes))
+            .collect();
+        assert_eq!(ops, vec![
+            ("A", 10, Some(50.0), Some(60.0)),
+            ("A", 20, Some(10.0), Some(10.0)),
+            ("SUB", 10, Some(18.0), Some(35.0)),
+            // Booked to an operation no longer in the routing
+            ("A", 99, None, Some(5.0)),
+        ]);
+        let b2 = report.builds.iter().find(|b| b.id == "B2").unwrap();
+        assert!(b2.operations.is_empty());
+        assert_eq!(b2.standard_minutes, Some(59.0));
+
+        assert_eq!(report.by_assembly.len(), 1);
+        assert_eq!(report.by_assembly[0].builds, 2);
+        assert_eq!(report.by_assembly[0].standard_minutes, 137.0);
+
+        assert_eq!(report.by_operation.len(), 3);
+        let sub = report.by_operation.iter().find(|o| o.assembly_sku == "SUB").unwrap();
+        assert_eq!((sub.work_center.as_str(), sub.variance_minutes), ("WIRE", 17.0));
+
+        let centers: Vec<(&str, usize, f64, f64, f64)> = report.by_work_center.iter()
+            .map(|w| (w.work_center.as_str(), w.builds, w.standard_minutes, w.actual_minutes, w.variance_minutes))
+            .collect();
+        assert_eq!(centers, vec![("ASSY", 1, 50.0, 60.0, 10.0), ("WIRE", 1, 28.0, 45.0, 17.0)]);
+        assert_eq!(report.by_work_center[1].efficiency_pct.map(|e| e.round()), Some(62.0));
+    }
+
+    #[test]
+    fn corrupt_routing_files_are_errors_and_not_overwritten() {
+        let dir = fixture();
+        let corrupt = "assembly_sku,operation_seq,operation,work_center,setup_minutes,run_minutes\nA,ten,Mount,ASSY,30,10\n";
+        dir.write("routings.csv", corrupt);
+        assert!(load_routings(dir.path()).is_err());
+        let operation = RoutingOperation {
+            assembly_sku: "A".to_string(),
+            operation_seq: 30,
+            operation: "Pack".to_string(),
+            work_center: "ASSY".to_string(),
+            setup_minutes: 0.0,
+            run_minutes: 1.0,
+        };
+        assert!(save_routing_operation(dir.path(), operation).is_err());
+        assert!(remove_routing_operation(dir.path(), "A", 10).is_err());
+        assert!(capacity_buildable(dir.path(), "A", 1.0, date()).is_err());
+        assert_eq!(dir.read("routings.csv"), corrupt);
+
+        let corrupt = "work_center,name,hours_per_day\nASSY,Assembly,all day\n";
+        dir.write("work_centers.csv", corrupt);
+        let center = WorkCenter { work_center: "PACK".to_string(), name: "Packing".to_string(), hours_per_day: 4.0 };
+        assert!(save_work_center(dir.path(), center).is_err());
+        assert_eq!(dir.read("work_centers.csv"), corrupt);
+
+        dir.write("build_labor.csv", "build_id,operation_seq,minutes\nB1,10,lots\n");
+        assert!(labor_report(dir.path(), &HistoryQuery::default()).is_err());
+    }
+}
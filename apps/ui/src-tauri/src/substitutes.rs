@@ -0,0 +1,531 @@
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use crate::bom::{available_stock, effective_qty, explode_bom, index_bom_by_parent, max_buildable, KnownSkus};
+use crate::data::{load_data_dir_as_of, read_csv_if_exists, write_csv, BomItem, StockRow};
+use crate::inventory::{load_reservations, ReservationStatus};
+
+/// One alternate for a primary part in `substitutes.csv`. The alternates of a
+/// primary sharing a `group` form a substitute group, drawn from in `priority`
+/// order (1 first) once the primary itself is exhausted. A BOM line draws on
+/// the group named in its `substitute_group`, or on the alternates without a
+/// group when it names none.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Substitute {
+    pub primary_sku: String,
+    pub substitute_sku: String,
+    pub priority: u32,
+    /// Units of the substitute that replace one unit of the primary
+    pub conversion_ratio: f64,
+    /// Limits the substitute to builds of this top-level assembly
+    #[serde(default)]
+    pub assembly_sku: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+impl Substitute {
+    fn same_entry(&self, other: &Substitute) -> bool {
+        self.primary_sku == other.primary_sku
+            && self.substitute_sku == other.substitute_sku
+            && self.assembly_sku == other.assembly_sku
+            && self.group == other.group
+    }
+
+    fn applies_to(&self, assembly_sku: &str) -> bool {
+        self.assembly_sku.iter().all(|a| a == assembly_sku)
+    }
+}
+
+/// Quantity of a substitute consumed in place of its primary.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Substitution {
+    pub primary_sku: String,
+    pub substitute_sku: String,
+    /// Primary quantity covered
+    pub primary_qty: f64,
+    /// Substitute quantity consumed
+    pub substitute_qty: f64,
+}
+
+/// How a set of leaf requirements is met from stock.
+#[derive(Debug, Default)]
+pub struct Allocation {
+    /// Quantity drawn per SKU, primaries and substitutes alike
+    pub consumed: HashMap<String, f64>,
+    pub substitutions: Vec<Substitution>,
+    /// Primary quantity neither stock nor substitutes could cover
+    pub short: HashMap<String, f64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct SubstituteBuildability {
+    pub assembly_sku: String,
+    /// Whole units buildable from primary parts alone
+    pub primary_only: f64,
+    /// Whole units buildable when alternates cover exhausted primaries
+    pub with_substitutes: f64,
+    /// Substitutes consumed to build `with_substitutes` units
+    pub substitutions: Vec<Substitution>,
+}
+
+/// Requirement per component and the substitute group its BOM line names.
+type Requirements = BTreeMap<(String, Option<String>), f64>;
+
+/// Alternates by primary and group, each sorted by priority.
+type Groups<'a> = HashMap<(&'a str, Option<&'a str>), Vec<&'a Substitute>>;
+
+pub fn load_substitutes(data_dir: &Path) -> Result<Vec<Substitute>> {
+    read_csv_if_exists::<Substitute>(&data_dir.join("substitutes.csv"))
+        .context("Reading substitutes.csv")
+}
+
+/// Fail if a BOM line names a substitute group with no alternates for its component.
+pub fn check_line_group(data_dir: &Path, item: &BomItem) -> Result<()> {
+    let Some(group) = item.substitute_group.as_deref() else {
+        return Ok(());
+    };
+    if !load_substitutes(data_dir)?.iter()
+        .any(|s| s.primary_sku == item.component_sku && s.group.as_deref() == Some(group))
+    {
+        bail!("Substitute group {} has no alternates for {}", group, item.component_sku);
+    }
+    Ok(())
+}
+
+fn write_substitutes(data_dir: &Path, substitutes: &[Substitute]) -> Result<()> {
+    write_csv(&data_dir.join("substitutes.csv"), substitutes)
+        .context("Writing substitutes.csv")
+}
+
+/// Add or replace a substitute, keyed by primary, substitute and assembly scope.
+pub fn save_substitute(data_dir: &Path, substitute: Substitute) -> Result<Vec<Substitute>> {
+    let substitute = Substitute {
+        group: substitute.group.map(|g| g.trim().to_string()).filter(|g| !g.is_empty()),
+        ..substitute
+    };
+    let known = KnownSkus::load(data_dir)?;
+    let (primary, alternate) = (substitute.primary_sku.as_str(), substitute.substitute_sku.as_str());
+    for sku in [primary, alternate] {
+        if !known.parts.contains(sku) {
+            bail!("Unknown part: {}", sku);
+        }
+    }
+    if primary == alternate {
+        bail!("{} cannot substitute for itself", primary);
+    }
+    if known.inactive.contains(alternate) {
+        bail!("{} is inactive", alternate);
+    }
+    if let Some(assembly) = &substitute.assembly_sku {
+        if !known.assemblies.contains(assembly) {
+            bail!("Unknown assembly: {}", assembly);
+        }
+    }
+    if substitute.priority == 0 {
+        bail!("Priority starts at 1");
+    }
+    if substitute.conversion_ratio <= 0.0 || !substitute.conversion_ratio.is_finite() {
+        bail!("Conversion ratio for {} must be positive", alternate);
+    }
+
+    let mut substitutes = load_substitutes(data_dir)?;
+    match substitutes.iter_mut().find(|s| s.same_entry(&substitute)) {
+        Some(existing) => *existing = substitute,
+        None => substitutes.push(substitute),
+    }
+    write_substitutes(data_dir, &substitutes)?;
+    Ok(substitutes)
+}
+
+pub fn remove_substitute(
+    data_dir: &Path,
+    primary_sku: &str,
+    substitute_sku: &str,
+    assembly_sku: Option<&str>,
+    group: Option<&str>,
+) -> Result<Vec<Substitute>> {
+    let group = group.filter(|g| !g.trim().is_empty());
+    let mut substitutes = load_substitutes(data_dir)?;
+    let before = substitutes.len();
+    substitutes.retain(|s| {
+        !(s.primary_sku == primary_sku
+            && s.substitute_sku == substitute_sku
+            && s.assembly_sku.as_deref() == assembly_sku
+            && s.group.as_deref() == group)
+    });
+    if substitutes.len() == before {
+        bail!("{} is not a substitute for {}", substitute_sku, primary_sku);
+    }
+    write_substitutes(data_dir, &substitutes)?;
+    Ok(substitutes)
+}
+
+/// Substitute groups usable when building `assembly_sku`, each sorted by
+/// priority. Substitutes made inactive since they were saved are skipped.
+fn groups_for<'a>(assembly_sku: &str, substitutes: &'a [Substitute], known: &KnownSkus) -> Groups<'a> {
+    let mut groups: Groups = HashMap::new();
+    for sub in substitutes {
+        if sub.applies_to(assembly_sku) && !known.inactive.contains(&sub.substitute_sku) {
+            groups.entry((sub.primary_sku.as_str(), sub.group.as_deref())).or_default().push(sub);
+        }
+    }
+    for group in groups.values_mut() {
+        group.sort_by_key(|s| s.priority);
+    }
+    groups
+}
+
+/// Requirements for one unit of `assembly_sku` keyed by component and the
+/// substitute group of the line using it: its direct lines at `qty_per`, as a
+/// build deducts them (through phantoms, which are never stocked), or exploded
+/// to leaf parts as buildability counts them.
+fn requirements(assembly_sku: &str, bom_items: &[BomItem], explode: bool) -> Result<Requirements> {
+    fn walk<'a>(
+        sku: &'a str,
+        group: Option<&'a str>,
+        multiplier: f64,
+        explode: bool,
+        by_parent: &HashMap<&'a str, Vec<&'a BomItem>>,
+        visiting: &mut HashSet<&'a str>,
+        out: &mut Requirements,
+    ) -> Result<()> {
+        let Some(children) = by_parent.get(sku) else {
+            *out.entry((sku.to_string(), group.map(str::to_string))).or_insert(0.0) += multiplier;
+            return Ok(());
+        };
+        if !visiting.insert(sku) {
+            bail!("Circular BOM detected at {}", sku);
+        }
+        for item in children {
+            let component = item.component_sku.as_str();
+            let group = item.substitute_group.as_deref();
+            if explode {
+                walk(component, group, multiplier * effective_qty(item), explode, by_parent, visiting, out)?;
+            } else if item.is_phantom && by_parent.contains_key(component) {
+                walk(component, group, multiplier * item.qty_per, explode, by_parent, visiting, out)?;
+            } else {
+                *out.entry((component.to_string(), group.map(str::to_string))).or_insert(0.0) += multiplier * item.qty_per;
+            }
+        }
+        visiting.remove(sku);
+        Ok(())
+    }
+
+    let mut out = Requirements::new();
+    let by_parent = index_bom_by_parent(bom_items);
+    if !explode && !by_parent.contains_key(assembly_sku) {
+        return Ok(out);
+    }
+    walk(assembly_sku, None, 1.0, explode, &by_parent, &mut HashSet::new(), &mut out)?;
+    Ok(out)
+}
+
+/// Meet `required` from `available`: every primary draws on its own stock
+/// first, then shortfalls are covered from the line's substitute group by
+/// priority.
+fn allocate(required: &Requirements, available: &HashMap<String, f64>, groups: &Groups) -> Allocation {
+    let mut pool = available.clone();
+    let mut allocation = Allocation::default();
+    let mut shortfalls = Vec::new();
+
+    for ((sku, group), &qty) in required {
+        let stock = pool.entry(sku.clone()).or_insert(0.0);
+        let take = qty.min(*stock).max(0.0);
+        *stock -= take;
+        *allocation.consumed.entry(sku.clone()).or_insert(0.0) += take;
+        if qty - take > 1e-9 {
+            shortfalls.push((sku.as_str(), group.as_deref(), qty - take));
+        }
+    }
+
+    for (primary, group, mut shortfall) in shortfalls {
+        for sub in groups.get(&(primary, group)).into_iter().flatten() {
+            let stock = pool.entry(sub.substitute_sku.clone()).or_insert(0.0);
+            let take = (shortfall * sub.conversion_ratio).min(*stock);
+            if take <= 1e-9 {
+                continue;
+            }
+            *stock -= take;
+            *allocation.consumed.entry(sub.substitute_sku.clone()).or_insert(0.0) += take;
+            let covered = take / sub.conversion_ratio;
+            shortfall -= covered;
+            allocation.substitutions.push(Substitution {
+                primary_sku: primary.to_string(),
+                substitute_sku: sub.substitute_sku.clone(),
+                primary_qty: covered,
+                substitute_qty: take,
+            });
+            if shortfall <= 1e-9 {
+                break;
+            }
+        }
+        if shortfall > 1e-9 {
+            *allocation.short.entry(primary.to_string()).or_insert(0.0) += shortfall;
+        }
+    }
+    allocation
+}
+
+fn scaled(req_per_unit: &Requirements, quantity: f64) -> Requirements {
+    req_per_unit.iter().map(|(key, per_unit)| (key.clone(), per_unit * quantity)).collect()
+}
+
+/// Parts to deduct for building `quantity` units: the assembly's direct lines,
+/// drawn from available stock plus what is reserved for `work_order`, and
+/// switching to substitutes where a primary runs out. Any quantity still
+/// short is left on the primary, which the deduction clamps at zero.
+pub fn plan_build(
+    data_dir: &Path,
+    assembly_sku: &str,
+    work_order: &str,
+    quantity: f64,
+    bom_items: &[BomItem],
+    stock: &[StockRow],
+) -> Result<Allocation> {
+    let required = scaled(&requirements(assembly_sku, bom_items, false)?, quantity);
+
+    // Stock reserved for this work order is there to be built with
+    let mut own: HashMap<&str, f64> = HashMap::new();
+    let reservations = load_reservations(data_dir)?;
+    for r in reservations.iter().filter(|r| r.work_order == work_order && r.status == ReservationStatus::Active) {
+        *own.entry(r.sku.as_str()).or_insert(0.0) += r.open_qty();
+    }
+    let available = stock.iter()
+        .map(|s| {
+            let held_for_others = (s.reserved_qty - own.get(s.sku.as_str()).copied().unwrap_or(0.0)).max(0.0);
+            (s.sku.clone(), (s.on_hand_qty - held_for_others).max(0.0))
+        })
+        .collect();
+
+    let known = KnownSkus::load(data_dir)?;
+    let substitutes = load_substitutes(data_dir)?;
+    let mut allocation = allocate(&required, &available, &groups_for(assembly_sku, &substitutes, &known));
+    for (sku, short) in &allocation.short {
+        *allocation.consumed.entry(sku.clone()).or_insert(0.0) += short;
+    }
+    Ok(allocation)
+}
+
+/// Whole units of an assembly buildable from available stock, with and
+/// without drawing on substitutes.
+pub fn buildable_with_substitutes(
+    data_dir: &Path,
+    assembly_sku: &str,
+    bom_date: chrono::NaiveDate,
+) -> Result<SubstituteBuildability> {
+    let data = load_data_dir_as_of(data_dir, bom_date)?;
+    data.check_complete(assembly_sku)?;
+    let req_per_unit = requirements(assembly_sku, &data.bom_items, true)?;
+    let available = available_stock(&data.stock);
+    let known = KnownSkus::load(data_dir)?;
+    let substitutes = load_substitutes(data_dir)?;
+    let groups = groups_for(assembly_sku, &substitutes, &known);
+
+    let primary_only = max_buildable(&explode_bom(assembly_sku, &data.bom_items)?, &available);
+
+    // Upper bound: each primary as if every alternate for it were free for it alone
+    let mut per_sku: BTreeMap<&str, f64> = BTreeMap::new();
+    for ((sku, _), req) in &req_per_unit {
+        *per_sku.entry(sku.as_str()).or_insert(0.0) += req;
+    }
+    let upper = per_sku.iter()
+        .filter(|(_, req)| **req > 0.0)
+        .map(|(sku, req)| {
+            let alternates: f64 = groups.iter()
+                .filter(|((primary, _), _)| primary == sku)
+                .flat_map(|(_, group)| group)
+                .map(|s| available.get(&s.substitute_sku).copied().unwrap_or(0.0) / s.conversion_ratio)
+                .sum();
+            ((available.get(*sku).copied().unwrap_or(0.0) + alternates) / req).floor()
+        })
+        .reduce(f64::min)
+        .unwrap_or(0.0);
+
+    // Requirements only grow with quantity, so search for the largest feasible
+    let feasible = |qty: f64| allocate(&scaled(&req_per_unit, qty), &available, &groups).short.is_empty();
+    let (mut low, mut high) = (primary_only, upper.max(primary_only));
+    while high > low {
+        let mid = ((low + high) / 2.0).ceil();
+        if feasible(mid) {
+            low = mid;
+        } else {
+            high = mid - 1.0;
+        }
+    }
+
+    let substitutions = allocate(&scaled(&req_per_unit, low), &available, &groups).substitutions;
+    Ok(SubstituteBuildability {
+        assembly_sku: assembly_sku.to_string(),
+        primary_only,
+        with_substitutes: low,
+        substitutions,
+    })
+}
+
+/// Summary of substitutes consumed for the build record, e.g. `B2 for B x4`.
+pub fn describe(substitutions: &[Substitution]) -> Option<String> {
+    if substitutions.is_empty() {
+        return None;
+    }
+    Some(substitutions.iter()
+        .map(|s| format!("{} for {} x{}", s.substitute_sku, s.primary_sku, s.substitute_qty))
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    fn fixture() -> TestDir {
+        let dir = TestDir::new();
+        dir.write("assemblies.csv", "assembly_sku,name,uom,active\nA,Assembly,ea,true\nB,Other,ea,true\n");
+        dir.write("parts.csv", "part_sku,name,uom\nP1,Part one,ea\nS1,First alternate,ea\nS2,Second alternate,ea\nG1,Grouped alternate,ea\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\nA,P1,2,0,1,false\n");
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,4,0\nS1,2,0\nS2,10,0\nG1,10,0\n");
+        dir.write("substitutes.csv", "primary_sku,substitute_sku,priority,conversion_ratio,assembly_sku,notes,group\n\
+            P1,S2,2,2,,,\nP1,S1,1,1,,,\nP1,G1,1,1,,,OUTDOOR\n");
+        dir
+    }
+
+    fn required(entries: &[(&str, Option<&str>, f64)]) -> Requirements {
+        entries.iter()
+            .map(|(sku, group, qty)| ((sku.to_string(), group.map(str::to_string)), *qty))
+            .collect()
+    }
+
+    fn stock_map(entries: &[(&str, f64)]) -> HashMap<String, f64> {
+        entries.iter().map(|(sku, qty)| (sku.to_string(), *qty)).collect()
+    }
+
+    #[test]
+    fn allocation_uses_primary_then_substitutes_by_priority() {
+        let dir = fixture();
+        let substitutes = load_substitutes(dir.path()).unwrap();
+        let known = KnownSkus::load(dir.path()).unwrap();
+        let groups = groups_for("A", &substitutes, &known);
+        let available = stock_map(&[("P1", 4.0), ("S1", 2.0), ("S2", 10.0)]);
+
+        let allocation = allocate(&required(&[("P1", None, 10.0)]), &available, &groups);
+        assert_eq!(allocation.consumed["P1"], 4.0);
+        assert_eq!(allocation.consumed["S1"], 2.0);
+        // Four primaries left at two S2 each
+        assert_eq!(allocation.consumed["S2"], 8.0);
+        let order: Vec<&str> = allocation.substitutions.iter().map(|s| s.substitute_sku.as_str()).collect();
+        assert_eq!(order, vec!["S1", "S2"]);
+        assert!(allocation.short.is_empty());
+
+        let allocation = allocate(&required(&[("P1", None, 20.0)]), &available, &groups);
+        assert!((allocation.short["P1"] - 9.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn lines_draw_only_on_the_group_they_name() {
+        let dir = fixture();
+        let substitutes = load_substitutes(dir.path()).unwrap();
+        let known = KnownSkus::load(dir.path()).unwrap();
+        let groups = groups_for("A", &substitutes, &known);
+        let available = stock_map(&[("P1", 0.0), ("S1", 5.0), ("G1", 5.0)]);
+
+        let allocation = allocate(&required(&[("P1", Some("OUTDOOR"), 3.0)]), &available, &groups);
+        assert_eq!(allocation.consumed.get("G1"), Some(&3.0));
+        assert!(!allocation.consumed.contains_key("S1"));
+
+        let allocation = allocate(&required(&[("P1", Some("MISSING"), 3.0)]), &available, &groups);
+        assert!((allocation.short["P1"] - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn substitutes_scoped_to_another_assembly_are_ignored() {
+        let dir = fixture();
+        dir.write("substitutes.csv", "primary_sku,substitute_sku,priority,conversion_ratio,assembly_sku,notes,group\nP1,S1,1,1,B,,\n");
+        let substitutes = load_substitutes(dir.path()).unwrap();
+        let known = KnownSkus::load(dir.path()).unwrap();
+        assert!(groups_for("A", &substitutes, &known).is_empty());
+        assert_eq!(groups_for("B", &substitutes, &known).len(), 1);
+    }
+
+    #[test]
+    fn plan_build_respects_reservations_of_other_work_orders() {
+        let dir = fixture();
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,4,3\nS1,5,0\n");
+        dir.write("reservations.csv", "reservation_id,work_order,sales_order,assembly_sku,sku,qty_reserved,qty_consumed,qty_released,created_at,status\n\
+            R1,WO-1,SO-1,A,P1,2,0,0,2024-01-01T00:00:00Z,active\nR2,WO-2,SO-2,A,P1,1,0,0,2024-01-01T00:00:00Z,active\n");
+        let data = load_data_dir_as_of(dir.path(), chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap();
+
+        // WO-1 may use its own 2 plus the 1 unreserved; WO-2's unit stays put
+        let plan = plan_build(dir.path(), "A", "WO-1", 2.0, &data.bom_items, &data.stock).unwrap();
+        assert_eq!(plan.consumed["P1"], 3.0);
+        assert_eq!(plan.consumed["S1"], 1.0);
+
+        let plan = plan_build(dir.path(), "A", "WO-9", 2.0, &data.bom_items, &data.stock).unwrap();
+        assert_eq!(plan.consumed["P1"], 1.0);
+        assert_eq!(plan.consumed["S1"], 3.0);
+    }
+
+    #[test]
+    fn plan_build_blows_through_phantom_lines() {
+        let dir = fixture();
+        dir.write("assemblies.csv", "assembly_sku,name,uom,active\nA,Assembly,ea,true\nK,Kit,ea,true\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\n\
+            A,K,2,0,1,true\nK,P1,1,0,1,false\n");
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,4,0\nK,5,0\n");
+        let data = load_data_dir_as_of(dir.path(), chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()).unwrap();
+
+        let plan = plan_build(dir.path(), "A", "WO-1", 2.0, &data.bom_items, &data.stock).unwrap();
+        assert_eq!(plan.consumed["P1"], 4.0);
+        assert!(!plan.consumed.contains_key("K"));
+    }
+
+    #[test]
+    fn buildability_counts_substitutes() {
+        let dir = fixture();
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let result = buildable_with_substitutes(dir.path(), "A", date).unwrap();
+        assert_eq!(result.primary_only, 2.0);
+        // 4 P1 + 2 S1 + 10 S2 / 2 covers 11 primaries
+        assert_eq!(result.with_substitutes, 5.0);
+    }
+
+    #[test]
+    fn bom_lines_must_name_an_existing_group() {
+        let dir = fixture();
+        let line = |group: &str| BomItem {
+            parent_assembly_sku: "B".to_string(),
+            component_sku: "P1".to_string(),
+            qty_per: 1.0,
+            scrap_rate: 0.0,
+            yield_pct: 1.0,
+            is_phantom: false,
+            revision: None,
+            uom: None,
+            substitute_group: Some(group.to_string()),
+        };
+        assert!(crate::bom::add_bom_item(dir.path(), line("INDOOR")).is_err());
+        crate::bom::add_bom_item(dir.path(), line("OUTDOOR")).unwrap();
+    }
+
+    #[test]
+    fn corrupt_substitutes_file_is_not_overwritten() {
+        let dir = fixture();
+        let corrupt = "primary_sku,substitute_sku,priority,conversion_ratio\nP1,S1,first,1\n";
+        dir.write("substitutes.csv", corrupt);
+        let substitute = Substitute {
+            primary_sku: "P1".to_string(),
+            substitute_sku: "S2".to_string(),
+            priority: 1,
+            conversion_ratio: 1.0,
+            assembly_sku: None,
+            notes: None,
+            group: None,
+        };
+        assert!(save_substitute(dir.path(), substitute).is_err());
+        assert!(remove_substitute(dir.path(), "P1", "S1", None, None).is_err());
+        assert_eq!(dir.read("substitutes.csv"), corrupt);
+    }
+}
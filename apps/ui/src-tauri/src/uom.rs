@@ -0,0 +1,279 @@
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::data::{load_assemblies, load_bom_items, load_parts, read_csv_if_exists, write_csv, Assembly, BomItem, Part};
+
+/// A conversion factor in `uom_conversions.csv`: one `from_uom` is `factor`
+/// `to_uom`, and the reverse is implied. With a `sku` it applies to that part
+/// only and wins over a global factor for the same units.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct UomConversion {
+    pub from_uom: String,
+    pub to_uom: String,
+    pub factor: f64,
+    #[serde(default)]
+    pub sku: Option<String>,
+}
+
+impl UomConversion {
+    /// Same units (in either direction) and same scope.
+    fn same_entry(&self, other: &UomConversion) -> bool {
+        self.sku == other.sku && (
+            (same_unit(&self.from_uom, &other.from_uom) && same_unit(&self.to_uom, &other.to_uom))
+                || (same_unit(&self.from_uom, &other.to_uom) && same_unit(&self.to_uom, &other.from_uom))
+        )
+    }
+}
+
+fn same_unit(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+/// Conversion factors plus the stock unit of every part and assembly, which
+/// is the unit stock, explosion and deductions are expressed in.
+pub struct UomTable {
+    conversions: Vec<UomConversion>,
+    stock_units: HashMap<String, String>,
+}
+
+impl UomTable {
+    pub fn new(conversions: Vec<UomConversion>, assemblies: &[Assembly], parts: &[Part]) -> Self {
+        let stock_units = assemblies.iter().map(|a| (a.assembly_sku.clone(), a.uom.clone()))
+            .chain(parts.iter().map(|p| (p.part_sku.clone(), p.uom.clone())))
+            .collect();
+        UomTable { conversions, stock_units }
+    }
+
+    pub fn load(data_dir: &Path) -> Result<Self> {
+        Ok(UomTable::new(load_uom_conversions(data_dir)?, &load_assemblies(data_dir)?, &load_parts(data_dir)?))
+    }
+
+    /// Multiplier turning a quantity of `sku` in `from` into `to`: part-specific
+    /// conversions first, then global ones.
+    pub fn factor(&self, sku: &str, from: &str, to: &str) -> Result<f64> {
+        if same_unit(from, to) {
+            return Ok(1.0);
+        }
+        for scope in [Some(sku), None] {
+            for c in self.conversions.iter().filter(|c| c.sku.as_deref() == scope) {
+                if same_unit(&c.from_uom, from) && same_unit(&c.to_uom, to) {
+                    return Ok(c.factor);
+                }
+                if same_unit(&c.from_uom, to) && same_unit(&c.to_uom, from) {
+                    return Ok(1.0 / c.factor);
+                }
+            }
+        }
+        bail!("Cannot convert {} from {} to {}: no conversion in uom_conversions.csv", sku, from, to)
+    }
+
+    /// Convert a quantity of `sku` in `from` into its stock unit.
+    pub fn to_stock(&self, sku: &str, qty: f64, from: &str) -> Result<f64> {
+        let stock_unit = self.stock_units.get(sku)
+            .ok_or_else(|| anyhow!("Unknown SKU {}, cannot convert from {}", sku, from))?;
+        Ok(qty * self.factor(sku, from, stock_unit)?)
+    }
+
+    /// BOM lines with `qty_per` restated in each component's stock unit. Lines
+    /// without a unit of their own are already in it.
+    pub fn to_stock_units(&self, bom_items: Vec<BomItem>) -> Result<Vec<BomItem>> {
+        bom_items.into_iter()
+            .map(|item| {
+                let Some(uom) = item.uom.as_deref().filter(|u| !u.trim().is_empty()) else {
+                    return Ok(item);
+                };
+                let qty_per = self.to_stock(&item.component_sku, item.qty_per, uom)
+                    .with_context(|| format!("BOM line {} on {}", item.component_sku, item.parent_assembly_sku))?;
+                let uom = self.stock_units.get(&item.component_sku).cloned();
+                Ok(BomItem { qty_per, uom, ..item })
+            })
+            .collect()
+    }
+
+    /// Like `to_stock_units`, but lines whose unit cannot be converted are
+    /// set aside with the reason instead of failing the whole BOM.
+    pub fn usable_lines(&self, bom_items: Vec<BomItem>) -> (Vec<BomItem>, Vec<(BomItem, String)>) {
+        let mut skipped = Vec::new();
+        let usable = bom_items.into_iter()
+            .filter_map(|item| match self.to_stock_units(vec![item.clone()]) {
+                Ok(mut converted) => converted.pop(),
+                Err(e) => {
+                    skipped.push((item, format!("{:#}", e)));
+                    None
+                },
+            })
+            .collect();
+        (usable, skipped)
+    }
+}
+
+pub fn load_uom_conversions(data_dir: &Path) -> Result<Vec<UomConversion>> {
+    read_csv_if_exists::<UomConversion>(&data_dir.join("uom_conversions.csv"))
+        .context("Reading uom_conversions.csv")
+}
+
+/// Fail if any BOM line's unit cannot be converted with `table`.
+pub fn check_bom_units(data_dir: &Path, table: &UomTable) -> Result<()> {
+    table.to_stock_units(load_bom_items(data_dir)?).map(|_| ())
+}
+
+/// Add or replace a conversion, keyed by its pair of units and SKU scope.
+pub fn save_uom_conversion(data_dir: &Path, conversion: UomConversion) -> Result<Vec<UomConversion>> {
+    let conversion = UomConversion {
+        from_uom: conversion.from_uom.trim().to_string(),
+        to_uom: conversion.to_uom.trim().to_string(),
+        sku: conversion.sku.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+        ..conversion
+    };
+    if conversion.from_uom.is_empty() || conversion.to_uom.is_empty() {
+        bail!("Both units are required");
+    }
+    if same_unit(&conversion.from_uom, &conversion.to_uom) {
+        bail!("A unit does not need converting to itself");
+    }
+    if conversion.factor <= 0.0 || !conversion.factor.is_finite() {
+        bail!("Conversion factor must be positive");
+    }
+    let (assemblies, parts) = (load_assemblies(data_dir)?, load_parts(data_dir)?);
+    if let Some(sku) = &conversion.sku {
+        if !assemblies.iter().any(|a| &a.assembly_sku == sku) && !parts.iter().any(|p| &p.part_sku == sku) {
+            bail!("Unknown SKU: {}", sku);
+        }
+    }
+
+    let mut conversions = load_uom_conversions(data_dir)?;
+    match conversions.iter_mut().find(|c| c.same_entry(&conversion)) {
+        Some(existing) => *existing = conversion,
+        None => conversions.push(conversion),
+    }
+    write_csv(&data_dir.join("uom_conversions.csv"), &conversions)
+        .context("Writing uom_conversions.csv")?;
+    Ok(conversions)
+}
+
+/// Remove a conversion, refusing while a BOM line still depends on it.
+pub fn remove_uom_conversion(
+    data_dir: &Path,
+    from_uom: &str,
+    to_uom: &str,
+    sku: Option<&str>,
+) -> Result<Vec<UomConversion>> {
+    let target = UomConversion {
+        from_uom: from_uom.to_string(),
+        to_uom: to_uom.to_string(),
+        factor: 1.0,
+        sku: sku.map(str::to_string),
+    };
+    let mut conversions = load_uom_conversions(data_dir)?;
+    let before = conversions.len();
+    conversions.retain(|c| !c.same_entry(&target));
+    if conversions.len() == before {
+        bail!("No conversion from {} to {}", from_uom, to_uom);
+    }
+    let table = UomTable::new(conversions.clone(), &load_assemblies(data_dir)?, &load_parts(data_dir)?);
+    check_bom_units(data_dir, &table).context("The conversion is still needed")?;
+
+    write_csv(&data_dir.join("uom_conversions.csv"), &conversions)
+        .context("Writing uom_conversions.csv")?;
+    Ok(conversions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{load_data_dir, TestDir};
+
+    fn fixture() -> TestDir {
+        let dir = TestDir::new();
+        dir.write("assemblies.csv", "assembly_sku,name,uom,active\nA,Assembly,ea,true\nB,Other,ea,true\n");
+        dir.write("parts.csv", "part_sku,name,uom\nCABLE,Gray cable,ft\nWIRE,Hookup wire,ft\nP1,Part one,ea\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom,revision,uom\n\
+            A,CABLE,24,0,1,false,,in\nA,P1,1,0,1,false,,\nB,P1,2,0,1,false,,\n");
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nCABLE,100,0\nP1,10,0\n");
+        dir.write("uom_conversions.csv", "from_uom,to_uom,factor,sku\nft,in,12,\nspool,ft,500,CABLE\n");
+        dir
+    }
+
+    fn conversion(from: &str, to: &str, factor: f64, sku: Option<&str>) -> UomConversion {
+        UomConversion {
+            from_uom: from.to_string(),
+            to_uom: to.to_string(),
+            factor,
+            sku: sku.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn factors_work_both_ways_and_prefer_part_specific_ones() {
+        let dir = fixture();
+        let table = UomTable::load(dir.path()).unwrap();
+        assert_eq!(table.factor("CABLE", "ft", "in").unwrap(), 12.0);
+        assert!((table.factor("CABLE", "IN", "ft").unwrap() - 1.0 / 12.0).abs() < 1e-12);
+        assert_eq!(table.factor("CABLE", "spool", "ft").unwrap(), 500.0);
+        assert!(table.factor("WIRE", "spool", "ft").is_err());
+        assert_eq!(table.to_stock("CABLE", 2.0, "spool").unwrap(), 1000.0);
+        assert!(table.to_stock("NOPE", 1.0, "ft").is_err());
+
+        let table = UomTable::new(
+            vec![conversion("ft", "in", 12.0, None), conversion("ft", "in", 10.0, Some("WIRE"))],
+            &[],
+            &[],
+        );
+        assert_eq!(table.factor("WIRE", "ft", "in").unwrap(), 10.0);
+        assert_eq!(table.factor("CABLE", "ft", "in").unwrap(), 12.0);
+    }
+
+    #[test]
+    fn bom_lines_are_restated_in_stock_units() {
+        let dir = fixture();
+        let data = load_data_dir(dir.path()).unwrap();
+        let cable = data.bom_items.iter().find(|b| b.component_sku == "CABLE").unwrap();
+        assert_eq!(cable.qty_per, 2.0);
+        assert_eq!(cable.uom.as_deref(), Some("ft"));
+        assert!(data.warnings.is_empty());
+    }
+
+    #[test]
+    fn unconvertible_line_is_skipped_and_blocks_only_its_assembly() {
+        let dir = fixture();
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom,revision,uom\n\
+            A,CABLE,1,0,1,false,,m\nA,P1,1,0,1,false,,\nB,P1,2,0,1,false,,\n");
+        let data = load_data_dir(dir.path()).unwrap();
+        assert_eq!(data.bom_items.len(), 2);
+        assert_eq!(data.warnings.len(), 1);
+        assert!(data.warnings[0].contains("CABLE on A"));
+        assert!(data.check_complete("A").is_err());
+        data.check_complete("B").unwrap();
+
+        let table = UomTable::load(dir.path()).unwrap();
+        assert!(table.to_stock_units(load_bom_items(dir.path()).unwrap()).is_err());
+    }
+
+    #[test]
+    fn conversions_in_use_cannot_be_removed() {
+        let dir = fixture();
+        assert!(save_uom_conversion(dir.path(), conversion("in", "in", 1.0, None)).is_err());
+        assert!(save_uom_conversion(dir.path(), conversion("m", "ft", 0.0, None)).is_err());
+        assert!(save_uom_conversion(dir.path(), conversion("m", "ft", 3.28, Some("NOPE"))).is_err());
+
+        // Saving the reverse pair replaces the existing entry
+        let conversions = save_uom_conversion(dir.path(), conversion("in", "ft", 1.0 / 12.0, None)).unwrap();
+        assert_eq!(conversions.len(), 2);
+
+        let err = remove_uom_conversion(dir.path(), "ft", "in", None).unwrap_err();
+        assert!(err.to_string().contains("still needed"));
+        remove_uom_conversion(dir.path(), "spool", "ft", Some("CABLE")).unwrap();
+        assert_eq!(load_uom_conversions(dir.path()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn corrupt_conversions_file_is_not_overwritten() {
+        let dir = fixture();
+        let corrupt = "from_uom,to_uom,factor,sku\nft,in,twelve,\n";
+        dir.write("uom_conversions.csv", corrupt);
+        assert!(load_data_dir(dir.path()).is_err());
+        assert!(save_uom_conversion(dir.path(), conversion("m", "ft", 3.28, None)).is_err());
+        assert_eq!(dir.read("uom_conversions.csv"), corrupt);
+    }
+}
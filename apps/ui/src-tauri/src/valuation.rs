@@ -0,0 +1,327 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::costs::{part_cost_index, CostMethod};
+use crate::data::load_data_dir;
+use crate::inventory::{load_ledger, LedgerEntry, LedgerKind};
+use crate::purchasing::{load_purchase_orders, PurchaseOrderLine};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValuationMethod {
+    /// Stock on hand is the latest receipts, at their purchase prices
+    #[default]
+    Fifo,
+    /// Average price of all receipts up to the valuation date
+    WeightedAverage,
+    /// Standard cost from `part_costs.csv`
+    Standard,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ValuationLine {
+    pub sku: String,
+    pub name: String,
+    pub category: Option<String>,
+    pub supplier: Option<String>,
+    pub on_hand_qty: f64,
+    pub unit_cost: Option<f64>,
+    /// Missing when no price or standard cost is known for the SKU
+    pub value: Option<f64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ValuationTotal {
+    pub key: String,
+    pub sku_count: usize,
+    pub on_hand_qty: f64,
+    pub value: f64,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct InventoryValuation {
+    pub as_of: String,
+    pub method: ValuationMethod,
+    pub total_value: f64,
+    pub lines: Vec<ValuationLine>,
+    pub by_category: Vec<ValuationTotal>,
+    pub by_supplier: Vec<ValuationTotal>,
+    /// Stocked SKUs left out of the totals for lack of a cost
+    pub unvalued_skus: Vec<String>,
+}
+
+/// A received quantity and the price paid, when its PO line is known.
+struct Receipt {
+    timestamp: String,
+    qty: f64,
+    price: Option<f64>,
+}
+
+fn entry_date(entry: &LedgerEntry) -> Result<NaiveDate> {
+    chrono::DateTime::parse_from_rfc3339(&entry.timestamp)
+        .map(|ts| ts.date_naive())
+        .with_context(|| format!("Invalid timestamp '{}' on ledger entry {}", entry.timestamp, entry.id))
+}
+
+/// Value of `qty` on hand from its receipts, oldest first. FIFO leaves the
+/// latest receipts in stock; anything older than the ledger goes at `fallback`.
+fn fifo_value(qty: f64, receipts: &[Receipt], fallback: Option<f64>) -> Option<f64> {
+    let mut remaining = qty;
+    let mut value = 0.0;
+    for receipt in receipts.iter().rev() {
+        if remaining <= 1e-9 {
+            break;
+        }
+        let take = receipt.qty.min(remaining);
+        value += take * receipt.price.or(fallback)?;
+        remaining -= take;
+    }
+    if remaining > 1e-9 {
+        value += remaining * fallback?;
+    }
+    Some(value)
+}
+
+/// Price of the PO line a receipt was booked against. Receipts from before
+/// ledger entries recorded their line only get a price when the PO has a
+/// single line for the SKU.
+fn receipt_price(
+    entry: &LedgerEntry,
+    orders: &[PurchaseOrderLine],
+    prices: &HashMap<(&str, u32), &PurchaseOrderLine>,
+) -> Option<f64> {
+    let po_number = entry.reference.as_deref()?;
+    match entry.po_line {
+        Some(line_no) => prices.get(&(po_number, line_no))
+            .filter(|l| l.part_sku == entry.sku)
+            .map(|l| l.unit_cost),
+        None => {
+            let mut lines = orders.iter().filter(|l| l.po_number == po_number && l.part_sku == entry.sku);
+            match (lines.next(), lines.next()) {
+                (Some(line), None) => Some(line.unit_cost),
+                _ => None,
+            }
+        },
+    }
+}
+
+fn totals<F>(lines: &[ValuationLine], key: F) -> Vec<ValuationTotal>
+where
+    F: Fn(&ValuationLine) -> String,
+{
+    let mut by_key: BTreeMap<String, ValuationTotal> = BTreeMap::new();
+    for line in lines {
+        let key = key(line);
+        let total = by_key.entry(key.clone()).or_insert(ValuationTotal {
+            key,
+            sku_count: 0,
+            on_hand_qty: 0.0,
+            value: 0.0,
+        });
+        total.sku_count += 1;
+        total.on_hand_qty += line.on_hand_qty;
+        total.value += line.value.unwrap_or(0.0);
+    }
+    by_key.into_values().collect()
+}
+
+/// Value stock on hand at the end of `as_of`. Quantities for past dates are
+/// today's on-hand with later ledger movements reversed; purchase prices come
+/// from the PO lines that receipts were booked against.
+pub fn inventory_valuation(data_dir: &Path, method: ValuationMethod, as_of: NaiveDate) -> Result<InventoryValuation> {
+    let data = load_data_dir(data_dir)?;
+    let inventory = data.inventory.unwrap_or_default();
+    let categories: HashMap<&str, &str> = data.parts.iter()
+        .filter_map(|p| p.category.as_deref().map(|c| (p.part_sku.as_str(), c)))
+        .collect();
+    let costs = part_cost_index(data_dir)?;
+
+    let orders = load_purchase_orders(data_dir)?;
+    let prices: HashMap<(&str, u32), &PurchaseOrderLine> = orders.iter()
+        .map(|l| ((l.po_number.as_str(), l.line_no), l))
+        .collect();
+    // Latest PO supplier for SKUs without one in the inventory file
+    let mut po_suppliers: HashMap<&str, (&str, &str)> = HashMap::new();
+    for line in &orders {
+        let latest = po_suppliers.entry(line.part_sku.as_str()).or_insert((line.order_date.as_str(), line.supplier_id.as_str()));
+        if line.order_date.as_str() >= latest.0 {
+            *latest = (line.order_date.as_str(), line.supplier_id.as_str());
+        }
+    }
+
+    let mut on_hand: BTreeMap<String, f64> = inventory.iter().map(|i| (i.sku.clone(), i.on_hand_qty)).collect();
+    let mut receipts: HashMap<String, Vec<Receipt>> = HashMap::new();
+    for entry in load_ledger(data_dir)? {
+        if entry_date(&entry)? > as_of {
+            *on_hand.entry(entry.sku.clone()).or_insert(0.0) -= entry.qty_change;
+        } else if entry.kind == LedgerKind::Receipt && entry.qty_change > 0.0 {
+            let price = receipt_price(&entry, &orders, &prices);
+            receipts.entry(entry.sku).or_default().push(Receipt {
+                timestamp: entry.timestamp,
+                qty: entry.qty_change,
+                price,
+            });
+        }
+    }
+    for list in receipts.values_mut() {
+        list.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    }
+
+    let mut lines = Vec::new();
+    for (sku, qty) in on_hand {
+        if qty <= 1e-9 {
+            continue;
+        }
+        let item = inventory.iter().find(|i| i.sku == sku);
+        let standard = costs.get(&sku).and_then(|c| c.unit_cost(CostMethod::Standard).0);
+        let sku_receipts = receipts.get(&sku).map(Vec::as_slice).unwrap_or_default();
+        let value = match method {
+            ValuationMethod::Standard => standard.map(|c| c * qty),
+            ValuationMethod::WeightedAverage => {
+                let priced: Vec<(f64, f64)> = sku_receipts.iter()
+                    .filter_map(|r| r.price.map(|p| (r.qty, p)))
+                    .collect();
+                let received: f64 = priced.iter().map(|(q, _)| q).sum();
+                let average = (received > 0.0)
+                    .then(|| priced.iter().map(|(q, p)| q * p).sum::<f64>() / received)
+                    .or(standard);
+                average.map(|c| c * qty)
+            },
+            ValuationMethod::Fifo => fifo_value(qty, sku_receipts, standard),
+        };
+        lines.push(ValuationLine {
+            name: item.map(|i| i.name.clone()).unwrap_or_default(),
+            category: categories.get(sku.as_str()).map(|c| c.to_string()),
+            supplier: item.and_then(|i| i.supplier.clone())
+                .or_else(|| po_suppliers.get(sku.as_str()).map(|(_, s)| s.to_string())),
+            on_hand_qty: qty,
+            unit_cost: value.map(|v| v / qty),
+            value,
+            sku,
+        });
+    }
+
+    Ok(InventoryValuation {
+        as_of: as_of.format("%Y-%m-%d").to_string(),
+        method,
+        total_value: lines.iter().filter_map(|l| l.value).sum(),
+        by_category: totals(&lines, |l| l.category.clone().unwrap_or_else(|| "Uncategorized".to_string())),
+        by_supplier: totals(&lines, |l| l.supplier.clone().unwrap_or_else(|| "No supplier".to_string())),
+        unvalued_skus: lines.iter().filter(|l| l.value.is_none()).map(|l| l.sku.clone()).collect(),
+        lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TestDir;
+
+    const LEDGER_HEADER: &str = "id,timestamp,sku,qty_change,kind,reference,po_line,reason,lot,operator,notes\n";
+
+    /// P1 received at 1, then twice on one PO at 2 and 4, and 5 used in a
+    /// build; P2 only has a standard cost and P3 no cost at all.
+    fn fixture() -> TestDir {
+        let dir = TestDir::new();
+        dir.write("assemblies.csv", "assembly_sku,name,uom\nA,Assembly,ea\n");
+        dir.write("parts.csv", "part_sku,name,uom,category\nP1,Part one,ea,Fasteners\nP2,Part two,ea,Fasteners\nP3,Part three,ea,\n");
+        dir.write("bom_items.csv", "parent_assembly_sku,component_sku,qty_per,scrap_rate,yield_pct,is_phantom\nA,P1,1,0,1,false\n");
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,15,0\nP2,4,0\nP3,2,0\n");
+        dir.write("part_costs.csv", "sku,standard_cost\nP1,3\nP2,5\n");
+        dir.write("purchase_orders.csv", "po_number,line_no,supplier_id,part_sku,qty_ordered,qty_received,unit_cost,order_date,expected_date,status\n\
+            PO-1,1,S1,P1,10,10,1,2024-01-01,2024-01-10,received\n\
+            PO-2,1,S2,P1,5,5,2,2024-02-01,2024-02-10,received\n\
+            PO-2,2,S2,P1,5,5,4,2024-02-01,2024-02-10,received\n");
+        dir.write("inventory_ledger.csv", &format!("{LEDGER_HEADER}\
+            1,2024-01-10T09:00:00+00:00,P1,10,receipt,PO-1,1,,,sam,\n\
+            2,2024-02-10T09:00:00+00:00,P1,5,receipt,PO-2,1,,,sam,\n\
+            3,2024-02-10T09:00:01+00:00,P1,5,receipt,PO-2,2,,,sam,\n\
+            4,2024-03-01T09:00:00+00:00,P1,-5,build,WO-1,,,,sam,\n"));
+        dir
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn line<'a>(valuation: &'a InventoryValuation, sku: &str) -> &'a ValuationLine {
+        valuation.lines.iter().find(|l| l.sku == sku).unwrap()
+    }
+
+    #[test]
+    fn fifo_values_stock_as_the_latest_receipts() {
+        let dir = fixture();
+        let valuation = inventory_valuation(dir.path(), ValuationMethod::Fifo, date(2024, 6, 1)).unwrap();
+        // 15 on hand: 5 at 4, 5 at 2 and 5 at 1, each at its own PO line price
+        assert_eq!(line(&valuation, "P1").value, Some(35.0));
+        // No receipts, so the standard cost
+        assert_eq!(line(&valuation, "P2").value, Some(20.0));
+        assert_eq!(line(&valuation, "P3").value, None);
+        assert_eq!(valuation.total_value, 55.0);
+        assert_eq!(valuation.unvalued_skus, vec!["P3".to_string()]);
+
+        let fasteners = valuation.by_category.iter().find(|t| t.key == "Fasteners").unwrap();
+        assert_eq!((fasteners.sku_count, fasteners.value), (2, 55.0));
+        // P1's supplier comes from its latest PO
+        assert_eq!(line(&valuation, "P1").supplier.as_deref(), Some("S2"));
+    }
+
+    #[test]
+    fn fifo_falls_back_to_standard_for_stock_older_than_the_ledger() {
+        let dir = fixture();
+        dir.write("stock.csv", "sku,on_hand_qty,reserved_qty\nP1,25,0\n");
+        let valuation = inventory_valuation(dir.path(), ValuationMethod::Fifo, date(2024, 6, 1)).unwrap();
+        // All 20 received (5 at 4, 5 at 2, 10 at 1) plus 5 at the standard 3
+        assert_eq!(line(&valuation, "P1").value, Some(20.0 + 10.0 + 10.0 + 15.0));
+    }
+
+    #[test]
+    fn weighted_average_uses_all_receipts_to_date() {
+        let dir = fixture();
+        let valuation = inventory_valuation(dir.path(), ValuationMethod::WeightedAverage, date(2024, 6, 1)).unwrap();
+        // (10 x 1 + 5 x 2 + 5 x 4) / 20 = 2
+        let p1 = line(&valuation, "P1");
+        assert_eq!(p1.unit_cost, Some(2.0));
+        assert_eq!(p1.value, Some(30.0));
+
+        let standard = inventory_valuation(dir.path(), ValuationMethod::Standard, date(2024, 6, 1)).unwrap();
+        assert_eq!(line(&standard, "P1").value, Some(45.0));
+    }
+
+    #[test]
+    fn past_dates_reverse_later_movements() {
+        let dir = fixture();
+        let fifo = inventory_valuation(dir.path(), ValuationMethod::Fifo, date(2024, 2, 1)).unwrap();
+        // 15 now, less the 10 received and plus the 5 built since
+        let p1 = line(&fifo, "P1");
+        assert_eq!(p1.on_hand_qty, 10.0);
+        assert_eq!(p1.value, Some(10.0));
+
+        let average = inventory_valuation(dir.path(), ValuationMethod::WeightedAverage, date(2024, 2, 1)).unwrap();
+        assert_eq!(line(&average, "P1").unit_cost, Some(1.0));
+    }
+
+    #[test]
+    fn receipts_without_a_po_line_are_priced_only_when_unambiguous() {
+        let dir = fixture();
+        dir.write("inventory_ledger.csv", &format!("{LEDGER_HEADER}\
+            1,2024-01-10T09:00:00+00:00,P1,10,receipt,PO-1,,,,sam,\n\
+            2,2024-02-10T09:00:00+00:00,P1,5,receipt,PO-2,,,,sam,\n"));
+        let valuation = inventory_valuation(dir.path(), ValuationMethod::Fifo, date(2024, 6, 1)).unwrap();
+        // PO-2 has two P1 lines at different prices, so its 5 go at the
+        // standard 3; PO-1 has one, so its 10 go at 1
+        assert_eq!(line(&valuation, "P1").value, Some(15.0 + 10.0));
+    }
+
+    #[test]
+    fn bad_ledger_stops_the_valuation() {
+        let dir = fixture();
+        dir.write("inventory_ledger.csv", &format!("{LEDGER_HEADER}1,yesterday,P1,10,receipt,PO-1,1,,,sam,\n"));
+        assert!(inventory_valuation(dir.path(), ValuationMethod::Fifo, date(2024, 6, 1)).is_err());
+
+        dir.write("inventory_ledger.csv", "id,timestamp,sku,qty_change,kind\n1,2024-01-10T09:00:00+00:00,P1,ten,receipt\n");
+        assert!(inventory_valuation(dir.path(), ValuationMethod::Fifo, date(2024, 6, 1)).is_err());
+    }
+}
@@ -3,13 +3,17 @@ import type { Buildability, RequirementsPerUnit, SKU, StockRow } from "./types";
 export function computeMaxBuildable(
   reqPerUnit: RequirementsPerUnit,
   stock: StockRow[],
-  respectReservations = true
+  respectReservations = true,
+  incoming: Record<SKU, number> = {} // e.g. on-order qty due by a date
 ): Buildability {
   const avail = new Map<SKU, number>();
   for (const s of stock) {
     const a = s.on_hand_qty - (respectReservations ? s.reserved_qty : 0);
     avail.set(s.sku, Math.max(0, a));
   }
+  for (const [sku, qty] of Object.entries(incoming)) {
+    avail.set(sku, (avail.get(sku) ?? 0) + qty);
+  }
 
   let maxBuildable = Number.POSITIVE_INFINITY;
   const allCandidates: Buildability["limitingComponents"] = [];
@@ -4,12 +4,15 @@ export interface Assembly {
   assembly_sku: SKU;
   name: string;
   uom: string; // "ea"
+  active?: boolean; // Defaults to true; inactive assemblies cannot be built
 }
 
 export interface Part {
   part_sku: SKU;
   name: string;
   uom: string; // "ea", "ft"
+  active?: boolean; // Defaults to true; inactive parts cannot be added to BOMs
+  category?: string | null; // Groups inventory valuation totals
 }
 
 export interface BomItem {
@@ -19,6 +22,9 @@ export interface BomItem {
   scrap_rate: number; // 0.0 to start
   yield_pct: number; // 1.0 to start
   is_phantom: boolean; // false for now
+  revision?: string; // Parent revision this line belongs to; absent for the base BOM
+  uom?: string; // Unit of qty_per; absent means the component's stock unit
+  substitute_group?: string; // Group in substitutes.csv this line may draw on
 }
 
 export interface StockRow {
@@ -38,6 +44,16 @@ export interface BuildHistoryRecord {
   quantity_built: number; // How many panels
   operator?: string; // Who built them (optional)
   notes?: string; // Any additional notes
+  bom_revision?: string; // Assembly revision effective when built
+  substitutions?: string; // Substitutes consumed, e.g. "B2 for B x4; ..."
+  labor_minutes?: number; // Actual labor for the whole build, when captured
+}
+
+// Labor booked to a routing operation when recording a build (build_labor.csv)
+export interface OperationLabor {
+  operation_seq: number;
+  assembly_sku?: SKU; // Sub-assembly whose routing has the operation; omit for the assembly built
+  minutes: number;
 }
 
 // New: Unified Inventory Item (combines parts and stock info)
@@ -50,15 +66,76 @@ export interface InventoryItem {
   available_qty: number; // Calculated: on_hand_qty - reserved_qty
   reorder_point?: number; // Optional: minimum stock level
   supplier?: string; // Optional: preferred supplier
+  on_order_qty: number; // Open purchase order quantity
+}
+
+// Purchasing: suppliers.csv and purchase_orders.csv
+export interface Supplier {
+  supplier_id: string;
+  name: string;
+  contact?: string;
+  email?: string;
+  phone?: string;
+  lead_time_days?: number;
+}
+
+export type PoLineStatus = "open" | "partial" | "received" | "cancelled";
+
+export interface PurchaseOrderLine {
+  po_number: string; // e.g., "PO-00012"
+  line_no: number;
+  supplier_id: string;
+  part_sku: SKU;
+  qty_ordered: number;
+  qty_received: number;
+  unit_cost: number;
+  order_date: string; // YYYY-MM-DD
+  expected_date: string; // YYYY-MM-DD
+  status: PoLineStatus;
+}
+
+export interface Shortage {
+  sku: SKU;
+  required_qty: number;
+  available_qty: number;
+  on_order_qty: number;
+  shortage_qty: number; // required - available - on order
+}
+
+export interface ShortageLine {
+  sku: SKU;
+  qty: number;
+  unit_cost?: number;
+}
+
+// Which files hold authoritative quantities: main_inventory.csv when present,
+// otherwise parts.csv + stock.csv
+export type InventorySource = "main_inventory" | "parts_stock";
+
+export interface InventoryDiscrepancy {
+  sku: SKU;
+  main_on_hand_qty?: number; // Missing when the SKU is not in main_inventory.csv
+  main_reserved_qty?: number;
+  stock_on_hand_qty?: number; // Missing when the SKU is not in stock.csv
+  stock_reserved_qty?: number;
+}
+
+export interface ReconciliationReport {
+  authoritative_source: InventorySource;
+  compared_skus: number;
+  discrepancies: InventoryDiscrepancy[];
 }
 
 export interface DataSnapshot {
   assemblies: Assembly[];
   parts: Part[];
-  bom_items: BomItem[];
+  bom_items: BomItem[]; // Lines effective on bom_date
+  bom_revisions: BomRevision[];
+  bom_date: string; // YYYY-MM-DD
   stock: StockRow[];
   build_history?: BuildHistoryRecord[]; // New: optional for backward compatibility
   inventory?: InventoryItem[]; // New: unified inventory view
+  warnings: string[]; // Rows left out because they could not be used
 }
 
 export type RequirementsPerUnit = Record<SKU, number>; // leaf part -> qty for 1 panel
@@ -72,3 +149,672 @@ export interface Buildability {
     candidateBuilds: number;
   }>;
 }
+
+// Inventory ledger (inventory_ledger.csv): every on-hand movement
+export type LedgerKind = "receipt" | "build" | "adjustment";
+
+export type AdjustmentReason = "damage" | "miscount" | "found";
+
+export interface LedgerEntry {
+  id: string;
+  timestamp: string; // ISO timestamp
+  sku: SKU;
+  qty_change: number; // Positive = stock in, negative = stock out
+  kind: LedgerKind;
+  reference?: string; // PO number, work order or count session
+  po_line?: number; // PO line a receipt was booked against
+  reason?: AdjustmentReason; // Set for adjustments
+  lot?: string;
+  operator?: string;
+  notes?: string;
+}
+
+export interface ReceiptRequest {
+  sku: SKU;
+  qty: number;
+  po_number?: string;
+  lot?: string;
+  received_by: string;
+  close_po_lines?: boolean; // Close touched PO lines even if short-shipped
+}
+
+export interface ReceiptResult {
+  entries: LedgerEntry[]; // One per PO line received against
+  on_hand_qty: number;
+  po_lines: PurchaseOrderLine[];
+}
+
+export interface AdjustmentRequest {
+  sku: SKU;
+  qty_change: number; // Positive adds stock, negative removes it
+  reason: AdjustmentReason;
+  operator: string;
+  notes?: string;
+}
+
+// Cycle counts (cycle_counts.csv): one line per SKU on a count sheet
+export type CycleCountStatus = "open" | "posted";
+
+export interface CycleCountLine {
+  session_id: string; // e.g., "CC-00003"
+  sku: SKU;
+  created_at: string; // ISO timestamp
+  counted_qty?: number;
+  system_qty?: number; // On hand when the count was entered
+  counted_by?: string;
+  reason?: AdjustmentReason;
+  posted_variance?: number; // Set once the session is posted
+  status: CycleCountStatus;
+}
+
+export interface CountEntry {
+  sku: SKU;
+  counted_qty: number;
+  reason?: AdjustmentReason;
+}
+
+export interface CountVariance {
+  sku: SKU;
+  on_hand_qty: number; // Current on hand
+  system_qty?: number; // On hand when counted
+  counted_qty?: number;
+  variance?: number; // counted - on hand when counted
+  reason?: AdjustmentReason;
+}
+
+// Reservations (reservations.csv): components held for a work order
+export type ReservationStatus = "active" | "released" | "consumed";
+
+export interface Reservation {
+  reservation_id: string;
+  work_order: string;
+  sales_order: string;
+  assembly_sku: SKU;
+  sku: SKU; // Reserved component
+  qty_reserved: number;
+  qty_consumed: number; // Drawn down by record_build for the same work order
+  qty_released: number;
+  created_at: string; // ISO timestamp
+  status: ReservationStatus;
+}
+
+// Work orders (work_orders.csv)
+export type WorkOrderStatus =
+  | "planned"
+  | "released"
+  | "in_progress"
+  | "completed"
+  | "closed";
+
+export interface WorkOrder {
+  work_order: string; // e.g., "WO#23898"
+  assembly_sku: SKU;
+  quantity: number;
+  quantity_completed: number;
+  due_date: string; // YYYY-MM-DD
+  customer: string;
+  sales_order?: string;
+  status: WorkOrderStatus;
+  created_at: string; // ISO timestamp
+  notes?: string;
+}
+
+export type WorkOrderRequest = Omit<
+  WorkOrder,
+  "quantity_completed" | "status" | "created_at"
+>;
+
+// record_build returns the refreshed snapshot plus the managed work order, if any
+export type BuildResult = DataSnapshot & { work_order?: WorkOrder };
+
+// Customer master (customers.csv) and sales orders (sales_orders.csv)
+export interface Customer {
+  customer_id: string; // Canonical ID recorded on builds, e.g., "BEACON"
+  name: string;
+  aliases?: string; // Other spellings separated by ";", e.g., "Beacon;beacon inc"
+  active: boolean;
+}
+
+export interface SalesOrderLine {
+  sales_order: string; // e.g., "SO#23709"
+  line_no: number;
+  customer_id: string;
+  assembly_sku: SKU;
+  qty_ordered: number;
+  order_date: string; // YYYY-MM-DD
+  due_date?: string; // YYYY-MM-DD
+}
+
+export interface SalesOrderLineRequest {
+  assembly_sku: SKU;
+  qty_ordered: number;
+  due_date?: string;
+}
+
+export interface FulfillmentLine {
+  sales_order: string;
+  line_no: number;
+  customer_id: string;
+  assembly_sku: SKU;
+  qty_ordered: number;
+  qty_shipped: number; // Built against this SO in panel_history.csv
+  qty_remaining: number;
+}
+
+// Operator roster (operators.csv)
+export interface Operator {
+  operator_id: string; // Canonical ID recorded on builds
+  name: string;
+  badge_number?: string;
+  active: boolean;
+}
+
+export type Period = "day" | "week" | "month"; // Weeks start on Monday
+
+export interface OperatorProduction {
+  operator: string; // Operator ID, or "(unassigned)"
+  period_start: string; // YYYY-MM-DD
+  units_built: number;
+  build_count: number;
+}
+
+// A panel_history.csv row left out of a report because it could not be read
+export interface SkippedRow {
+  line: number; // Line in the file, header is line 1
+  error: string;
+}
+
+export interface OperatorProductionReport {
+  rows: OperatorProduction[];
+  skipped_rows: SkippedRow[];
+}
+
+// Build history query (query_history)
+export type HistorySortField =
+  | "timestamp"
+  | "assembly_sku"
+  | "customer"
+  | "work_order"
+  | "sales_order"
+  | "operator"
+  | "quantity_built";
+
+export interface HistoryQuery {
+  from_date?: string; // YYYY-MM-DD, inclusive
+  to_date?: string; // YYYY-MM-DD, inclusive
+  assembly_sku?: SKU;
+  customer?: string; // Also matches customer names and aliases
+  work_order?: string;
+  sales_order?: string;
+  operator?: string;
+  sort_by?: HistorySortField; // Default: timestamp
+  ascending?: boolean; // Default: newest first
+  page?: number; // 1-based
+  page_size?: number; // Default 50, max 1000
+}
+
+export interface HistoryPage {
+  records: BuildHistoryRecord[];
+  total: number;
+  page: number;
+  page_size: number;
+  total_pages: number;
+  skipped_rows: SkippedRow[];
+}
+
+// Production analytics (production_analytics): chart-ready series
+export interface AssemblySeries {
+  assembly_sku: SKU;
+  units: number[]; // One value per entry in ProductionAnalytics.periods
+  rolling_average: number[]; // Trailing mean over rolling_window periods
+  total_units: number;
+  build_count: number;
+}
+
+export interface CustomerTotal {
+  customer: string;
+  units_built: number;
+  build_count: number;
+}
+
+export interface ProductionAnalytics {
+  period: Period;
+  rolling_window: number;
+  periods: string[]; // Bucket start dates (YYYY-MM-DD), without gaps
+  series: AssemblySeries[];
+  customer_totals: CustomerTotal[]; // Largest first
+  total_units: number;
+  skipped_rows: SkippedRow[];
+}
+
+// Component consumption history (consumption_history)
+export interface ComponentSeries {
+  sku: SKU;
+  quantities: number[]; // One value per entry in ConsumptionHistory.periods
+  total: number;
+}
+
+export interface ConsumptionHistory {
+  period: Period;
+  periods: string[]; // Bucket start dates (YYYY-MM-DD), without gaps
+  components: ComponentSeries[];
+  unexploded_assemblies: SKU[]; // Built assemblies with no BOM to explode
+  skipped_rows: SkippedRow[];
+  skipped_lines: string[]; // BOM lines left out because their unit cannot be converted
+}
+
+// Demand forecasting (forecast_demand)
+export type ForecastMethod = "moving_average" | "exponential_smoothing" | "seasonal_naive";
+
+export interface ForecastRequest {
+  period?: Period; // Default "week"
+  method?: ForecastMethod; // Default "moving_average"
+  horizon?: number; // Future periods, default 4
+  window?: number; // Moving average window, default 4
+  alpha?: number; // Exponential smoothing factor, default 0.3
+  season_length?: number; // Seasonal naive; defaults to a year of periods
+  default_lead_time_days?: number; // Used when the supplier has none, default 14
+  safety_z?: number; // Safety stock in standard deviations, default 1.65
+}
+
+export interface AssemblyForecast {
+  assembly_sku: SKU;
+  history: number[]; // One value per DemandForecast.history_periods
+  forecast: number[]; // One value per DemandForecast.forecast_periods
+}
+
+export interface ComponentForecast {
+  sku: SKU;
+  forecast: number[]; // Exploded leaf demand per forecast period
+  total: number;
+}
+
+export interface ReorderSuggestion {
+  sku: SKU;
+  daily_demand: number;
+  lead_time_days: number;
+  safety_stock: number;
+  suggested_reorder_point: number;
+  current_reorder_point?: number;
+}
+
+export interface DemandForecast {
+  period: Period;
+  method: ForecastMethod;
+  history_periods: string[];
+  forecast_periods: string[];
+  assemblies: AssemblyForecast[];
+  components: ComponentForecast[];
+  reorder_points: ReorderSuggestion[];
+  skipped_rows: SkippedRow[]; // History rows left out of the forecast
+  skipped_lines: string[]; // BOM lines left out of the consumption history
+}
+
+// Days of cover (inventory_coverage)
+export type CoverageSortField = "days_of_cover" | "stockout_date" | "daily_consumption" | "available_qty" | "sku";
+
+export interface InventoryCoverage extends InventoryItem {
+  daily_consumption: number; // Average over the lookback window
+  days_of_cover?: number; // available_qty / daily_consumption; absent when not consumed
+  stockout_date?: string; // Projected YYYY-MM-DD
+}
+
+export interface CoverageReport {
+  items: InventoryCoverage[];
+  skipped_rows: SkippedRow[];
+  skipped_lines: string[]; // BOM lines left out of the consumption
+}
+
+// BOM revisions (bom_revisions.csv)
+export type RevisionStatus = "draft" | "released" | "obsolete";
+
+export interface BomRevision {
+  assembly_sku: SKU;
+  revision: string; // e.g., "A", "B"
+  effective_from?: string; // Inclusive YYYY-MM-DD
+  effective_to?: string; // Exclusive YYYY-MM-DD
+  status: RevisionStatus;
+  notes?: string;
+}
+
+// Engineering change orders (ecos.csv, eco_changes.csv, eco_audit.csv)
+export type EcoStatus = "proposed" | "approved" | "rejected";
+export type EcoAction = "add" | "remove" | "change_qty";
+
+export interface EcoChangeRequest {
+  parent_assembly_sku: SKU;
+  action: EcoAction;
+  component_sku: SKU;
+  qty_per?: number; // Required for add and change_qty
+  scrap_rate?: number;
+  yield_pct?: number;
+  is_phantom?: boolean;
+}
+
+export interface EcoChange extends EcoChangeRequest {
+  eco_number: string;
+  line_no: number;
+}
+
+export interface EcoRequest {
+  title: string;
+  proposed_by: string;
+  effective_from?: string; // YYYY-MM-DD; today at approval when omitted
+  notes?: string;
+  changes: EcoChangeRequest[];
+}
+
+export interface Eco {
+  eco_number: string; // e.g., "ECO-00001"
+  title: string;
+  status: EcoStatus;
+  proposed_by: string;
+  proposed_at: string; // ISO timestamp
+  effective_from?: string;
+  decided_by?: string; // Approver or reviewer who rejected it
+  decided_at?: string;
+  new_revisions?: string; // "ASSEMBLY:REV" pairs, ';'-separated
+  notes?: string;
+}
+
+export interface EcoDetail extends Eco {
+  changes: EcoChange[];
+}
+
+export interface EcoAuditEntry {
+  eco_number: string;
+  timestamp: string;
+  action: EcoStatus;
+  user: string;
+  notes?: string;
+}
+
+export interface BuildabilityDelta {
+  assembly_sku: SKU;
+  before: number;
+  after: number;
+}
+
+export interface ObsoleteStock {
+  sku: SKU;
+  on_hand_qty: number;
+  dropped_from: SKU[]; // Assemblies that no longer use it
+}
+
+export interface EcoImpact {
+  eco_number: string;
+  as_of: string; // BOM date the ECO was evaluated against
+  changed_assemblies: SKU[];
+  affected_assemblies: SKU[]; // Changed assemblies and everything that uses them
+  buildability: BuildabilityDelta[];
+  obsolete_stock: ObsoleteStock[];
+}
+
+// Master data maintenance (rename_sku, sku_references)
+export interface SkuReference {
+  file: string; // e.g., "bom_items.csv"
+  rows: number;
+}
+
+export interface RenameReport {
+  old_sku: SKU;
+  new_sku: SKU;
+  files: SkuReference[]; // Files rewritten and rows changed in each
+}
+
+// Reverse BOM lookup (where_used)
+export interface WhereUsedPath {
+  path: SKU[]; // Top-level assembly first, traced SKU last
+  qty_per_unit: number; // Including scrap and yield along the path
+}
+
+export interface WhereUsed {
+  assembly_sku: SKU; // Top-level assembly
+  qty_per_unit: number; // Summed over all paths
+  paths: WhereUsedPath[];
+}
+
+// Indented BOM (bom_tree, export_bom_tree)
+export type ExportFormat = "csv" | "markdown";
+
+export interface BomTreeRow {
+  level: number; // 1 = direct component of the top assembly
+  parent_assembly_sku: SKU;
+  component_sku: SKU;
+  name: string;
+  qty_per: number;
+  scrap_rate: number;
+  yield_pct: number;
+  is_phantom: boolean;
+  extended_qty: number; // Cumulative for the requested quantity, incl. scrap and yield
+}
+
+// BOM comparison (compare_bom)
+export interface BomSide {
+  assembly_sku: SKU;
+  revision?: string | null; // Pin to a revision (drafts allowed)
+  as_of?: string | null; // YYYY-MM-DD, today when omitted
+}
+
+export type DiffKind = "added" | "removed" | "changed";
+
+export interface LeafDiff {
+  sku: SKU;
+  kind: DiffKind;
+  qty_a: number; // Exploded qty per unit, 0 when absent
+  qty_b: number;
+}
+
+export interface LineValues {
+  qty_per: number;
+  scrap_rate: number;
+  yield_pct: number;
+  is_phantom: boolean;
+}
+
+export interface StructureDiff {
+  path: SKU[]; // Components below the top assembly, line SKU last
+  kind: DiffKind;
+  a: LineValues | null;
+  b: LineValues | null;
+}
+
+export interface BomComparison {
+  a: BomSide; // Resolved side: as_of and revision filled in
+  b: BomSide;
+  leaf: LeafDiff[];
+  structure: StructureDiff[];
+  unchanged_leaf_count: number;
+}
+
+// Alternate components (substitutes.csv)
+export interface Substitute {
+  primary_sku: SKU;
+  substitute_sku: SKU;
+  priority: number; // 1 is drawn first
+  conversion_ratio: number; // Substitute units replacing one primary unit
+  assembly_sku?: SKU | null; // Only for builds of this assembly
+  notes?: string | null;
+  group?: string | null; // Named group BOM lines opt into; absent for the default group
+}
+
+export interface Substitution {
+  primary_sku: SKU;
+  substitute_sku: SKU;
+  primary_qty: number; // Primary quantity covered
+  substitute_qty: number; // Substitute quantity consumed
+}
+
+export interface SubstituteBuildability {
+  assembly_sku: SKU;
+  primary_only: number;
+  with_substitutes: number;
+  substitutions: Substitution[]; // Used to build with_substitutes units
+}
+
+// Unit of measure conversions (uom_conversions.csv)
+export interface UomConversion {
+  from_uom: string;
+  to_uom: string;
+  factor: number; // One from_uom is this many to_uom
+  sku?: SKU | null; // Part-specific; absent for a global conversion
+}
+
+// Costing (part_costs.csv, cost_rollup, export_build_history)
+export type CostMethod = "standard" | "average"; // Each falls back to the other
+export type CostSource = "standard" | "average" | "missing";
+
+export interface StandardCost {
+  sku: SKU;
+  standard_cost: number; // Per stock unit
+}
+
+export interface PartCost {
+  sku: SKU;
+  standard_cost: number | null;
+  average_cost: number | null; // Weighted by qty received on purchase orders
+  received_qty: number;
+}
+
+export interface CostLine {
+  sku: SKU;
+  qty_per_unit: number; // Including scrap and yield
+  unit_cost: number | null;
+  extended_cost: number;
+  source: CostSource;
+}
+
+export interface CostRollup {
+  assembly_sku: SKU;
+  method: CostMethod;
+  bom_date: string;
+  material_cost: number; // Per unit, excluding parts without a cost
+  lines: CostLine[];
+  missing_costs: SKU[];
+}
+
+// Inventory valuation (inventory_valuation, export_inventory_valuation)
+export type ValuationMethod = "fifo" | "weighted_average" | "standard";
+
+export interface ValuationLine {
+  sku: SKU;
+  name: string;
+  category: string | null;
+  supplier: string | null; // From main_inventory.csv, else the latest PO
+  on_hand_qty: number; // As of the valuation date
+  unit_cost: number | null;
+  value: number | null; // Null when no price or standard cost is known
+}
+
+export interface ValuationTotal {
+  key: string; // Category or supplier
+  sku_count: number;
+  on_hand_qty: number;
+  value: number;
+}
+
+export interface InventoryValuation {
+  as_of: string;
+  method: ValuationMethod;
+  total_value: number;
+  lines: ValuationLine[];
+  by_category: ValuationTotal[];
+  by_supplier: ValuationTotal[];
+  unvalued_skus: SKU[];
+}
+
+// Routings (routings.csv, work_centers.csv) and labor
+export interface WorkCenter {
+  work_center: string;
+  name: string;
+  hours_per_day: number;
+}
+
+export interface RoutingOperation {
+  assembly_sku: SKU;
+  operation_seq: number;
+  operation: string;
+  work_center: string;
+  setup_minutes: number; // Once per build
+  run_minutes: number; // Per unit
+}
+
+export interface OperationVariance {
+  assembly_sku: SKU; // Assembly whose routing has the operation
+  operation_seq: number;
+  operation: string | null; // Null when no longer in the routing
+  work_center: string | null;
+  standard_minutes: number | null;
+  actual_minutes: number | null; // Null when no labor was booked to it
+  variance_minutes: number | null;
+}
+
+export interface LaborVariance {
+  id: string;
+  timestamp: string;
+  work_order: string;
+  assembly_sku: SKU;
+  quantity_built: number;
+  operator: string | null;
+  standard_minutes: number | null; // Null without a routing
+  actual_minutes: number | null; // Null when no labor was recorded
+  variance_minutes: number | null; // Actual - standard
+  efficiency_pct: number | null; // Standard / actual * 100
+  operations: OperationVariance[]; // Empty unless labor was booked to operations
+}
+
+export interface LaborSummary {
+  assembly_sku: SKU;
+  builds: number; // Builds with both standard and actual labor
+  quantity_built: number;
+  standard_minutes: number;
+  actual_minutes: number;
+  efficiency_pct: number | null;
+}
+
+export interface OperationSummary {
+  assembly_sku: SKU;
+  operation_seq: number;
+  operation: string;
+  work_center: string;
+  builds: number; // Builds with labor booked to the operation
+  standard_minutes: number;
+  actual_minutes: number;
+  variance_minutes: number;
+  efficiency_pct: number | null;
+}
+
+export interface WorkCenterSummary {
+  work_center: string;
+  builds: number;
+  standard_minutes: number;
+  actual_minutes: number;
+  variance_minutes: number;
+  efficiency_pct: number | null;
+}
+
+export interface LaborReport {
+  builds: LaborVariance[];
+  by_assembly: LaborSummary[];
+  by_operation: OperationSummary[];
+  by_work_center: WorkCenterSummary[];
+  skipped_rows: SkippedRow[];
+}
+
+export interface WorkCenterLoad {
+  work_center: string;
+  available_minutes: number | null; // Null when not in work_centers.csv
+  setup_minutes: number;
+  run_minutes_per_unit: number;
+  max_units: number | null;
+}
+
+export interface CapacityBuildability {
+  assembly_sku: SKU;
+  days: number;
+  material_limited: number;
+  capacity_limited: number | null; // Null without a routing
+  buildable: number;
+  limited_by: string; // "material" or the bottleneck work center
+  work_centers: WorkCenterLoad[];
+}
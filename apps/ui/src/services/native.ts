@@ -3,10 +3,73 @@ import type {
   DataSnapshot,
   BuildHistoryRecord,
   InventoryItem,
+  ReconciliationReport,
+  Supplier,
+  PurchaseOrderLine,
+  Shortage,
+  ShortageLine,
+  LedgerEntry,
+  ReceiptRequest,
+  ReceiptResult,
+  AdjustmentRequest,
+  AdjustmentReason,
+  CycleCountLine,
+  CountEntry,
+  CountVariance,
+  Reservation,
+  WorkOrder,
+  WorkOrderRequest,
+  WorkOrderStatus,
+  BuildResult,
+  Customer,
+  SalesOrderLine,
+  SalesOrderLineRequest,
+  FulfillmentLine,
+  Operator,
+  OperatorProductionReport,
+  Period,
+  HistoryQuery,
+  HistoryPage,
+  ProductionAnalytics,
+  ConsumptionHistory,
+  ForecastRequest,
+  DemandForecast,
+  CoverageSortField,
+  CoverageReport,
+  BomRevision,
+  RequirementsPerUnit,
+  EcoDetail,
+  EcoAuditEntry,
+  EcoRequest,
+  EcoImpact,
+  BomItem,
+  Assembly,
+  Part,
+  SkuReference,
+  RenameReport,
+  WhereUsed,
+  BomTreeRow,
+  ExportFormat,
+  BomSide,
+  BomComparison,
+  Substitute,
+  SubstituteBuildability,
+  UomConversion,
+  CostMethod,
+  StandardCost,
+  PartCost,
+  CostRollup,
+  ValuationMethod,
+  InventoryValuation,
+  WorkCenter,
+  RoutingOperation,
+  LaborReport,
+  OperationLabor,
+  CapacityBuildability,
 } from "../domain/types";
 
-export async function loadData(dataDir: string): Promise<DataSnapshot> {
-  return await invoke<DataSnapshot>("load_data", { dataDir });
+export async function loadData(dataDir: string, asOf?: string): Promise<DataSnapshot> {
+  return await invoke<DataSnapshot>("load_data", { dataDir, asOf: asOf || null });
 }
 
 export async function loadMainInventory(
@@ -15,11 +78,18 @@ export async function loadMainInventory(
   return await invoke<InventoryItem[]>("load_main_inventory", { dataDir });
 }
 
+export async function reconcileInventory(
+  dataDir: string
+): Promise<ReconciliationReport> {
+  return await invoke<ReconciliationReport>("reconcile_inventory", { dataDir });
+}
+
 export async function recordBuild(
   dataDir: string,
-  buildRecord: Omit<BuildHistoryRecord, "id" | "timestamp">
-): Promise<DataSnapshot> {
-  return await invoke<DataSnapshot>("record_build", {
+  buildRecord: Omit<BuildHistoryRecord, "id" | "timestamp">,
+  labor?: OperationLabor[]
+): Promise<BuildResult> {
+  return await invoke<BuildResult>("record_build", {
     dataDir,
     workOrder: buildRecord.work_order,
     salesOrder: buildRecord.sales_order,
@@ -28,6 +98,8 @@ export async function recordBuild(
     quantityBuilt: buildRecord.quantity_built,
     operator: buildRecord.operator || null,
     notes: buildRecord.notes || null,
+    laborMinutes: buildRecord.labor_minutes ?? null,
+    labor: labor ?? null,
   });
 }
 
@@ -38,3 +110,673 @@ export async function loadPanelHistory(
     dataDir,
   });
 }
+
+export async function loadSuppliers(dataDir: string): Promise<Supplier[]> {
+  return await invoke<Supplier[]>("load_suppliers", { dataDir });
+}
+
+export async function saveSupplier(
+  dataDir: string,
+  supplier: Supplier
+): Promise<Supplier[]> {
+  return await invoke<Supplier[]>("save_supplier", { dataDir, supplier });
+}
+
+export async function loadPurchaseOrders(
+  dataDir: string
+): Promise<PurchaseOrderLine[]> {
+  return await invoke<PurchaseOrderLine[]>("load_purchase_orders", { dataDir });
+}
+
+export async function loadOnOrder(
+  dataDir: string,
+  byDate?: string
+): Promise<Record<string, number>> {
+  return await invoke<Record<string, number>>("load_on_order", {
+    dataDir,
+    byDate: byDate || null,
+  });
+}
+
+export async function shortageReport(
+  dataDir: string,
+  assemblySku: string,
+  quantity: number
+): Promise<Shortage[]> {
+  return await invoke<Shortage[]>("shortage_report", {
+    dataDir,
+    assemblySku,
+    quantity,
+  });
+}
+
+export async function createPurchaseOrder(
+  dataDir: string,
+  supplierId: string,
+  expectedDate: string,
+  lines: ShortageLine[]
+): Promise<PurchaseOrderLine[]> {
+  return await invoke<PurchaseOrderLine[]>("create_purchase_order", {
+    dataDir,
+    supplierId,
+    expectedDate,
+    lines,
+  });
+}
+
+export async function receiveStock(
+  dataDir: string,
+  receipt: ReceiptRequest
+): Promise<ReceiptResult> {
+  return await invoke<ReceiptResult>("receive_stock", { dataDir, receipt });
+}
+
+export async function loadInventoryLedger(
+  dataDir: string
+): Promise<LedgerEntry[]> {
+  return await invoke<LedgerEntry[]>("load_inventory_ledger", { dataDir });
+}
+
+export async function adjustStock(
+  dataDir: string,
+  adjustment: AdjustmentRequest
+): Promise<LedgerEntry> {
+  return await invoke<LedgerEntry>("adjust_stock", { dataDir, adjustment });
+}
+
+export async function loadCycleCounts(
+  dataDir: string
+): Promise<CycleCountLine[]> {
+  return await invoke<CycleCountLine[]>("load_cycle_counts", { dataDir });
+}
+
+export async function startCycleCount(
+  dataDir: string,
+  skus: string[]
+): Promise<CycleCountLine[]> {
+  return await invoke<CycleCountLine[]>("start_cycle_count", { dataDir, skus });
+}
+
+export async function enterCycleCounts(
+  dataDir: string,
+  sessionId: string,
+  counts: CountEntry[],
+  countedBy: string
+): Promise<CountVariance[]> {
+  return await invoke<CountVariance[]>("enter_cycle_counts", {
+    dataDir,
+    sessionId,
+    counts,
+    countedBy,
+  });
+}
+
+export async function cycleCountVariances(
+  dataDir: string,
+  sessionId: string
+): Promise<CountVariance[]> {
+  return await invoke<CountVariance[]>("cycle_count_variances", {
+    dataDir,
+    sessionId,
+  });
+}
+
+export async function postCycleCount(
+  dataDir: string,
+  sessionId: string,
+  defaultReason: AdjustmentReason,
+  operator: string
+): Promise<LedgerEntry[]> {
+  return await invoke<LedgerEntry[]>("post_cycle_count", {
+    dataDir,
+    sessionId,
+    defaultReason,
+    operator,
+  });
+}
+
+export async function loadReservations(dataDir: string): Promise<Reservation[]> {
+  return await invoke<Reservation[]>("load_reservations", { dataDir });
+}
+
+export async function reserveForOrder(
+  dataDir: string,
+  workOrder: string,
+  salesOrder: string,
+  assemblySku: string,
+  quantity: number
+): Promise<Reservation[]> {
+  return await invoke<Reservation[]>("reserve_for_order", {
+    dataDir,
+    workOrder,
+    salesOrder,
+    assemblySku,
+    quantity,
+  });
+}
+
+export async function releaseReservations(
+  dataDir: string,
+  workOrder: string,
+  sku?: string
+): Promise<Reservation[]> {
+  return await invoke<Reservation[]>("release_reservations", {
+    dataDir,
+    workOrder,
+    sku: sku || null,
+  });
+}
+
+export async function loadWorkOrders(dataDir: string): Promise<WorkOrder[]> {
+  return await invoke<WorkOrder[]>("load_work_orders", { dataDir });
+}
+
+export async function createWorkOrder(
+  dataDir: string,
+  workOrder: WorkOrderRequest
+): Promise<WorkOrder> {
+  return await invoke<WorkOrder>("create_work_order", { dataDir, workOrder });
+}
+
+export async function transitionWorkOrder(
+  dataDir: string,
+  workOrder: string,
+  status: WorkOrderStatus
+): Promise<WorkOrder> {
+  return await invoke<WorkOrder>("transition_work_order", {
+    dataDir,
+    workOrder,
+    status,
+  });
+}
+
+export async function loadCustomers(dataDir: string): Promise<Customer[]> {
+  return await invoke<Customer[]>("load_customers", { dataDir });
+}
+
+export async function saveCustomer(
+  dataDir: string,
+  customer: Customer
+): Promise<Customer[]> {
+  return await invoke<Customer[]>("save_customer", { dataDir, customer });
+}
+
+export async function loadSalesOrders(
+  dataDir: string
+): Promise<SalesOrderLine[]> {
+  return await invoke<SalesOrderLine[]>("load_sales_orders", { dataDir });
+}
+
+export async function createSalesOrder(
+  dataDir: string,
+  salesOrder: string,
+  customer: string,
+  lines: SalesOrderLineRequest[]
+): Promise<SalesOrderLine[]> {
+  return await invoke<SalesOrderLine[]>("create_sales_order", {
+    dataDir,
+    salesOrder,
+    customer,
+    lines,
+  });
+}
+
+export async function salesOrderFulfillment(
+  dataDir: string,
+  salesOrder?: string
+): Promise<FulfillmentLine[]> {
+  return await invoke<FulfillmentLine[]>("sales_order_fulfillment", {
+    dataDir,
+    salesOrder: salesOrder || null,
+  });
+}
+
+export async function loadOperators(dataDir: string): Promise<Operator[]> {
+  return await invoke<Operator[]>("load_operators", { dataDir });
+}
+
+export async function saveOperator(
+  dataDir: string,
+  operator: Operator
+): Promise<Operator[]> {
+  return await invoke<Operator[]>("save_operator", { dataDir, operator });
+}
+
+export async function operatorProduction(
+  dataDir: string,
+  period: Period
+): Promise<OperatorProductionReport> {
+  return await invoke<OperatorProductionReport>("operator_production", {
+    dataDir,
+    period,
+  });
+}
+
+export async function queryHistory(
+  dataDir: string,
+  query: HistoryQuery
+): Promise<HistoryPage> {
+  return await invoke<HistoryPage>("query_history", { dataDir, query });
+}
+
+export async function productionAnalytics(
+  dataDir: string,
+  period: Period,
+  options: { fromDate?: string; toDate?: string; rollingWindow?: number } = {}
+): Promise<ProductionAnalytics> {
+  return await invoke<ProductionAnalytics>("production_analytics", {
+    dataDir,
+    period,
+    fromDate: options.fromDate || null,
+    toDate: options.toDate || null,
+    rollingWindow: options.rollingWindow ?? null,
+  });
+}
+
+export async function consumptionHistory(
+  dataDir: string,
+  period: Period,
+  options: { fromDate?: string; toDate?: string; sku?: string } = {}
+): Promise<ConsumptionHistory> {
+  return await invoke<ConsumptionHistory>("consumption_history", {
+    dataDir,
+    period,
+    fromDate: options.fromDate || null,
+    toDate: options.toDate || null,
+    sku: options.sku || null,
+  });
+}
+
+export async function forecastDemand(
+  dataDir: string,
+  request: ForecastRequest = {}
+): Promise<DemandForecast> {
+  return await invoke<DemandForecast>("forecast_demand", { dataDir, request });
+}
+
+export async function inventoryCoverage(
+  dataDir: string,
+  options: { lookbackDays?: number; sortBy?: CoverageSortField; descending?: boolean } = {}
+): Promise<CoverageReport> {
+  return await invoke<CoverageReport>("inventory_coverage", {
+    dataDir,
+    lookbackDays: options.lookbackDays ?? null,
+    sortBy: options.sortBy || null,
+    descending: options.descending ?? null,
+  });
+}
+
+export async function loadBomRevisions(dataDir: string): Promise<BomRevision[]> {
+  return await invoke<BomRevision[]>("load_bom_revisions", { dataDir });
+}
+
+export async function saveBomRevision(
+  dataDir: string,
+  revision: BomRevision
+): Promise<BomRevision[]> {
+  return await invoke<BomRevision[]>("save_bom_revision", { dataDir, revision });
+}
+
+export async function releaseBomRevision(
+  dataDir: string,
+  assemblySku: string,
+  revision: string,
+  effectiveFrom?: string
+): Promise<BomRevision[]> {
+  return await invoke<BomRevision[]>("release_bom_revision", {
+    dataDir,
+    assemblySku,
+    revision,
+    effectiveFrom: effectiveFrom || null,
+  });
+}
+
+export async function explodeAssembly(
+  dataDir: string,
+  assemblySku: string,
+  asOf?: string
+): Promise<RequirementsPerUnit> {
+  return await invoke<RequirementsPerUnit>("explode_assembly", {
+    dataDir,
+    assemblySku,
+    asOf: asOf || null,
+  });
+}
+
+export async function loadEcos(dataDir: string): Promise<EcoDetail[]> {
+  return await invoke<EcoDetail[]>("load_ecos", { dataDir });
+}
+
+export async function loadEcoAudit(dataDir: string): Promise<EcoAuditEntry[]> {
+  return await invoke<EcoAuditEntry[]>("load_eco_audit", { dataDir });
+}
+
+export async function proposeEco(dataDir: string, request: EcoRequest): Promise<EcoDetail> {
+  return await invoke<EcoDetail>("propose_eco", { dataDir, request });
+}
+
+export async function previewEco(dataDir: string, ecoNumber: string): Promise<EcoImpact> {
+  return await invoke<EcoImpact>("preview_eco", { dataDir, ecoNumber });
+}
+
+export async function approveEco(
+  dataDir: string,
+  ecoNumber: string,
+  approvedBy: string,
+  effectiveFrom?: string
+): Promise<EcoDetail> {
+  return await invoke<EcoDetail>("approve_eco", {
+    dataDir,
+    ecoNumber,
+    approvedBy,
+    effectiveFrom: effectiveFrom || null,
+  });
+}
+
+export async function rejectEco(
+  dataDir: string,
+  ecoNumber: string,
+  rejectedBy: string,
+  reason?: string
+): Promise<EcoDetail> {
+  return await invoke<EcoDetail>("reject_eco", {
+    dataDir,
+    ecoNumber,
+    rejectedBy,
+    reason: reason || null,
+  });
+}
+
+export async function addBomItem(dataDir: string, item: BomItem): Promise<BomItem[]> {
+  return await invoke<BomItem[]>("add_bom_item", { dataDir, item });
+}
+
+export async function updateBomItem(dataDir: string, item: BomItem): Promise<BomItem[]> {
+  return await invoke<BomItem[]>("update_bom_item", { dataDir, item });
+}
+
+export async function removeBomItem(
+  dataDir: string,
+  parentAssemblySku: string,
+  componentSku: string,
+  revision?: string
+): Promise<BomItem[]> {
+  return await invoke<BomItem[]>("remove_bom_item", {
+    dataDir,
+    parentAssemblySku,
+    componentSku,
+    revision: revision || null,
+  });
+}
+
+export async function createAssembly(dataDir: string, assembly: Assembly): Promise<Assembly[]> {
+  return await invoke<Assembly[]>("create_assembly", { dataDir, assembly });
+}
+
+export async function createPart(dataDir: string, part: Part): Promise<Part[]> {
+  return await invoke<Part[]>("create_part", { dataDir, part });
+}
+
+export async function updatePart(dataDir: string, part: Part): Promise<Part[]> {
+  return await invoke<Part[]>("update_part", { dataDir, part });
+}
+
+export async function deletePart(dataDir: string, partSku: string): Promise<Part[]> {
+  return await invoke<Part[]>("delete_part", { dataDir, partSku });
+}
+
+export async function updateAssembly(dataDir: string, assembly: Assembly): Promise<Assembly[]> {
+  return await invoke<Assembly[]>("update_assembly", { dataDir, assembly });
+}
+
+export async function deleteAssembly(dataDir: string, assemblySku: string): Promise<Assembly[]> {
+  return await invoke<Assembly[]>("delete_assembly", { dataDir, assemblySku });
+}
+
+export async function setSkuActive(dataDir: string, sku: string, active: boolean): Promise<void> {
+  return await invoke<void>("set_sku_active", { dataDir, sku, active });
+}
+
+export async function skuReferences(dataDir: string, sku: string): Promise<SkuReference[]> {
+  return await invoke<SkuReference[]>("sku_references", { dataDir, sku });
+}
+
+export async function renameSku(
+  dataDir: string,
+  oldSku: string,
+  newSku: string
+): Promise<RenameReport> {
+  return await invoke<RenameReport>("rename_sku", { dataDir, oldSku, newSku });
+}
+
+export async function whereUsed(
+  dataDir: string,
+  sku: string,
+  asOf?: string
+): Promise<WhereUsed[]> {
+  return await invoke<WhereUsed[]>("where_used", { dataDir, sku, asOf: asOf || null });
+}
+
+export async function bomTree(
+  dataDir: string,
+  assemblySku: string,
+  options: { quantity?: number; asOf?: string } = {}
+): Promise<BomTreeRow[]> {
+  return await invoke<BomTreeRow[]>("bom_tree", {
+    dataDir,
+    assemblySku,
+    quantity: options.quantity ?? null,
+    asOf: options.asOf || null,
+  });
+}
+
+// Returns the exported text; also written to outputPath when given
+export async function exportBomTree(
+  dataDir: string,
+  assemblySku: string,
+  format: ExportFormat,
+  options: { quantity?: number; asOf?: string; outputPath?: string } = {}
+): Promise<string> {
+  return await invoke<string>("export_bom_tree", {
+    dataDir,
+    assemblySku,
+    format,
+    quantity: options.quantity ?? null,
+    asOf: options.asOf || null,
+    outputPath: options.outputPath || null,
+  });
+}
+
+export async function compareBom(
+  dataDir: string,
+  a: BomSide,
+  b: BomSide
+): Promise<BomComparison> {
+  return await invoke<BomComparison>("compare_bom", { dataDir, a, b });
+}
+
+export async function loadSubstitutes(dataDir: string): Promise<Substitute[]> {
+  return await invoke<Substitute[]>("load_substitutes", { dataDir });
+}
+
+export async function saveSubstitute(
+  dataDir: string,
+  substitute: Substitute
+): Promise<Substitute[]> {
+  return await invoke<Substitute[]>("save_substitute", { dataDir, substitute });
+}
+
+export async function removeSubstitute(
+  dataDir: string,
+  primarySku: string,
+  substituteSku: string,
+  assemblySku?: string,
+  group?: string
+): Promise<Substitute[]> {
+  return await invoke<Substitute[]>("remove_substitute", {
+    dataDir,
+    primarySku,
+    substituteSku,
+    assemblySku: assemblySku || null,
+    group: group || null,
+  });
+}
+
+export async function buildableWithSubstitutes(
+  dataDir: string,
+  assemblySku: string,
+  asOf?: string
+): Promise<SubstituteBuildability> {
+  return await invoke<SubstituteBuildability>("buildable_with_substitutes", {
+    dataDir,
+    assemblySku,
+    asOf: asOf || null,
+  });
+}
+
+export async function loadUomConversions(dataDir: string): Promise<UomConversion[]> {
+  return await invoke<UomConversion[]>("load_uom_conversions", { dataDir });
+}
+
+export async function saveUomConversion(
+  dataDir: string,
+  conversion: UomConversion
+): Promise<UomConversion[]> {
+  return await invoke<UomConversion[]>("save_uom_conversion", { dataDir, conversion });
+}
+
+export async function removeUomConversion(
+  dataDir: string,
+  fromUom: string,
+  toUom: string,
+  sku?: string
+): Promise<UomConversion[]> {
+  return await invoke<UomConversion[]>("remove_uom_conversion", {
+    dataDir,
+    fromUom,
+    toUom,
+    sku: sku || null,
+  });
+}
+
+export async function partCosts(dataDir: string): Promise<PartCost[]> {
+  return await invoke<PartCost[]>("part_costs", { dataDir });
+}
+
+// Merges a sku,standard_cost CSV into part_costs.csv
+export async function importStandardCosts(
+  dataDir: string,
+  sourcePath: string
+): Promise<StandardCost[]> {
+  return await invoke<StandardCost[]>("import_standard_costs", { dataDir, sourcePath });
+}
+
+export async function costRollup(
+  dataDir: string,
+  assemblySku: string,
+  options: { method?: CostMethod; asOf?: string } = {}
+): Promise<CostRollup> {
+  return await invoke<CostRollup>("cost_rollup", {
+    dataDir,
+    assemblySku,
+    method: options.method ?? null,
+    asOf: options.asOf || null,
+  });
+}
+
+// Paging in the query is ignored; every matching build is exported
+export async function exportBuildHistory(
+  dataDir: string,
+  query: HistoryQuery,
+  format: ExportFormat,
+  options: { method?: CostMethod; outputPath?: string } = {}
+): Promise<string> {
+  return await invoke<string>("export_build_history", {
+    dataDir,
+    query,
+    format,
+    method: options.method ?? null,
+    outputPath: options.outputPath || null,
+  });
+}
+
+export async function inventoryValuation(
+  dataDir: string,
+  options: { method?: ValuationMethod; asOf?: string } = {}
+): Promise<InventoryValuation> {
+  return await invoke<InventoryValuation>("inventory_valuation", {
+    dataDir,
+    method: options.method ?? null,
+    asOf: options.asOf || null,
+  });
+}
+
+// Returns the exported text; also written to outputPath when given
+export async function exportInventoryValuation(
+  dataDir: string,
+  format: ExportFormat,
+  options: { method?: ValuationMethod; asOf?: string; outputPath?: string } = {}
+): Promise<string> {
+  return await invoke<string>("export_inventory_valuation", {
+    dataDir,
+    format,
+    method: options.method ?? null,
+    asOf: options.asOf || null,
+    outputPath: options.outputPath || null,
+  });
+}
+
+export async function loadWorkCenters(dataDir: string): Promise<WorkCenter[]> {
+  return await invoke<WorkCenter[]>("load_work_centers", { dataDir });
+}
+
+export async function saveWorkCenter(
+  dataDir: string,
+  workCenter: WorkCenter
+): Promise<WorkCenter[]> {
+  return await invoke<WorkCenter[]>("save_work_center", { dataDir, workCenter });
+}
+
+export async function loadRoutings(dataDir: string): Promise<RoutingOperation[]> {
+  return await invoke<RoutingOperation[]>("load_routings", { dataDir });
+}
+
+export async function saveRoutingOperation(
+  dataDir: string,
+  operation: RoutingOperation
+): Promise<RoutingOperation[]> {
+  return await invoke<RoutingOperation[]>("save_routing_operation", { dataDir, operation });
+}
+
+export async function removeRoutingOperation(
+  dataDir: string,
+  assemblySku: string,
+  operationSeq: number
+): Promise<RoutingOperation[]> {
+  return await invoke<RoutingOperation[]>("remove_routing_operation", {
+    dataDir,
+    assemblySku,
+    operationSeq,
+  });
+}
+
+// Paging in the query is ignored; every matching build is reported
+export async function laborReport(
+  dataDir: string,
+  query: HistoryQuery
+): Promise<LaborReport> {
+  return await invoke<LaborReport>("labor_report", { dataDir, query });
+}
+
+export async function capacityBuildable(
+  dataDir: string,
+  assemblySku: string,
+  options: { days?: number; asOf?: string } = {}
+): Promise<CapacityBuildability> {
+  return await invoke<CapacityBuildability>("capacity_buildable", {
+    dataDir,
+    assemblySku,
+    days: options.days ?? null,
+    asOf: options.asOf || null,
+  });
+}